@@ -0,0 +1,18 @@
+//! Stub HTTP servers for the chains and relayers that tests script as axum
+//! routers.
+use std::net::{SocketAddr, TcpListener};
+
+use axum::Router;
+
+/// Serves `router` on a free local port, on the current tokio runtime.
+pub fn serve(router: Router) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router.into_make_service()),
+    );
+
+    addr
+}
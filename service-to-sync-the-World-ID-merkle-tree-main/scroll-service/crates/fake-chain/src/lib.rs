@@ -0,0 +1,338 @@
+//! An in-process stand-in for the chains and the relayer the bridge service
+//! talks to, scripted by tests.
+//!
+//! The L1 `WorldId` root changes when the test says so. A propagation sent
+//! through the relayer is mined after the mining delay and its root reaches
+//! L2 after the delivery delay, unless the test scripted another outcome.
+//! Time is tokio's, so tests with paused time step through it
+//! deterministically.
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use ethers::types::U256;
+use ethers::utils::parse_units;
+use tokio::time::{self, Instant};
+
+pub mod http;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FakeError {
+    #[error("relayer unavailable: {0}")]
+    Unavailable(String),
+    #[error("relayer rejected transaction: {0}")]
+    Rejected(String),
+    #[error("unknown transaction {0}")]
+    UnknownTransaction(String),
+}
+
+/// What happens to a propagation once the relayer has sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Outcome {
+    /// Mined, and the root is delivered to L2
+    #[default]
+    Delivered,
+    /// Mined, but the message fails on L2 so the root never arrives
+    MessageFailed,
+    /// Reverted on L1
+    Reverted,
+}
+
+/// A propagation transaction sent through the relayer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Propagation {
+    pub id:              String,
+    pub idempotency_key: String,
+    /// The L1 root when the transaction was sent
+    pub root:            U256,
+    pub outcome:         Outcome,
+    pub mined_at:        Instant,
+    pub delivered_at:    Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Receipt {
+    pub succeeded: bool,
+}
+
+#[derive(Debug)]
+pub struct FakeChain {
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    genesis_root:        U256,
+    /// L1 roots by the time they were set, oldest first
+    l1_roots:            Vec<(Instant, U256)>,
+    propagations:        Vec<Propagation>,
+    mining_delay:        Duration,
+    delivery_delay:      Duration,
+    send_faults:         VecDeque<FakeError>,
+    outcomes:            VecDeque<Outcome>,
+    gas_price:           U256,
+    base_fee:            U256,
+    relayer_balance:     U256,
+    root_history_expiry: Duration,
+}
+
+impl FakeChain {
+    /// Both chains start at `genesis_root`. Propagations are mined after
+    /// 12 seconds and delivered to L2 a minute later.
+    #[must_use]
+    pub fn new(genesis_root: U256) -> Self {
+        let gwei: U256 = parse_units(1, "gwei").unwrap().into();
+        let ether: U256 = parse_units(1, "ether").unwrap().into();
+
+        Self {
+            state: Mutex::new(State {
+                genesis_root,
+                l1_roots: Vec::new(),
+                propagations: Vec::new(),
+                mining_delay: Duration::from_secs(12),
+                delivery_delay: Duration::from_secs(60),
+                send_faults: VecDeque::new(),
+                outcomes: VecDeque::new(),
+                gas_price: gwei,
+                base_fee: gwei,
+                relayer_balance: ether,
+                root_history_expiry: Duration::from_secs(7 * 24 * 60 * 60),
+            }),
+        }
+    }
+
+    /// Inserts `root` into the L1 `WorldId` now.
+    pub fn set_l1_root(&self, root: U256) {
+        self.schedule_l1_root(Duration::ZERO, root);
+    }
+
+    /// Inserts `root` into the L1 `WorldId` once `after` has passed.
+    pub fn schedule_l1_root(&self, after: Duration, root: U256) {
+        let mut state = self.state();
+        state.l1_roots.push((Instant::now() + after, root));
+        state.l1_roots.sort_by_key(|(at, _)| *at);
+    }
+
+    pub fn set_mining_delay(&self, delay: Duration) {
+        self.state().mining_delay = delay;
+    }
+
+    /// How long a mined propagation takes to reach L2.
+    pub fn set_delivery_delay(&self, delay: Duration) {
+        self.state().delivery_delay = delay;
+    }
+
+    /// Fails the next send through the relayer with `err`.
+    pub fn fail_next_send(&self, err: FakeError) {
+        self.state().send_faults.push_back(err);
+    }
+
+    /// Scripts the outcome of the next propagation sent, the ones after it
+    /// are delivered.
+    pub fn next_outcome(&self, outcome: Outcome) {
+        self.state().outcomes.push_back(outcome);
+    }
+
+    pub fn set_gas_price(&self, gas_price: U256) {
+        self.state().gas_price = gas_price;
+    }
+
+    pub fn set_base_fee(&self, base_fee: U256) {
+        self.state().base_fee = base_fee;
+    }
+
+    pub fn set_relayer_balance(&self, balance: U256) {
+        self.state().relayer_balance = balance;
+    }
+
+    #[must_use]
+    pub fn l1_root(&self) -> U256 {
+        self.state().l1_root(Instant::now())
+    }
+
+    /// The root of the latest propagation delivered to L2.
+    #[must_use]
+    pub fn l2_root(&self) -> U256 {
+        let now = Instant::now();
+        let state = self.state();
+        state
+            .propagations
+            .iter()
+            .filter_map(|propagation| Some((propagation.delivered_at?, propagation.root)))
+            .filter(|(delivered_at, _)| *delivered_at <= now)
+            .max_by_key(|(delivered_at, _)| *delivered_at)
+            .map_or(state.genesis_root, |(_, root)| root)
+    }
+
+    #[must_use]
+    pub fn is_synced(&self) -> bool {
+        self.l1_root() == self.l2_root()
+    }
+
+    /// Every propagation sent so far, oldest first.
+    #[must_use]
+    pub fn propagations(&self) -> Vec<Propagation> {
+        self.state().propagations.clone()
+    }
+
+    /// Sends a propagation of the current L1 root through the relayer.
+    /// Sending again with the same key returns the first transaction.
+    ///
+    /// # Errors
+    ///
+    /// Will return the next scripted send failure, if any.
+    pub fn send(&self, idempotency_key: &str) -> Result<String, FakeError> {
+        let now = Instant::now();
+        let mut state = self.state();
+        if let Some(err) = state.send_faults.pop_front() {
+            return Err(err);
+        }
+        if let Some(propagation) = state
+            .propagations
+            .iter()
+            .find(|propagation| propagation.idempotency_key == idempotency_key)
+        {
+            return Ok(propagation.id.clone());
+        }
+
+        let outcome = state.outcomes.pop_front().unwrap_or_default();
+        let mined_at = now + state.mining_delay;
+        let propagation = Propagation {
+            id: format!("tx-{}", state.propagations.len() + 1),
+            idempotency_key: idempotency_key.to_string(),
+            root: state.l1_root(now),
+            outcome,
+            mined_at,
+            delivered_at: (outcome == Outcome::Delivered).then(|| mined_at + state.delivery_delay),
+        };
+        let id = propagation.id.clone();
+        state.propagations.push(propagation);
+
+        Ok(id)
+    }
+
+    /// The ids of the propagations mined so far, reverted or not.
+    #[must_use]
+    pub fn mined(&self) -> Vec<String> {
+        let now = Instant::now();
+        self.state()
+            .propagations
+            .iter()
+            .filter(|propagation| propagation.mined_at <= now)
+            .map(|propagation| propagation.id.clone())
+            .collect()
+    }
+
+    /// Waits for the transaction `id` to be mined.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if no propagation was sent as `id`.
+    pub async fn wait_for_receipt(&self, id: &str) -> Result<Receipt, FakeError> {
+        let (mined_at, outcome) = self
+            .state()
+            .propagations
+            .iter()
+            .find(|propagation| propagation.id == id)
+            .map(|propagation| (propagation.mined_at, propagation.outcome))
+            .ok_or_else(|| FakeError::UnknownTransaction(id.to_string()))?;
+        time::sleep_until(mined_at).await;
+
+        Ok(Receipt {
+            succeeded: outcome != Outcome::Reverted,
+        })
+    }
+
+    #[must_use]
+    pub fn gas_price(&self) -> U256 {
+        self.state().gas_price
+    }
+
+    #[must_use]
+    pub fn base_fee(&self) -> U256 {
+        self.state().base_fee
+    }
+
+    #[must_use]
+    pub fn relayer_balance(&self) -> U256 {
+        self.state().relayer_balance
+    }
+
+    #[must_use]
+    pub fn root_history_expiry(&self) -> Duration {
+        self.state().root_history_expiry
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl State {
+    fn l1_root(&self, now: Instant) -> U256 {
+        self.l1_roots
+            .iter()
+            .rev()
+            .find(|(at, _)| *at <= now)
+            .map_or(self.genesis_root, |(_, root)| *root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn delivers_roots_after_the_delays() {
+        let chain = FakeChain::new(U256::from(1));
+        chain.schedule_l1_root(Duration::from_secs(5), U256::from(2));
+        assert!(chain.is_synced());
+
+        time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(chain.l1_root(), U256::from(2));
+        assert!(!chain.is_synced());
+
+        let id = chain.send("propagation-1").unwrap();
+        assert_eq!(chain.send("propagation-1").unwrap(), id);
+        assert!(chain.mined().is_empty());
+
+        assert!(chain.wait_for_receipt(&id).await.unwrap().succeeded);
+        assert_eq!(chain.mined(), vec![id]);
+        assert_eq!(chain.l2_root(), U256::from(1));
+
+        time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(chain.l2_root(), U256::from(2));
+        assert!(chain.is_synced());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn plays_scripted_failures() {
+        let chain = FakeChain::new(U256::from(1));
+        chain.set_l1_root(U256::from(2));
+        chain.fail_next_send(FakeError::Unavailable("timed out".into()));
+        chain.next_outcome(Outcome::Reverted);
+        chain.next_outcome(Outcome::MessageFailed);
+
+        assert_eq!(
+            chain.send("propagation-1"),
+            Err(FakeError::Unavailable("timed out".into()))
+        );
+        let reverted = chain.send("propagation-1").unwrap();
+        let failed = chain.send("propagation-2").unwrap();
+        let delivered = chain.send("propagation-3").unwrap();
+
+        assert!(!chain.wait_for_receipt(&reverted).await.unwrap().succeeded);
+        assert!(chain.wait_for_receipt(&failed).await.unwrap().succeeded);
+        time::sleep(Duration::from_secs(59)).await;
+        assert!(!chain.is_synced());
+        time::sleep(Duration::from_secs(1)).await;
+        assert!(chain.is_synced());
+
+        assert_eq!(chain.propagations().len(), 3);
+        assert_eq!(
+            chain.wait_for_receipt("tx-4").await,
+            Err(FakeError::UnknownTransaction("tx-4".into()))
+        );
+        assert_eq!(chain.propagations()[2].id, delivered);
+    }
+}
@@ -0,0 +1,52 @@
+//! Faults to inject, so that the service can be run against a relayer that
+//! misbehaves. They can be set on startup and changed through `/faults`.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, clap::Args)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Faults {
+    /// Milliseconds to wait before sending each transaction
+    #[arg(long, env = "MICRO_OZ_SEND_DELAY_MS", default_value_t = 0)]
+    pub send_delay_ms: u64,
+
+    /// Share of transactions that are taken but never sent, from 0 to 1
+    #[arg(long, env = "MICRO_OZ_DROP_RATE", default_value_t = 0.0)]
+    pub drop_rate: f64,
+
+    /// Share of transactions sent with too little gas to execute, so that
+    /// calls to contracts revert
+    #[arg(long, env = "MICRO_OZ_REVERT_RATE", default_value_t = 0.0)]
+    pub revert_rate: f64,
+
+    /// Share of API requests answered with a 503
+    #[arg(long, env = "MICRO_OZ_ERROR_RATE", default_value_t = 0.0)]
+    pub error_rate: f64,
+}
+
+impl Faults {
+    /// Whether a fault that happens at `rate` happens this time.
+    pub fn roll(rate: f64) -> bool {
+        rate > 0.0 && rand::random::<f64>() < rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_by_rate() {
+        assert!(!Faults::roll(0.0));
+        assert!(Faults::roll(1.0));
+    }
+
+    #[test]
+    fn partial_updates_keep_defaults() {
+        let faults: Faults = serde_json::from_str(r#"{ "dropRate": 0.5 }"#).unwrap();
+
+        assert_eq!(faults, Faults {
+            drop_rate: 0.5,
+            ..Faults::default()
+        });
+    }
+}
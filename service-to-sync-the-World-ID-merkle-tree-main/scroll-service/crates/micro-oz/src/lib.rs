@@ -1,26 +1,63 @@
-use std::collections::HashMap;
+//! A local relayer that serves both the OpenZeppelin Defender Relay API and
+//! the tx-sitter API, sending the transactions it takes to any RPC with a
+//! local key.
 use std::sync::atomic::{AtomicBool, AtomicU64};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use anyhow::Context;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use ethers::prelude::k256::ecdsa::SigningKey;
 use ethers::prelude::SignerMiddleware;
-use ethers::providers::{Http, Middleware, Provider};
+use ethers::providers::{Http, Middleware, Provider, ProviderError};
 use ethers::signers::{LocalWallet, Signer};
 use ethers::types::transaction::eip2718::TypedTransaction;
-use ethers::types::{Eip1559TransactionRequest, U64};
-use oz_api::data::transactions::{RelayerTransactionBase, SendBaseTransactionRequestOwned, Status};
+use ethers::types::{Address, BlockNumber, Bytes, Eip1559TransactionRequest, U256, U64};
+use oz_api::data::transactions::{
+    ListTransactionsQuery, RelayerTransactionBase, SendBaseTransactionRequestOwned, SortOrder,
+    Status, TransactionPage,
+};
 use tokio::sync::{mpsc, Mutex};
+use tx_sitter_client::data::RelayerInfo;
 
+pub mod faults;
 pub mod server;
+pub mod tx_sitter;
 
 const DEFAULT_GAS_LIMIT: u32 = 1_000_000;
 
-pub use self::server::{spawn, ServerHandle};
+/// The id and name of the one relayer.
+pub const RELAYER_ID: &str = "micro-oz";
+
+pub use self::faults::Faults;
+pub use self::server::{spawn, spawn_at, ServerHandle};
 
 type PinheadSigner = SignerMiddleware<Provider<Http>, LocalWallet>;
 
+#[derive(Debug, thiserror::Error)]
+pub enum PinheadError {
+    #[error("Transaction {0} already exists")]
+    AlreadyExists(String),
+    #[error("Transaction {0} not found")]
+    NotFound(String),
+    #[error("Transaction {0} was already sent")]
+    AlreadySent(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// A transaction taken through either API.
+#[derive(Debug, Clone)]
+pub struct Tx {
+    pub base:       RelayerTransactionBase,
+    /// The nonce it was sent with, once sent
+    pub nonce:      Option<U256>,
+    /// Set once the runner picked it up to send, from when it can't be
+    /// replaced or forgotten any more
+    pub sending:    bool,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Clone)]
 pub struct Pinhead {
     inner: Arc<PinheadInner>,
@@ -31,7 +68,9 @@ struct PinheadInner {
     is_running:     AtomicBool,
     tx_id_counter:  AtomicU64,
     txs_to_execute: mpsc::Sender<String>,
-    txs:            Mutex<HashMap<String, Arc<Mutex<RelayerTransactionBase>>>>,
+    /// Oldest first
+    txs:            Mutex<Vec<Tx>>,
+    faults:         RwLock<Faults>,
 }
 
 impl Drop for PinheadInner {
@@ -41,6 +80,23 @@ impl Drop for PinheadInner {
     }
 }
 
+impl PinheadInner {
+    fn faults(&self) -> Faults {
+        self.faults.read().unwrap().clone()
+    }
+
+    async fn update(&self, tx_id: &str, update: impl FnOnce(&mut Tx)) -> anyhow::Result<()> {
+        let mut txs = self.txs.lock().await;
+        let tx = txs
+            .iter_mut()
+            .find(|tx| tx.base.transaction_id == tx_id)
+            .with_context(|| format!("Missing tx {tx_id}"))?;
+        update(tx);
+
+        Ok(())
+    }
+}
+
 async fn runner(
     inner: Arc<PinheadInner>,
     mut txs_to_execute: mpsc::Receiver<String>,
@@ -64,60 +120,98 @@ async fn runner(
 async fn runner_inner(inner: &Arc<PinheadInner>, tx_id: String) -> Result<(), anyhow::Error> {
     tracing::info!("Executing tx: {tx_id}");
 
-    let tx = inner
-        .txs
-        .lock()
-        .await
-        .get(&tx_id)
-        .expect("Missing tx")
-        .clone();
-
-    let mut typed_tx = {
-        let tx_guard = tx.lock().await;
-
-        TypedTransaction::Eip1559(Eip1559TransactionRequest {
-            to: Some(tx_guard.to.clone()),
-            value: tx_guard.value,
-            gas: Some(tx_guard.gas_limit.into()),
-            data: tx_guard.data.clone(),
-            ..Eip1559TransactionRequest::default()
-        })
+    // Marked under the same lock replacements take, so that a replacement
+    // can't be accepted after this copy is made
+    let tx = {
+        let mut txs = inner.txs.lock().await;
+        // Forgotten by a reset since it was queued
+        let Some(tx) = txs.iter_mut().find(|tx| tx.base.transaction_id == tx_id) else {
+            tracing::info!("Skipping reset tx: {tx_id}");
+            return Ok(());
+        };
+        tx.sending = true;
+        tx.base.clone()
     };
 
-    inner.signer.fill_transaction(&mut typed_tx, None).await?;
+    let faults = inner.faults();
+    if faults.send_delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(faults.send_delay_ms)).await;
+    }
+    if Faults::roll(faults.drop_rate) {
+        tracing::warn!("Dropping tx: {tx_id}");
+        return Ok(());
+    }
 
-    let pending_tx = inner.signer.send_transaction(typed_tx, None).await?;
+    let mut typed_tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+        to: Some(tx.to.clone()),
+        value: tx.value,
+        gas: Some(tx.gas_limit.into()),
+        data: tx.data.clone(),
+        ..Eip1559TransactionRequest::default()
+    });
 
-    {
-        let mut tx_guard = tx.lock().await;
+    inner.signer.fill_transaction(&mut typed_tx, None).await?;
 
-        tx_guard.status = Status::Pending;
-        tx_guard.hash = Some(pending_tx.tx_hash());
+    if Faults::roll(faults.revert_rate) {
+        // Just enough gas to be included, none left to execute the call
+        tracing::warn!("Sending tx {tx_id} to revert");
+        typed_tx.set_gas(intrinsic_gas(tx.data.as_ref()));
     }
 
+    let nonce = typed_tx.nonce().copied();
+    let pending_tx = inner.signer.send_transaction(typed_tx, None).await?;
+    let hash = pending_tx.tx_hash();
+
+    inner
+        .update(&tx_id, |tx| {
+            tx.base.status = Status::Pending;
+            tx.base.hash = Some(hash);
+            tx.nonce = nonce;
+        })
+        .await?;
+
     tracing::info!("Awaiting for receipt");
 
     let receipt = pending_tx.await?;
 
-    let mut tx_guard = tx.lock().await;
-
-    if let Some(receipt) = receipt {
+    let status = if let Some(receipt) = receipt {
         if let Some(U64([0])) = receipt.status {
             tracing::error!("Receipt: {:?}", receipt);
         } else {
             tracing::info!("Receipt: {:?}", receipt);
         }
-        tx_guard.status = Status::Mined;
+        Status::Mined
     } else {
         tracing::error!("Receipt not found");
-        tx_guard.status = Status::Failed;
-    }
+        Status::Failed
+    };
 
-    Ok(())
+    inner.update(&tx_id, |tx| tx.base.status = status).await
+}
+
+/// The gas a transaction with `data` costs before it executes anything.
+fn intrinsic_gas(data: Option<&Bytes>) -> U256 {
+    let data_gas: u64 = data
+        .map(|data| {
+            data.iter()
+                .map(|byte| if *byte == 0 { 4 } else { 16 })
+                .sum()
+        })
+        .unwrap_or_default();
+
+    U256::from(21_000 + data_gas)
 }
 
 impl Pinhead {
     pub async fn new(rpc_url: String, secret_key: SigningKey) -> anyhow::Result<Self> {
+        Self::with_faults(rpc_url, secret_key, Faults::default()).await
+    }
+
+    pub async fn with_faults(
+        rpc_url: String,
+        secret_key: SigningKey,
+        faults: Faults,
+    ) -> anyhow::Result<Self> {
         let provider = Provider::<Http>::try_from(rpc_url)?;
 
         let chain_id = provider.get_chainid().await?.as_u64();
@@ -127,7 +221,7 @@ impl Pinhead {
 
         let is_running = AtomicBool::new(true);
         let tx_id_counter = AtomicU64::new(0);
-        let txs = Mutex::new(HashMap::new());
+        let txs = Mutex::new(Vec::new());
 
         let (tx_sender, tx_receiver) = mpsc::channel(100);
 
@@ -137,6 +231,7 @@ impl Pinhead {
             is_running,
             txs_to_execute: tx_sender,
             txs,
+            faults: RwLock::new(faults),
         });
 
         tokio::spawn(runner(inner.clone(), tx_receiver));
@@ -144,77 +239,201 @@ impl Pinhead {
         Ok(Self { inner })
     }
 
+    pub fn faults(&self) -> Faults {
+        self.inner.faults()
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        *self.inner.faults.write().unwrap() = faults;
+    }
+
     pub async fn send_transaction(
         &self,
         tx_request: SendBaseTransactionRequestOwned,
     ) -> anyhow::Result<RelayerTransactionBase> {
+        Ok(self.submit(None, tx_request).await?.base)
+    }
+
+    /// Takes a transaction to send as `tx_id`, or under a new id.
+    pub async fn submit(
+        &self,
+        tx_id: Option<String>,
+        tx_request: SendBaseTransactionRequestOwned,
+    ) -> Result<Tx, PinheadError> {
         let mut txs = self.inner.txs.lock().await;
 
-        let tx_id = self.next_tx_id();
-
-        let tx = RelayerTransactionBase {
-            transaction_id: tx_id.clone(),
-            to:             tx_request.to.context("Missing to")?,
-            value:          tx_request.value,
-            gas_limit:      tx_request
-                .gas_limit
-                .map(|gas_limit| gas_limit.as_u32())
-                .unwrap_or(DEFAULT_GAS_LIMIT),
-            data:           tx_request.data,
-            status:         Status::Pending,
-            hash:           None,
-            valid_until:    tx_request
-                .valid_until
-                .unwrap_or(Utc::now() + chrono::Duration::hours(24)),
+        let tx_id = tx_id.unwrap_or_else(|| self.next_tx_id());
+        if txs.iter().any(|tx| tx.base.transaction_id == tx_id) {
+            return Err(PinheadError::AlreadyExists(tx_id));
+        }
+
+        let tx = Tx {
+            base:       RelayerTransactionBase {
+                transaction_id: tx_id.clone(),
+                to:             tx_request.to.context("Missing to")?,
+                value:          tx_request.value,
+                gas_limit:      tx_request
+                    .gas_limit
+                    .map(|gas_limit| gas_limit.as_u32())
+                    .unwrap_or(DEFAULT_GAS_LIMIT),
+                data:           tx_request.data,
+                status:         Status::Pending,
+                hash:           None,
+                valid_until:    tx_request
+                    .valid_until
+                    .unwrap_or(Utc::now() + chrono::Duration::hours(24)),
+            },
+            nonce:      None,
+            sending:    false,
+            created_at: Utc::now(),
         };
 
-        txs.insert(tx_id.clone(), Arc::new(Mutex::new(tx.clone())));
+        txs.push(tx.clone());
 
-        self.inner.txs_to_execute.send(tx_id).await?;
+        self.inner
+            .txs_to_execute
+            .send(tx_id)
+            .await
+            .context("Runner stopped")?;
 
         Ok(tx)
     }
 
+    /// Every transaction taken, newest first.
+    pub async fn transactions(&self) -> Vec<Tx> {
+        self.inner.txs.lock().await.iter().rev().cloned().collect()
+    }
+
+    pub async fn transaction(&self, tx_id: &str) -> Result<Tx, PinheadError> {
+        self.inner
+            .txs
+            .lock()
+            .await
+            .iter()
+            .find(|tx| tx.base.transaction_id == tx_id)
+            .cloned()
+            .ok_or_else(|| PinheadError::NotFound(tx_id.to_string()))
+    }
+
+    /// A page of the transactions matching `query`, newest first unless
+    /// sorted otherwise. The `next` cursor is the offset of the next page.
     pub async fn list_transactions(
         &self,
-        status: Option<Status>,
-        limit: Option<usize>,
-    ) -> anyhow::Result<Vec<RelayerTransactionBase>> {
-        let txs = self.inner.txs.lock().await;
+        query: &ListTransactionsQuery,
+    ) -> anyhow::Result<TransactionPage> {
+        let offset: usize = match &query.next {
+            Some(next) => next.parse().context("Invalid next cursor")?,
+            None => 0,
+        };
 
-        let mut txs_to_return = vec![];
+        let mut txs: Vec<_> = self
+            .transactions()
+            .await
+            .into_iter()
+            .filter(|tx| {
+                query
+                    .status
+                    .is_none_or(|status| status.matches(tx.base.status))
+            })
+            .filter(|tx| query.since.is_none_or(|since| tx.created_at >= since))
+            .map(|tx| tx.base)
+            .collect();
+        if query.sort == Some(SortOrder::Asc) {
+            txs.reverse();
+        }
 
-        for tx in txs.values() {
-            let tx_guard = tx.lock().await;
+        let limit = query.limit.unwrap_or(usize::MAX);
+        let items: Vec<_> = txs.iter().skip(offset).take(limit).cloned().collect();
+        let end = offset + items.len();
 
-            if let Some(status) = status {
-                if tx_guard.status != status {
-                    continue;
-                }
-            }
+        Ok(TransactionPage {
+            items,
+            next: (end < txs.len()).then(|| end.to_string()),
+        })
+    }
 
-            txs_to_return.push(tx_guard.clone());
+    pub async fn query_transaction(
+        &self,
+        tx_id: &str,
+    ) -> Result<RelayerTransactionBase, PinheadError> {
+        Ok(self.transaction(tx_id).await?.base)
+    }
 
-            if let Some(limit) = limit {
-                if txs_to_return.len() >= limit {
-                    break;
-                }
-            }
+    /// Replaces a transaction the runner hasn't picked up yet. Sent ones
+    /// would have to be resent with the same nonce, which isn't emulated.
+    pub async fn replace_transaction(
+        &self,
+        tx_id: &str,
+        tx_request: SendBaseTransactionRequestOwned,
+    ) -> Result<RelayerTransactionBase, PinheadError> {
+        let mut txs = self.inner.txs.lock().await;
+        let tx = txs
+            .iter_mut()
+            .find(|tx| tx.base.transaction_id == tx_id)
+            .ok_or_else(|| PinheadError::NotFound(tx_id.to_string()))?;
+        if tx.sending {
+            return Err(PinheadError::AlreadySent(tx_id.to_string()));
         }
 
-        Ok(txs_to_return)
+        if let Some(to) = tx_request.to {
+            tx.base.to = to;
+        }
+        if let Some(gas_limit) = tx_request.gas_limit {
+            tx.base.gas_limit = gas_limit.as_u32();
+        }
+        if let Some(valid_until) = tx_request.valid_until {
+            tx.base.valid_until = valid_until;
+        }
+        tx.base.value = tx_request.value;
+        tx.base.data = tx_request.data;
+
+        Ok(tx.base.clone())
     }
 
-    pub async fn query_transaction(&self, tx_id: &str) -> anyhow::Result<RelayerTransactionBase> {
-        let txs = self.inner.txs.lock().await;
+    pub fn address(&self) -> Address {
+        self.inner.signer.address()
+    }
 
-        let tx = txs
-            .get(tx_id)
-            .context(format!("Transaction {} not found", tx_id))?;
+    /// The relayer as the tx-sitter describes it.
+    pub async fn relayer_info(&self) -> anyhow::Result<RelayerInfo> {
+        let signer = &self.inner.signer;
+        let address = signer.address();
+        let nonce = signer
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await?;
+        let current_nonce = signer
+            .get_transaction_count(address, Some(BlockNumber::Latest.into()))
+            .await?;
+
+        Ok(RelayerInfo {
+            id: RELAYER_ID.to_string(),
+            name: RELAYER_ID.to_string(),
+            chain_id: signer.signer().chain_id(),
+            address,
+            nonce: nonce.as_u64(),
+            current_nonce: current_nonce.as_u64(),
+            max_inflight_txs: 0,
+            enabled: true,
+        })
+    }
+
+    /// Forgets the transactions the runner hasn't picked up yet, returning
+    /// how many.
+    pub async fn reset(&self) -> usize {
+        let mut txs = self.inner.txs.lock().await;
+        let before = txs.len();
+        txs.retain(|tx| tx.sending);
 
-        let tx_guard = tx.lock().await;
+        before - txs.len()
+    }
 
-        Ok(tx_guard.clone())
+    /// Passes a JSON-RPC request through to the chain.
+    pub async fn rpc(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        self.inner.signer.provider().request(method, params).await
     }
 
     fn next_tx_id(&self) -> String {
@@ -226,3 +445,124 @@ impl Pinhead {
         format!("tx-{}", id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use fake_chain::http::serve;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Serves only `eth_chainId`, so transactions are taken but never sent.
+    pub(crate) fn spawn_rpc() -> String {
+        let rpc = Router::new().route(
+            "/",
+            post(|Json(request): Json<Value>| async move {
+                Json(if request["method"] == "eth_chainId" {
+                    json!({ "jsonrpc": "2.0", "id": request["id"], "result": "0x1" })
+                } else {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -32601, "message": "not supported" },
+                    })
+                })
+            }),
+        );
+
+        format!("http://{}", serve(rpc))
+    }
+
+    fn request(data: &'static [u8]) -> SendBaseTransactionRequestOwned {
+        SendBaseTransactionRequestOwned {
+            to:          Some(Address::repeat_byte(0x01).into()),
+            value:       None,
+            data:        Some(Bytes::from_static(data)),
+            gas_limit:   None,
+            valid_until: None,
+            speed:       None,
+        }
+    }
+
+    #[test]
+    fn intrinsic_gas_counts_calldata() {
+        assert_eq!(intrinsic_gas(None), U256::from(21_000));
+        assert_eq!(
+            intrinsic_gas(Some(&Bytes::from(vec![0, 1, 0, 2]))),
+            U256::from(21_000 + 4 + 16 + 4 + 16)
+        );
+    }
+
+    #[tokio::test]
+    async fn picked_up_transactions_cant_be_replaced() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let pinhead = Pinhead::with_faults(spawn_rpc(), key, Faults {
+            send_delay_ms: 60_000,
+            ..Faults::default()
+        })
+        .await
+        .unwrap();
+
+        let tx_id = pinhead
+            .send_transaction(request(&[1]))
+            .await
+            .unwrap()
+            .transaction_id;
+        while !pinhead.transaction(&tx_id).await.unwrap().sending {
+            tokio::task::yield_now().await;
+        }
+
+        // Still waiting out the send delay, with the original copy
+        let err = pinhead
+            .replace_transaction(&tx_id, request(&[2]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PinheadError::AlreadySent(id) if id == tx_id));
+        assert_eq!(pinhead.reset().await, 0);
+        assert_eq!(
+            pinhead.query_transaction(&tx_id).await.unwrap().data,
+            Some(Bytes::from_static(&[1]))
+        );
+    }
+
+    #[tokio::test]
+    async fn runner_skips_reset_transactions() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let pinhead = Pinhead::with_faults(spawn_rpc(), key, Faults {
+            send_delay_ms: 500,
+            ..Faults::default()
+        })
+        .await
+        .unwrap();
+
+        let first = pinhead
+            .send_transaction(request(&[1]))
+            .await
+            .unwrap()
+            .transaction_id;
+        while !pinhead.transaction(&first).await.unwrap().sending {
+            tokio::task::yield_now().await;
+        }
+
+        // Queued behind the first one, which is waiting out the send delay
+        pinhead.send_transaction(request(&[2])).await.unwrap();
+        pinhead.send_transaction(request(&[3])).await.unwrap();
+        assert_eq!(pinhead.reset().await, 2);
+
+        pinhead.set_faults(Faults::default());
+        let next = pinhead
+            .send_transaction(request(&[4]))
+            .await
+            .unwrap()
+            .transaction_id;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !pinhead.transaction(&next).await.unwrap().sending {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the runner stopped at a reset transaction");
+    }
+}
@@ -0,0 +1,61 @@
+//! Runs the relayer for local development, e.g. against anvil:
+//!
+//! ```shell
+//! cargo run -p micro-oz -- --private-key 0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80
+//! ```
+use std::net::SocketAddr;
+
+use clap::Parser;
+use ethers::signers::LocalWallet;
+use micro_oz::Faults;
+
+#[derive(Debug, Parser)]
+#[command(about = "A local relayer serving the Defender Relay and tx-sitter APIs")]
+struct Args {
+    /// The RPC of the chain to send transactions to
+    #[arg(
+        long,
+        env = "MICRO_OZ_RPC_URL",
+        default_value = "http://127.0.0.1:8545"
+    )]
+    rpc_url: String,
+
+    /// The hex private key of the relayer account
+    #[arg(long, env = "MICRO_OZ_PRIVATE_KEY")]
+    private_key: String,
+
+    /// The address to serve the APIs on
+    #[arg(long, env = "MICRO_OZ_ADDRESS", default_value = "127.0.0.1:3000")]
+    address: SocketAddr,
+
+    #[command(flatten)]
+    faults: Faults,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let wallet: LocalWallet = args.private_key.parse()?;
+
+    let server = micro_oz::spawn_at(
+        args.rpc_url,
+        wallet.signer().clone(),
+        args.address,
+        args.faults,
+    )
+    .await?;
+
+    tracing::info!(
+        relayer = ?server.address(),
+        defender = server.endpoint(),
+        tx_sitter = server.tx_sitter_endpoint(),
+        "Relayer running"
+    );
+
+    tokio::signal::ctrl_c().await?;
+    server.shutdown().await;
+
+    Ok(())
+}
@@ -3,72 +3,192 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use axum::extract::{Path, Query, State};
-use axum::http::StatusCode;
+use axum::http::{Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use ethers::prelude::k256::ecdsa::SigningKey;
+use ethers::providers::RpcError;
 use ethers::types::Address;
-use oz_api::data::transactions::{RelayerTransactionBase, SendBaseTransactionRequestOwned, Status};
-use serde::{Deserialize, Serialize};
+use oz_api::data::relayer::{Relayer, RelayerStatus};
+use oz_api::data::transactions::{
+    ListTransactionsQuery, RelayerTransactionBase, SendBaseTransactionRequestOwned,
+};
+use serde::Deserialize;
+use serde_json::json;
 use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 
-use crate::Pinhead;
+use crate::{tx_sitter, Faults, Pinhead, PinheadError};
+
+pub(crate) fn error_response(err: PinheadError) -> (StatusCode, String) {
+    let status = match err {
+        PinheadError::AlreadyExists(_) => StatusCode::CONFLICT,
+        PinheadError::NotFound(_) => StatusCode::NOT_FOUND,
+        PinheadError::AlreadySent(_) => StatusCode::BAD_REQUEST,
+        PinheadError::Other(ref err) => {
+            tracing::error!("Pinhead error: {:?}", err);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    (status, err.to_string())
+}
 
 async fn send_transaction(
     State(pinhead): State<Pinhead>,
     Json(request): Json<SendBaseTransactionRequestOwned>,
-) -> Result<Json<RelayerTransactionBase>, StatusCode> {
-    let result = pinhead.send_transaction(request).await;
-
-    match result {
-        Ok(tx) => Ok(Json(tx)),
-        Err(err) => {
-            tracing::error!("Pinhead send_transaction error: {:?}", err);
+) -> Result<Json<RelayerTransactionBase>, (StatusCode, String)> {
+    let tx = pinhead
+        .send_transaction(request)
+        .await
+        .map_err(|err| error_response(err.into()))?;
 
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(tx))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ListTransactionsQuery {
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pagination {
     #[serde(default)]
-    status: Option<Status>,
-    #[serde(default)]
-    limit:  Option<usize>,
+    use_pagination: bool,
 }
 
+/// Pages with `usePagination`, otherwise just the items of the first page.
 async fn list_transactions(
     State(pinhead): State<Pinhead>,
     Query(query): Query<ListTransactionsQuery>,
-) -> Result<Json<Vec<RelayerTransactionBase>>, StatusCode> {
-    let txs = pinhead.list_transactions(query.status, query.limit).await;
+    Query(pagination): Query<Pagination>,
+) -> Result<Response, (StatusCode, String)> {
+    let page = pinhead
+        .list_transactions(&query)
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
 
-    match txs {
-        Ok(txs) => Ok(Json(txs)),
-        Err(err) => {
-            tracing::error!("Pinhead list_transactions error: {:?}", err);
-
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    if pagination.use_pagination {
+        Ok(Json(page).into_response())
+    } else {
+        Ok(Json(page.items).into_response())
     }
 }
 
 async fn query_transaction(
     State(pinhead): State<Pinhead>,
     Path(tx_id): Path<String>,
-) -> Result<Json<RelayerTransactionBase>, StatusCode> {
-    let tx = pinhead.query_transaction(&tx_id).await;
+) -> Result<Json<RelayerTransactionBase>, (StatusCode, String)> {
+    let tx = pinhead
+        .query_transaction(&tx_id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(tx))
+}
+
+async fn replace_transaction(
+    State(pinhead): State<Pinhead>,
+    Path(tx_id): Path<String>,
+    Json(request): Json<SendBaseTransactionRequestOwned>,
+) -> Result<Json<RelayerTransactionBase>, (StatusCode, String)> {
+    if let Some(speed) = request.speed {
+        tracing::info!("Replacing {tx_id} at {speed:?}, which is ignored");
+    }
+    let tx = pinhead
+        .replace_transaction(&tx_id, request)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(tx))
+}
+
+async fn get_relayer(
+    State(pinhead): State<Pinhead>,
+) -> Result<Json<Relayer>, (StatusCode, String)> {
+    let relayer = pinhead
+        .relayer_info()
+        .await
+        .map_err(|err| error_response(err.into()))?;
+
+    Ok(Json(Relayer {
+        relayer_id: relayer.id,
+        name:       relayer.name,
+        address:    relayer.address,
+        network:    relayer.chain_id.to_string(),
+        paused:     !relayer.enabled,
+    }))
+}
+
+async fn get_relayer_status(
+    State(pinhead): State<Pinhead>,
+) -> Result<Json<RelayerStatus>, (StatusCode, String)> {
+    let relayer = pinhead
+        .relayer_info()
+        .await
+        .map_err(|err| error_response(err.into()))?;
+
+    Ok(Json(RelayerStatus {
+        relayer_id: relayer.id,
+        name: relayer.name,
+        address: relayer.address,
+        nonce: relayer.nonce,
+        number_of_pending_transactions: relayer.nonce.saturating_sub(relayer.current_nonce),
+        paused: !relayer.enabled,
+    }))
+}
 
-    match tx {
-        Ok(tx) => Ok(Json(tx)),
+#[derive(Debug, Deserialize)]
+pub(crate) struct RpcRequest {
+    id:     serde_json::Value,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+/// Both APIs pass JSON-RPC requests through to the chain.
+pub(crate) async fn rpc(
+    State(pinhead): State<Pinhead>,
+    Json(request): Json<RpcRequest>,
+) -> Json<serde_json::Value> {
+    let response = match pinhead.rpc(&request.method, request.params).await {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id, "result": result }),
         Err(err) => {
-            tracing::error!("Pinhead query_transaction error: {:?}", err);
+            let error = err.as_error_response().map_or_else(
+                || json!({ "code": -32603, "message": err.to_string() }),
+                |err| json!({ "code": err.code, "message": err.message, "data": err.data }),
+            );
 
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            json!({ "jsonrpc": "2.0", "id": request.id, "error": error })
         }
+    };
+
+    Json(response)
+}
+
+async fn get_faults(State(pinhead): State<Pinhead>) -> Json<Faults> {
+    Json(pinhead.faults())
+}
+
+async fn set_faults(State(pinhead): State<Pinhead>, Json(faults): Json<Faults>) -> Json<Faults> {
+    tracing::info!("Injecting faults: {:?}", faults);
+    pinhead.set_faults(faults.clone());
+
+    Json(faults)
+}
+
+/// Fails requests to either API at the configured error rate.
+async fn inject_errors<B>(
+    State(pinhead): State<Pinhead>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if Faults::roll(pinhead.faults().error_rate) {
+        tracing::warn!("Failing request: {} {}", request.method(), request.uri());
+
+        return (StatusCode::SERVICE_UNAVAILABLE, "Injected fault").into_response();
     }
+
+    next.run(request).await
 }
 
 pub struct ServerHandle {
@@ -80,17 +200,27 @@ pub struct ServerHandle {
 
 impl ServerHandle {
     pub fn address(&self) -> Address {
-        self.pinhead.inner.signer.address()
+        self.pinhead.address()
     }
 
     pub fn addr(&self) -> SocketAddr {
         self.addr
     }
 
+    /// The Defender Relay API.
     pub fn endpoint(&self) -> String {
         format!("http://{}", self.addr)
     }
 
+    /// The tx-sitter API.
+    pub fn tx_sitter_endpoint(&self) -> String {
+        format!("{}/tx-sitter", self.endpoint())
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        self.pinhead.set_faults(faults);
+    }
+
     pub async fn shutdown(self) {
         self.shutdown_notify.notify_waiters();
 
@@ -100,16 +230,39 @@ impl ServerHandle {
     }
 }
 
+/// Serves on a random local port, without faults.
 pub async fn spawn(rpc_url: String, secret_key: SigningKey) -> anyhow::Result<ServerHandle> {
-    let pinhead = Pinhead::new(rpc_url, secret_key).await?;
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+    spawn_at(rpc_url, secret_key, addr, Faults::default()).await
+}
+
+pub async fn spawn_at(
+    rpc_url: String,
+    secret_key: SigningKey,
+    addr: SocketAddr,
+    faults: Faults,
+) -> anyhow::Result<ServerHandle> {
+    let pinhead = Pinhead::with_faults(rpc_url, secret_key, faults).await?;
 
     let router = Router::new()
         .route("/txs", post(send_transaction).get(list_transactions))
-        .route("/txs/:tx_id", get(query_transaction))
+        .route(
+            "/txs/:tx_id",
+            get(query_transaction).put(replace_transaction),
+        )
+        .route("/relayer", get(get_relayer))
+        .route("/relayer/status", get(get_relayer_status))
+        .route("/relayer/jsonrpc", post(rpc))
+        .nest("/tx-sitter", tx_sitter::router())
+        .layer(middleware::from_fn_with_state(
+            pinhead.clone(),
+            inject_errors,
+        ))
+        .route("/faults", get(get_faults).put(set_faults))
         .with_state(pinhead.clone());
 
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
-    let listener = TcpListener::bind(addr).context("Failed to bind random port")?;
+    let listener = TcpListener::bind(addr).with_context(|| format!("Failed to bind {addr}"))?;
     let local_addr = listener.local_addr()?;
 
     let shutdown_notify = Arc::new(Notify::new());
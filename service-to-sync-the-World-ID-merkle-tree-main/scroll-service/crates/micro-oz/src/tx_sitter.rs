@@ -0,0 +1,223 @@
+//! The tx-sitter API used by `TxSitterClient`, served next to the Defender
+//! one and sending through the same relayer.
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use ethers::types::{Address, NameOrAddress, H256};
+use oz_api::data::transactions::{SendBaseTransactionRequestOwned, Status};
+use serde::Deserialize;
+use tx_sitter_client::data::{
+    GetTxResponse, RelayerInfo, SendTxRequest, SendTxResponse, TxStatus, UpdatePriorityRequest,
+};
+
+use crate::server::{error_response, rpc};
+use crate::{Pinhead, Tx, RELAYER_ID};
+
+pub fn router() -> Router<Pinhead> {
+    Router::new()
+        .route("/tx", post(send_tx))
+        .route("/tx/:tx_id", get(get_tx))
+        .route("/tx/:tx_id/priority", post(update_priority))
+        .route("/txs", get(get_txs))
+        .route("/relayer", get(get_relayer))
+        .route("/relayer/:relayer_id/reset", post(reset_relayer))
+        .route("/rpc", post(rpc))
+}
+
+async fn send_tx(
+    State(pinhead): State<Pinhead>,
+    Json(request): Json<SendTxRequest>,
+) -> Result<Json<SendTxResponse>, (StatusCode, String)> {
+    let tx = pinhead
+        .submit(request.tx_id, SendBaseTransactionRequestOwned {
+            to:          Some(request.to.into()),
+            value:       Some(request.value),
+            data:        request.data,
+            gas_limit:   Some(request.gas_limit),
+            valid_until: None,
+            speed:       None,
+        })
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(SendTxResponse {
+        tx_id: tx.base.transaction_id,
+    }))
+}
+
+async fn get_tx(
+    State(pinhead): State<Pinhead>,
+    Path(tx_id): Path<String>,
+) -> Result<Json<GetTxResponse>, (StatusCode, String)> {
+    let tx = pinhead.transaction(&tx_id).await.map_err(error_response)?;
+
+    Ok(Json(tx_response(&tx)))
+}
+
+#[derive(Debug, Deserialize)]
+struct GetTxsQuery {
+    #[serde(default)]
+    status: Option<TxStatus>,
+    #[serde(default)]
+    unsent: bool,
+}
+
+async fn get_txs(
+    State(pinhead): State<Pinhead>,
+    Query(query): Query<GetTxsQuery>,
+) -> Json<Vec<GetTxResponse>> {
+    let txs = pinhead
+        .transactions()
+        .await
+        .iter()
+        .map(tx_response)
+        .filter(|tx| {
+            if query.unsent {
+                tx.tx_hash.is_none()
+            } else {
+                query.status.is_none_or(|status| tx.status == Some(status))
+            }
+        })
+        .collect();
+
+    Json(txs)
+}
+
+/// Priorities are only checked, every transaction is sent at the network's
+/// gas price.
+async fn update_priority(
+    State(pinhead): State<Pinhead>,
+    Path(tx_id): Path<String>,
+    Json(request): Json<UpdatePriorityRequest>,
+) -> Result<(), (StatusCode, String)> {
+    pinhead.transaction(&tx_id).await.map_err(error_response)?;
+    tracing::info!("Priority of {tx_id} raised to {:?}", request.priority);
+
+    Ok(())
+}
+
+async fn get_relayer(
+    State(pinhead): State<Pinhead>,
+) -> Result<Json<RelayerInfo>, (StatusCode, String)> {
+    let relayer = pinhead
+        .relayer_info()
+        .await
+        .map_err(|err| error_response(err.into()))?;
+
+    Ok(Json(relayer))
+}
+
+async fn reset_relayer(
+    State(pinhead): State<Pinhead>,
+    Path(relayer_id): Path<String>,
+) -> Result<(), (StatusCode, String)> {
+    if relayer_id != RELAYER_ID {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Relayer {relayer_id} not found"),
+        ));
+    }
+    let dropped = pinhead.reset().await;
+    tracing::info!("Reset the relayer, dropping {dropped} unsent txs");
+
+    Ok(())
+}
+
+fn tx_response(tx: &Tx) -> GetTxResponse {
+    GetTxResponse {
+        tx_id:     tx.base.transaction_id.clone(),
+        to:        match tx.base.to {
+            NameOrAddress::Address(address) => address,
+            NameOrAddress::Name(_) => Address::zero(),
+        },
+        data:      tx.base.data.clone(),
+        value:     tx.base.value.unwrap_or_default(),
+        gas_limit: tx.base.gas_limit.into(),
+        nonce:     tx.nonce.map_or(0, |nonce| nonce.as_u64()),
+        tx_hash:   tx.base.hash,
+        status:    tx_status(tx.base.status, tx.base.hash),
+    }
+}
+
+/// The tx-sitter status of a transaction, which has none until it's sent.
+fn tx_status(status: Status, hash: Option<H256>) -> Option<TxStatus> {
+    match status {
+        Status::Pending | Status::Sent | Status::Submitted | Status::Inmempool => {
+            hash.map(|_| TxStatus::Pending)
+        }
+        Status::Mined => Some(TxStatus::Mined),
+        Status::Confirmed => Some(TxStatus::Finalized),
+        // The tx-sitter has no failed status. A transaction that was lost
+        // isn't in flight any more, so it isn't listed as pending either.
+        Status::Failed => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::prelude::k256::ecdsa::SigningKey;
+    use ethers::types::{Bytes, U256};
+    use tx_sitter_client::data::TransactionPriority;
+    use tx_sitter_client::TxSitterClient;
+
+    use super::*;
+    use crate::tests::spawn_rpc;
+
+    #[test]
+    fn maps_statuses() {
+        assert_eq!(tx_status(Status::Pending, None), None);
+        assert_eq!(
+            tx_status(Status::Pending, Some(H256::zero())),
+            Some(TxStatus::Pending)
+        );
+        assert_eq!(
+            tx_status(Status::Mined, Some(H256::zero())),
+            Some(TxStatus::Mined)
+        );
+        assert_eq!(
+            tx_status(Status::Confirmed, Some(H256::zero())),
+            Some(TxStatus::Finalized)
+        );
+        assert_eq!(tx_status(Status::Failed, Some(H256::zero())), None);
+    }
+
+    #[tokio::test]
+    async fn round_trips_through_the_client() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let server = crate::spawn(spawn_rpc(), key).await.unwrap();
+        let client = TxSitterClient::new(server.tx_sitter_endpoint());
+
+        let request = SendTxRequest {
+            to:        Address::repeat_byte(0x01),
+            value:     U256::from(7),
+            data:      Some(Bytes::from(vec![0xde, 0xad])),
+            gas_limit: U256::from(100_000),
+            priority:  TransactionPriority::Regular,
+            tx_id:     Some("propagation-1".to_string()),
+        };
+        let sent = client.send_tx(&request).await.unwrap();
+        assert_eq!(sent.tx_id, "propagation-1");
+        // The id is an idempotency key
+        assert!(client.send_tx(&request).await.is_err());
+
+        let tx = client.get_tx("propagation-1").await.unwrap();
+        assert_eq!(tx.tx_id, "propagation-1");
+        assert_eq!(tx.to, request.to);
+        assert_eq!(tx.value, request.value);
+        assert_eq!(tx.data, request.data);
+        assert_eq!(tx.gas_limit, request.gas_limit);
+        assert_eq!((tx.tx_hash, tx.status), (None, None));
+
+        let unsent: Vec<_> = client
+            .get_unsent_txs()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tx| tx.tx_id)
+            .collect();
+        assert_eq!(unsent, vec!["propagation-1"]);
+
+        server.shutdown().await;
+    }
+}
@@ -1 +1,2 @@
+pub mod relayer;
 pub mod transactions;
@@ -0,0 +1,33 @@
+//! The relayer as described by the OpenZeppelin Defender API.
+//!
+//! https://docs.openzeppelin.com/defender/relay-api-reference#relayer-endpoint
+
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+
+/// https://docs.openzeppelin.com/defender/relay-api-reference#get-relayer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relayer {
+    pub relayer_id: String,
+    pub name:       String,
+    pub address:    Address,
+    pub network:    String,
+    #[serde(default)]
+    pub paused:     bool,
+}
+
+/// https://docs.openzeppelin.com/defender/relay-api-reference#get-relayer-status
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayerStatus {
+    pub relayer_id: String,
+    pub name: String,
+    pub address: Address,
+    /// The nonce the next transaction is sent with
+    pub nonce: u64,
+    #[serde(default)]
+    pub number_of_pending_transactions: u64,
+    #[serde(default)]
+    pub paused: bool,
+}
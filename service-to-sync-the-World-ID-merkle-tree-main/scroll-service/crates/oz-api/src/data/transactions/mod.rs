@@ -37,6 +37,84 @@ impl fmt::Display for Status {
     }
 }
 
+/// The status filter Defender lists transactions by, each covering several
+/// statuses.
+///
+/// https://docs.openzeppelin.com/defender/relay-api-reference#list-transactions
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StatusFilter {
+    Pending,
+    Mined,
+    Failed,
+}
+
+impl StatusFilter {
+    #[must_use]
+    pub fn matches(self, status: Status) -> bool {
+        match self {
+            StatusFilter::Pending => matches!(
+                status,
+                Status::Pending | Status::Sent | Status::Submitted | Status::Inmempool
+            ),
+            StatusFilter::Mined => matches!(status, Status::Mined | Status::Confirmed),
+            StatusFilter::Failed => status == Status::Failed,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// A page of the transactions list. Pages are followed with the `next`
+/// cursor of the previous one.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTransactionsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub status: Option<StatusFilter>,
+    /// Only transactions sent since
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub since:  Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub limit:  Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub sort:   Option<SortOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub next:   Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionPage {
+    pub items: Vec<RelayerTransactionBase>,
+    /// Missing on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub next:  Option<String>,
+}
+
+/// The gas price Defender sends a transaction at, relative to the network.
+///
+/// https://docs.openzeppelin.com/defender/relay-api-reference#speed
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum Speed {
+    SafeLow,
+    Average,
+    Fast,
+    Fastest,
+}
+
 /// OpenZeppelin Defender transaction to be sent.
 ///
 /// https://docs.openzeppelin.com/defender/relay-api-reference#send-transaction
@@ -53,6 +131,8 @@ pub struct SendBaseTransactionRequest<'a> {
     pub gas_limit:   Option<&'a U256>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub valid_until: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed:       Option<Speed>,
 }
 
 /// OpenZeppelin Defender transaction to be sent.
@@ -76,6 +156,9 @@ pub struct SendBaseTransactionRequestOwned {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub valid_until: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub speed:       Option<Speed>,
 }
 
 /// OpenZeppelin Defender transaction that has been received by the relayer and
@@ -100,3 +183,37 @@ pub struct RelayerTransactionBase {
     pub valid_until:    DateTime<Utc>,
     pub status:         Status,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_cover_statuses() {
+        assert!(StatusFilter::Pending.matches(Status::Inmempool));
+        assert!(!StatusFilter::Pending.matches(Status::Mined));
+        assert!(StatusFilter::Mined.matches(Status::Confirmed));
+        assert!(!StatusFilter::Mined.matches(Status::Failed));
+        assert!(StatusFilter::Failed.matches(Status::Failed));
+    }
+
+    #[test]
+    fn decode_page() {
+        let page: TransactionPage = serde_json::from_str(
+            r#"{
+                "items": [{
+                    "transactionId": "1",
+                    "to": "0x928a514350a403e2f5e3288c102f6b1ccabeb37c",
+                    "gasLimit": 100000,
+                    "validUntil": "2024-01-01T00:00:00Z",
+                    "status": "inmempool"
+                }],
+                "next": "cursor"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(page.items[0].status, Status::Inmempool);
+        assert_eq!(page.next.as_deref(), Some("cursor"));
+    }
+}
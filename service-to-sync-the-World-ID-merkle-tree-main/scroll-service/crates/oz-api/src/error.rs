@@ -1,4 +1,5 @@
 use cognitoauth::error::CognitoSrpAuthError;
+use ethers::providers::JsonRpcError;
 use hyper::header::InvalidHeaderValue;
 use hyper::StatusCode;
 use thiserror::Error;
@@ -6,7 +7,7 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Authentication failed: {0}")]
-    AuthFailed(#[from] CognitoSrpAuthError),
+    AuthFailed(Box<CognitoSrpAuthError>),
 
     #[error("Unauthorized")]
     Unauthorized,
@@ -23,6 +24,58 @@ pub enum Error {
     #[error("Parsing error: {0}")]
     ParseError(#[from] serde_json::Error),
 
-    #[error("Invalid response with status code: {0}")]
-    InvalidResponse(StatusCode),
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Forbidden ({status}): {body}")]
+    Forbidden { status: StatusCode, body: String },
+
+    #[error("Request rejected ({status}): {body}")]
+    Rejected { status: StatusCode, body: String },
+
+    #[error("Server error ({status}): {body}")]
+    Server { status: StatusCode, body: String },
+
+    #[error("JSON-RPC error: {0}")]
+    JsonRpc(Box<JsonRpcError>),
+}
+
+impl From<CognitoSrpAuthError> for Error {
+    fn from(err: CognitoSrpAuthError) -> Self {
+        Self::AuthFailed(Box::new(err))
+    }
+}
+
+impl Error {
+    /// Sorts an unsuccessful response by its status, keeping the body
+    /// Defender explains it with.
+    #[must_use]
+    pub fn from_response(status: StatusCode, body: String) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => Self::NotFound(body),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Forbidden { status, body },
+            status if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+                Self::Server { status, body }
+            }
+            status => Self::Rejected { status, body },
+        }
+    }
+
+    /// The status Defender responded with, if it responded.
+    #[must_use]
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::NotFound(_) => Some(StatusCode::NOT_FOUND),
+            Self::Forbidden { status, .. }
+            | Self::Rejected { status, .. }
+            | Self::Server { status, .. } => Some(*status),
+            Self::Reqwest(err) => err.status(),
+            Self::AuthFailed(_)
+            | Self::Unauthorized
+            | Self::Headers(_)
+            | Self::UrlParseError(_)
+            | Self::ParseError(_)
+            | Self::JsonRpc(_) => None,
+        }
+    }
 }
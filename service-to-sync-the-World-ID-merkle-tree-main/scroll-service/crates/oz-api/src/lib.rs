@@ -1,9 +1,18 @@
 use std::time::Instant;
 
 use auth::ExpiringHeaders;
-use data::transactions::{RelayerTransactionBase, SendBaseTransactionRequest, Status};
+use chrono::{DateTime, Utc};
+use data::relayer::{Relayer, RelayerStatus};
+use data::transactions::{
+    ListTransactionsQuery, RelayerTransactionBase, SendBaseTransactionRequest, Speed, StatusFilter,
+    TransactionPage,
+};
+use ethers::providers::JsonRpcError;
+use ethers::types::{Address, BlockNumber, U256};
 use reqwest::{IntoUrl, Url};
 use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
 use tokio::sync::{Mutex, MutexGuard};
 use tracing::info;
 
@@ -14,6 +23,9 @@ pub mod error;
 pub use error::Error;
 pub type Result<T> = std::result::Result<T, error::Error>;
 
+/// The page size `list_all_transactions` asks for.
+const PAGE_SIZE: usize = 50;
+
 #[derive(Debug)]
 pub struct OzApi {
     client:           reqwest::Client,
@@ -24,6 +36,12 @@ pub struct OzApi {
     auth_disabled:    bool,
 }
 
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse<T> {
+    result: Option<T>,
+    error:  Option<JsonRpcError>,
+}
+
 impl OzApi {
     pub async fn new<U, S>(api_url: U, api_key: S, api_secret: S) -> Result<Self>
     where
@@ -39,13 +57,15 @@ impl OzApi {
         Ok(Self {
             client: reqwest::Client::new(),
             expiring_headers,
-            api_url: api_url.into_url()?,
+            api_url: base_url(api_url)?,
             api_key,
             api_secret,
             auth_disabled: false,
         })
     }
 
+    /// Sends no authentication headers, and never refreshes them. For local
+    /// relayers only.
     pub fn without_auth<U>(api_url: U) -> Result<Self>
     where
         U: IntoUrl,
@@ -59,10 +79,10 @@ impl OzApi {
         Ok(Self {
             client: reqwest::Client::new(),
             expiring_headers,
-            api_url: api_url.into_url()?,
+            api_url: base_url(api_url)?,
             api_key,
             api_secret,
-            auth_disabled: false,
+            auth_disabled: true,
         })
     }
 
@@ -72,60 +92,168 @@ impl OzApi {
     ) -> Result<RelayerTransactionBase> {
         let headers = self.headers().await?;
 
-        info!(?headers);
-
         let res = headers
             .apply(self.client.post(self.txs_url()?))
             .json(&tx)
             .send()
             .await?;
-        info!(?res);
+
         Self::json_or_error(res).await
     }
 
-    pub async fn list_transactions(
+    /// Replaces a pending transaction with `tx`, sent with the same nonce.
+    ///
+    /// https://docs.openzeppelin.com/defender/relay-api-reference#replace-transaction
+    pub async fn replace_transaction(
         &self,
-        status: Option<Status>,
-        limit: Option<usize>,
-    ) -> Result<Vec<RelayerTransactionBase>> {
-        let mut url = self.txs_url()?;
+        tx_id: &str,
+        tx: SendBaseTransactionRequest<'_>,
+    ) -> Result<RelayerTransactionBase> {
+        let headers = self.headers().await?;
 
-        let mut query_items = vec![];
+        let res = headers
+            .apply(self.client.put(self.tx_url(tx_id)?))
+            .json(&tx)
+            .send()
+            .await?;
 
-        if let Some(status) = status {
-            query_items.push(format!("status={status}"));
-        }
+        Self::json_or_error(res).await
+    }
 
-        if let Some(limit) = limit {
-            query_items.push(format!("limit={limit}"));
-        }
+    /// Replaces a pending transaction with the same one sent at `speed`.
+    pub async fn speed_up_transaction(
+        &self,
+        tx_id: &str,
+        speed: Speed,
+    ) -> Result<RelayerTransactionBase> {
+        let tx = self.query_transaction(tx_id).await?;
+        let replacement = SendBaseTransactionRequest {
+            to:          Some(&tx.to),
+            value:       tx.value.as_ref(),
+            data:        tx.data.as_ref(),
+            gas_limit:   Some(&U256::from(tx.gas_limit)),
+            valid_until: Some(tx.valid_until),
+            speed:       Some(speed),
+        };
+
+        info!(tx_id, ?speed, "Speeding up transaction");
+
+        self.replace_transaction(tx_id, replacement).await
+    }
 
-        if !query_items.is_empty() {
-            url.set_query(Some(&query_items.join("&")));
+    /// One page of transactions. Pass the page's `next` in the query for the
+    /// next one.
+    pub async fn list_transactions(
+        &self,
+        query: &ListTransactionsQuery,
+    ) -> Result<TransactionPage> {
+        let headers = self.headers().await?;
+
+        let res = headers
+            .apply(self.client.get(self.txs_url()?))
+            .query(query)
+            .query(&[("usePagination", "true")])
+            .send()
+            .await?;
+
+        Self::json_or_error(res).await
+    }
+
+    /// Every transaction with `status` sent since `since`, following the
+    /// pages to the last one.
+    pub async fn list_all_transactions(
+        &self,
+        status: Option<StatusFilter>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<RelayerTransactionBase>> {
+        let mut query = ListTransactionsQuery {
+            status,
+            since,
+            limit: Some(PAGE_SIZE),
+            ..ListTransactionsQuery::default()
+        };
+        let mut transactions = vec![];
+
+        loop {
+            let page = self.list_transactions(&query).await?;
+            transactions.extend(page.items);
+
+            match page.next {
+                Some(next) if query.next.as_ref() != Some(&next) => query.next = Some(next),
+                _ => return Ok(transactions),
+            }
         }
+    }
 
+    pub async fn query_transaction(&self, tx_id: &str) -> Result<RelayerTransactionBase> {
         let headers = self.headers().await?;
 
-        let res = headers.apply(self.client.get(url)).send().await?;
+        let res = headers
+            .apply(self.client.get(self.tx_url(tx_id)?))
+            .send()
+            .await?;
 
         Self::json_or_error(res).await
     }
 
-    pub async fn query_transaction(&self, tx_id: &str) -> Result<RelayerTransactionBase> {
-        let url = self.txs_url()?.join("txs/")?.join(tx_id)?;
+    pub async fn relayer(&self) -> Result<Relayer> {
+        let headers = self.headers().await?;
 
+        let res = headers
+            .apply(self.client.get(self.api_url.join("relayer")?))
+            .send()
+            .await?;
+
+        Self::json_or_error(res).await
+    }
+
+    pub async fn relayer_status(&self) -> Result<RelayerStatus> {
         let headers = self.headers().await?;
 
-        let res = headers.apply(self.client.get(url)).send().await?;
+        let res = headers
+            .apply(self.client.get(self.api_url.join("relayer/status")?))
+            .send()
+            .await?;
 
         Self::json_or_error(res).await
     }
 
+    pub async fn balance(&self, address: Address) -> Result<U256> {
+        self.rpc("eth_getBalance", json!([address, BlockNumber::Latest]))
+            .await
+    }
+
+    /// Sends a JSON-RPC request through the relayer's own provider.
+    ///
+    /// https://docs.openzeppelin.com/defender/relay-api-reference#jsonrpc-endpoint
+    pub async fn rpc<T>(&self, method: &str, params: serde_json::Value) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let headers = self.headers().await?;
+
+        let res = headers
+            .apply(self.client.post(self.api_url.join("relayer/jsonrpc")?))
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await?;
+
+        let res: JsonRpcResponse<serde_json::Value> = Self::json_or_error(res).await?;
+        match (res.result, res.error) {
+            (_, Some(err)) => Err(Error::JsonRpc(Box::new(err))),
+            (result, None) => Ok(serde_json::from_value(result.unwrap_or_default())?),
+        }
+    }
+
     fn txs_url(&self) -> Result<Url> {
         Ok(self.api_url.join("txs")?)
     }
 
-    async fn headers(&self) -> Result<MutexGuard<ExpiringHeaders>> {
+    fn tx_url(&self, tx_id: &str) -> Result<Url> {
+        Ok(self.api_url.join("txs/")?.join(tx_id)?)
+    }
+
+    async fn headers(&self) -> Result<MutexGuard<'_, ExpiringHeaders>> {
         if self.auth_disabled {
             return Ok(self.expiring_headers.lock().await);
         }
@@ -150,12 +278,128 @@ impl OzApi {
         let status_code = res.status();
 
         if !status_code.is_success() {
-            let error_text = res.text().await?;
-            info!(?error_text, "response error");
+            let body = res.text().await?;
 
-            Err(Error::InvalidResponse(status_code))
+            Err(Error::from_response(status_code, body))
         } else {
             Ok(res.json().await?)
         }
     }
 }
+
+/// Paths are joined onto the API url, so it has to end with a slash for
+/// them to keep its last segment, e.g. the `v2` of Defender's.
+fn base_url<U: IntoUrl>(api_url: U) -> Result<Url> {
+    let mut api_url = api_url.into_url()?;
+    if !api_url.path().ends_with('/') {
+        let path = format!("{}/", api_url.path());
+        api_url.set_path(&path);
+    }
+
+    Ok(api_url)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use axum::extract::{Path, Query};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use data::transactions::Status;
+    use fake_chain::http::serve;
+
+    use super::*;
+
+    fn tx(id: usize) -> serde_json::Value {
+        json!({
+            "transactionId": id.to_string(),
+            "to": "0x928a514350a403e2f5e3288c102f6b1ccabeb37c",
+            "gasLimit": 100000,
+            "validUntil": "2024-01-01T00:00:00Z",
+            "status": "pending"
+        })
+    }
+
+    /// A Defender API that serves five pending transactions two at a time,
+    /// and rejects any request with an authorization header.
+    fn stub() -> String {
+        async fn list(
+            headers: HeaderMap,
+            Query(query): Query<ListTransactionsQuery>,
+        ) -> std::result::Result<Json<serde_json::Value>, (StatusCode, &'static str)> {
+            if headers.contains_key("authorization") {
+                return Err((StatusCode::UNAUTHORIZED, "unexpected credentials"));
+            }
+            if query.status != Some(StatusFilter::Pending) {
+                return Err((StatusCode::BAD_REQUEST, "expected pending"));
+            }
+            let start: usize = query.next.map_or(0, |next| next.parse().unwrap());
+            let end = (start + 2).min(5);
+            let next = (end < 5).then(|| end.to_string());
+
+            Ok(Json(json!({
+                "items": (start..end).map(tx).collect::<Vec<_>>(),
+                "next": next
+            })))
+        }
+
+        let router = Router::new()
+            .route("/v2/txs", get(list))
+            .route(
+                "/v2/txs/:id",
+                get(|Path(id): Path<String>| async move {
+                    (StatusCode::NOT_FOUND, format!("Transaction {id} not found"))
+                }),
+            )
+            .route(
+                "/v2/relayer/jsonrpc",
+                post(|| async {
+                    Json(json!({
+                        "jsonrpc": "2.0",
+                        "id": 1,
+                        "error": { "code": -32000, "message": "header not found" }
+                    }))
+                }),
+            );
+
+        format!("http://{}/v2", serve(router))
+    }
+
+    #[tokio::test]
+    async fn lists_every_page_without_auth() {
+        let api = OzApi::without_auth(stub()).unwrap();
+
+        let txs = api
+            .list_all_transactions(Some(StatusFilter::Pending), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            txs.iter()
+                .map(|tx| tx.transaction_id.as_str())
+                .collect::<Vec<_>>(),
+            ["0", "1", "2", "3", "4"]
+        );
+        assert!(txs.iter().all(|tx| tx.status == Status::Pending));
+    }
+
+    #[tokio::test]
+    async fn types_errors() {
+        let api = OzApi::without_auth(stub()).unwrap();
+
+        let err = api.query_transaction("7").await.unwrap_err();
+        assert!(matches!(&err, Error::NotFound(body) if body == "Transaction 7 not found"));
+
+        let err = api
+            .list_transactions(&ListTransactionsQuery::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, Error::Rejected { status, body } if *status == StatusCode::BAD_REQUEST && body == "expected pending")
+        );
+
+        let err = api.balance(Address::zero()).await.unwrap_err();
+        assert!(matches!(&err, Error::JsonRpc(err) if err.code == -32000));
+    }
+}
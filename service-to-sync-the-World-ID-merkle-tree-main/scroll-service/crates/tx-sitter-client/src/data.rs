@@ -20,7 +20,7 @@ pub struct SendTxRequest {
     pub tx_id:     Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum TransactionPriority {
     // 5th percentile
@@ -36,6 +36,20 @@ pub enum TransactionPriority {
     Fastest = 4,
 }
 
+impl TransactionPriority {
+    /// The next priority up, if any.
+    #[must_use]
+    pub const fn escalated(self) -> Option<Self> {
+        match self {
+            Self::Slowest => Some(Self::Slow),
+            Self::Slow => Some(Self::Regular),
+            Self::Regular => Some(Self::Fast),
+            Self::Fast => Some(Self::Fastest),
+            Self::Fastest => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SendTxResponse {
@@ -71,6 +85,28 @@ pub enum TxStatus {
     Finalized,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePriorityRequest {
+    pub priority: TransactionPriority,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayerInfo {
+    pub id:               String,
+    pub name:             String,
+    pub chain_id:         u64,
+    pub address:          Address,
+    /// The nonce the next transaction is sent with
+    pub nonce:            u64,
+    /// The nonce of the latest mined transaction
+    pub current_nonce:    u64,
+    #[serde(default)]
+    pub max_inflight_txs: u64,
+    pub enabled:          bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
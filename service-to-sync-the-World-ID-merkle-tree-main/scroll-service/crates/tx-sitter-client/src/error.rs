@@ -0,0 +1,66 @@
+use ethers::providers::ProviderError;
+use reqwest::StatusCode;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TxSitterError {
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Unauthorized ({status}): {body}")]
+    Unauthorized { status: StatusCode, body: String },
+    #[error("Invalid request ({status}): {body}")]
+    Validation { status: StatusCode, body: String },
+    #[error("Server error ({status}): {body}")]
+    Server { status: StatusCode, body: String },
+    #[error("Transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[error("RPC error: {0}")]
+    Rpc(#[from] ProviderError),
+    #[error("Invalid url: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("Invalid API token")]
+    InvalidApiToken,
+}
+
+impl TxSitterError {
+    #[must_use]
+    pub fn from_response(status: StatusCode, body: String) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => Self::NotFound(body),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized { status, body },
+            status if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+                Self::Server { status, body }
+            }
+            status => Self::Validation { status, body },
+        }
+    }
+
+    /// The status the tx-sitter responded with, if it responded.
+    #[must_use]
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::NotFound(_) => Some(StatusCode::NOT_FOUND),
+            Self::Unauthorized { status, .. }
+            | Self::Validation { status, .. }
+            | Self::Server { status, .. } => Some(*status),
+            Self::Transport(err) => err.status(),
+            Self::Rpc(_) | Self::InvalidUrl(_) | Self::InvalidApiToken => None,
+        }
+    }
+
+    /// Whether sending the same request again may succeed. Responses that
+    /// couldn't be decoded won't decode any better the second time, and
+    /// RPC requests are left to the provider.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Server { .. } => true,
+            Self::Transport(err) => !err.is_decode(),
+            Self::NotFound(_)
+            | Self::Unauthorized { .. }
+            | Self::Validation { .. }
+            | Self::Rpc(_)
+            | Self::InvalidUrl(_)
+            | Self::InvalidApiToken => false,
+        }
+    }
+}
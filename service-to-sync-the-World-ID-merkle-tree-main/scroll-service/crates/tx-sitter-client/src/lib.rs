@@ -1,71 +1,176 @@
-use data::{GetTxResponse, SendTxRequest, SendTxResponse, TxStatus};
-use reqwest::Response;
+use std::time::Duration;
+
+use data::{
+    GetTxResponse, RelayerInfo, SendTxRequest, SendTxResponse, TransactionPriority, TxStatus,
+    UpdatePriorityRequest,
+};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, U256};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{RequestBuilder, Response};
 use tracing::instrument;
+use url::Url;
 
 pub mod data;
+mod error;
+
+pub use self::error::TxSitterError;
+
+/// Timeouts, retries and authentication of a [`TxSitterClient`].
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub connect_timeout: Duration,
+    /// For the whole request, including reading the response
+    pub request_timeout: Duration,
+    /// How many times a request that failed on the way or on the server is
+    /// sent again, if that's safe
+    pub max_retries:     u32,
+    /// Doubled on every retry
+    pub retry_backoff:   Duration,
+    /// Sent as a bearer token, for when it isn't part of the url
+    pub api_token:       Option<String>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            max_retries:     3,
+            retry_backoff:   Duration::from_millis(500),
+            api_token:       None,
+        }
+    }
+}
 
 pub struct TxSitterClient {
-    client: reqwest::Client,
-    url:    String,
+    client:  reqwest::Client,
+    url:     String,
+    options: ClientOptions,
 }
 
 impl TxSitterClient {
+    /// A client with the default [`ClientOptions`].
+    ///
+    /// # Panics
+    ///
+    /// If the TLS backend can't be initialized, like `reqwest::Client::new`.
     pub fn new(url: impl ToString) -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            url:    url.to_string(),
+        Self::with_options(url, ClientOptions::default()).expect("Failed to build HTTP client")
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the API token can't be sent in a header or the
+    /// TLS backend can't be initialized.
+    pub fn with_options(url: impl ToString, options: ClientOptions) -> Result<Self, TxSitterError> {
+        let mut headers = HeaderMap::new();
+        if let Some(api_token) = &options.api_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {api_token}"))
+                .map_err(|_| TxSitterError::InvalidApiToken)?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
         }
+
+        let client = reqwest::Client::builder()
+            .connect_timeout(options.connect_timeout)
+            .timeout(options.request_timeout)
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            options,
+        })
     }
 
-    async fn json_post<T, R>(&self, url: &str, body: T) -> anyhow::Result<R>
+    /// Posts `body`, retrying only if `idempotent`, since a request that
+    /// timed out may still have been processed.
+    async fn json_post<T, R>(
+        &self,
+        url: &str,
+        body: T,
+        idempotent: bool,
+    ) -> Result<R, TxSitterError>
     where
         T: serde::Serialize,
         R: serde::de::DeserializeOwned,
     {
-        let response = self.client.post(url).json(&body).send().await?;
-
-        let response = Self::validate_response(response).await?;
+        let response = self
+            .with_retries(idempotent, || self.client.post(url).json(&body))
+            .await?;
 
         Ok(response.json().await?)
     }
 
-    async fn json_get<R>(&self, url: &str) -> anyhow::Result<R>
+    async fn json_get<R>(&self, url: &str) -> Result<R, TxSitterError>
     where
         R: serde::de::DeserializeOwned,
     {
-        let response = self.client.get(url).send().await?;
-
-        let response = Self::validate_response(response).await?;
+        let response = self.with_retries(true, || self.client.get(url)).await?;
 
         Ok(response.json().await?)
     }
 
-    async fn validate_response(response: Response) -> anyhow::Result<Response> {
+    async fn with_retries(
+        &self,
+        retry: bool,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, TxSitterError> {
+        let mut attempt = 0;
+
+        loop {
+            match self.send(request()).await {
+                Err(err) if retry && err.is_retryable() && attempt < self.options.max_retries => {
+                    let backoff = self
+                        .options
+                        .retry_backoff
+                        .saturating_mul(2_u32.saturating_pow(attempt));
+                    tracing::warn!(%err, attempt, ?backoff, "Retrying tx-sitter request");
+
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, TxSitterError> {
+        let response = request.send().await?;
+
+        Self::validate_response(response).await
+    }
+
+    async fn validate_response(response: Response) -> Result<Response, TxSitterError> {
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await?;
 
             tracing::error!("Response failed with status {} - {}", status, body);
-            return Err(anyhow::anyhow!(
-                "Response failed with status {status} - {body}"
-            ));
+            return Err(TxSitterError::from_response(status, body));
         }
 
         Ok(response)
     }
 
+    /// Retried only when `req` has a `tx_id`, which the tx-sitter dedupes
+    /// on. A retry of a request that got through fails, so callers should
+    /// look the id up when sending it fails.
     #[instrument(skip(self))]
-    pub async fn send_tx(&self, req: &SendTxRequest) -> anyhow::Result<SendTxResponse> {
-        self.json_post(&format!("{}/tx", self.url), req).await
+    pub async fn send_tx(&self, req: &SendTxRequest) -> Result<SendTxResponse, TxSitterError> {
+        self.json_post(&format!("{}/tx", self.url), req, req.tx_id.is_some())
+            .await
     }
 
     #[instrument(skip(self))]
-    pub async fn get_tx(&self, tx_id: &str) -> anyhow::Result<GetTxResponse> {
+    pub async fn get_tx(&self, tx_id: &str) -> Result<GetTxResponse, TxSitterError> {
         self.json_get(&format!("{}/tx/{}", self.url, tx_id)).await
     }
 
     #[instrument(skip(self))]
-    pub async fn get_txs(&self) -> anyhow::Result<Vec<GetTxResponse>> {
+    pub async fn get_txs(&self) -> Result<Vec<GetTxResponse>, TxSitterError> {
         let url = format!("{}/txs", self.url);
 
         self.json_get(&url).await
@@ -75,20 +180,243 @@ impl TxSitterClient {
     pub async fn get_txs_by_status(
         &self,
         tx_status: TxStatus,
-    ) -> anyhow::Result<Vec<GetTxResponse>> {
+    ) -> Result<Vec<GetTxResponse>, TxSitterError> {
         let url = format!("{}/txs?status={}", self.url, tx_status);
 
         self.json_get(&url).await
     }
 
     #[instrument(skip(self))]
-    pub async fn get_unsent_txs(&self) -> anyhow::Result<Vec<GetTxResponse>> {
+    pub async fn get_unsent_txs(&self) -> Result<Vec<GetTxResponse>, TxSitterError> {
         let url = format!("{}/txs?unsent=true", self.url);
 
         self.json_get(&url).await
     }
 
+    /// Raises the priority of a transaction that isn't mined yet.
+    #[instrument(skip(self))]
+    pub async fn update_tx_priority(
+        &self,
+        tx_id: &str,
+        priority: TransactionPriority,
+    ) -> Result<(), TxSitterError> {
+        let url = format!("{}/tx/{}/priority", self.url, tx_id);
+
+        self.with_retries(true, || {
+            self.client
+                .post(&url)
+                .json(&UpdatePriorityRequest { priority })
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_relayer(&self) -> Result<RelayerInfo, TxSitterError> {
+        self.json_get(&format!("{}/relayer", self.url)).await
+    }
+
+    /// The relayer's balance, read through the RPC passthrough.
+    #[instrument(skip(self))]
+    pub async fn get_balance(&self, address: Address) -> Result<U256, TxSitterError> {
+        let provider = Provider::new(self.rpc_client()?);
+
+        Ok(provider.get_balance(address, None).await?)
+    }
+
+    /// Drops the relayer's unsent transactions and resyncs its nonce with
+    /// the chain, to get it going again when a nonce is stuck.
+    #[instrument(skip(self))]
+    pub async fn reset_relayer(&self, relayer_id: &str) -> Result<(), TxSitterError> {
+        let url = format!("{}/relayer/{}/reset", self.url, relayer_id);
+
+        self.with_retries(false, || self.client.post(&url)).await?;
+
+        Ok(())
+    }
+
     pub fn rpc_url(&self) -> String {
         format!("{}/rpc", self.url.clone())
     }
+
+    /// The tx-sitter's JSON-RPC passthrough to the relayer's chain, with the
+    /// client's timeouts and API token.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the url isn't valid.
+    pub fn rpc_client(&self) -> Result<Http, TxSitterError> {
+        Ok(Http::new_with_client(
+            Url::parse(&self.rpc_url())?,
+            self.client.clone(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use axum::extract::{Path, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use ethers::types::{Address, U256};
+    use fake_chain::http::serve;
+
+    use super::*;
+
+    /// A tx-sitter that fails the first `failures` requests to `/tx` with a
+    /// 503, counting them all.
+    fn stub(failures: u32) -> (String, Arc<AtomicU32>) {
+        let requests = Arc::new(AtomicU32::new(0));
+
+        async fn respond(
+            State((failures, requests)): State<(u32, Arc<AtomicU32>)>,
+            headers: HeaderMap,
+        ) -> Result<Json<SendTxResponse>, StatusCode> {
+            if requests.fetch_add(1, Ordering::SeqCst) < failures {
+                return Err(StatusCode::SERVICE_UNAVAILABLE);
+            }
+            match headers.get("authorization") {
+                Some(token) if token != "Bearer secret" => Err(StatusCode::UNAUTHORIZED),
+                _ => Ok(Json(SendTxResponse { tx_id: "1".into() })),
+            }
+        }
+
+        let router = Router::new()
+            .route("/tx", axum::routing::post(respond))
+            .route("/tx/:id", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/tx/:id/priority",
+                axum::routing::post(|Json(req): Json<UpdatePriorityRequest>| async move {
+                    match req.priority {
+                        TransactionPriority::Fast => StatusCode::OK,
+                        _ => StatusCode::BAD_REQUEST,
+                    }
+                }),
+            )
+            .route(
+                "/relayer",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "id": "relayer",
+                        "name": "bridge",
+                        "chainId": 1,
+                        "address": "0x928a514350a403e2f5e3288c102f6b1ccabeb37c",
+                        "nonce": 12,
+                        "currentNonce": 10,
+                        "enabled": true
+                    }))
+                }),
+            )
+            .route(
+                "/relayer/:id/reset",
+                axum::routing::post(|Path(id): Path<String>| async move {
+                    if id == "relayer" {
+                        StatusCode::OK
+                    } else {
+                        StatusCode::NOT_FOUND
+                    }
+                }),
+            )
+            .route(
+                "/rpc",
+                axum::routing::post(|Json(request): Json<serde_json::Value>| async move {
+                    Json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": "0x2a"
+                    }))
+                }),
+            )
+            .with_state((failures, requests.clone()));
+
+        (format!("http://{}", serve(router)), requests)
+    }
+
+    fn client(url: &str, api_token: Option<&str>) -> TxSitterClient {
+        TxSitterClient::with_options(url, ClientOptions {
+            retry_backoff: Duration::from_millis(1),
+            api_token: api_token.map(ToString::to_string),
+            ..ClientOptions::default()
+        })
+        .unwrap()
+    }
+
+    fn request(tx_id: Option<&str>) -> SendTxRequest {
+        SendTxRequest {
+            to: Address::zero(),
+            gas_limit: U256::from(100_000),
+            tx_id: tx_id.map(ToString::to_string),
+            ..SendTxRequest::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests() {
+        let (url, requests) = stub(2);
+
+        let sent = client(&url, None)
+            .send_tx(&request(Some("1")))
+            .await
+            .unwrap();
+
+        assert_eq!(sent.tx_id, "1");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn doesnt_retry_sends_without_an_id() {
+        let (url, requests) = stub(2);
+
+        let err = client(&url, None)
+            .send_tx(&request(None))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, TxSitterError::Server { .. }), "{err}");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn types_errors() {
+        let (url, _) = stub(0);
+
+        let err = client(&url, None).get_tx("2").await.unwrap_err();
+        assert!(matches!(err, TxSitterError::NotFound(_)), "{err}");
+        assert!(!err.is_retryable());
+
+        let err = client(&url, Some("wrong"))
+            .send_tx(&request(None))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::UNAUTHORIZED));
+        client(&url, Some("secret"))
+            .send_tx(&request(None))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn manages_the_relayer() {
+        let (url, _) = stub(0);
+        let client = client(&url, None);
+
+        let relayer = client.get_relayer().await.unwrap();
+        assert_eq!((relayer.nonce, relayer.current_nonce), (12, 10));
+
+        let priority = TransactionPriority::Regular.escalated().unwrap();
+        client.update_tx_priority("1", priority).await.unwrap();
+
+        client.reset_relayer("relayer").await.unwrap();
+        let err = client.reset_relayer("other").await.unwrap_err();
+        assert!(matches!(err, TxSitterError::NotFound(_)), "{err}");
+
+        assert_eq!(client.rpc_url(), format!("{url}/rpc"));
+        let balance = client.get_balance(relayer.address).await.unwrap();
+        assert_eq!(balance, U256::from(42));
+    }
 }
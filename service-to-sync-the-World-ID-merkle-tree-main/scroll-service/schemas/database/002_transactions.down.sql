@@ -0,0 +1,3 @@
+DROP TABLE transactions;
+
+DROP TYPE tx_status;
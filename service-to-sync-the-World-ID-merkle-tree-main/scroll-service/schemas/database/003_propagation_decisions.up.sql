@@ -0,0 +1,8 @@
+CREATE TABLE propagation_decisions
+  (
+    id          BIGSERIAL    PRIMARY KEY,
+    root        VARCHAR(66)  NOT NULL,
+    propagate   BOOLEAN      NOT NULL,
+    reason      TEXT         NOT NULL,
+    decided_at  TIMESTAMPTZ  NOT NULL
+  );
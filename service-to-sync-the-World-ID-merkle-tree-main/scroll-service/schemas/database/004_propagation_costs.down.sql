@@ -0,0 +1 @@
+DROP TABLE propagation_costs;
@@ -0,0 +1,14 @@
+CREATE TABLE propagation_costs
+  (
+    transaction_id       VARCHAR(256)   NOT NULL PRIMARY KEY REFERENCES transactions (transaction_id),
+    tx_hash              VARCHAR(66)    NOT NULL,
+    block_number         BIGINT         NOT NULL,
+    gas_used             NUMERIC(78, 0) NOT NULL,
+    effective_gas_price  NUMERIC(78, 0) NOT NULL,
+    message_value        NUMERIC(78, 0) NOT NULL,
+    message_fee          NUMERIC(78, 0) NULL,
+    refund               NUMERIC(78, 0) NULL,
+    recorded_at          TIMESTAMPTZ    NOT NULL
+  );
+
+CREATE INDEX propagation_costs_recorded_at_idx ON propagation_costs (recorded_at);
@@ -0,0 +1,3 @@
+DROP TABLE propagation_outbox;
+
+DROP TYPE outbox_status;
@@ -0,0 +1,21 @@
+CREATE TYPE outbox_status AS ENUM ('pending', 'claimed', 'sent', 'failed');
+
+-- Intents to propagate a root, written before anything is sent. The sender
+-- worker claims them and records the relayer's transaction id once sent.
+CREATE TABLE propagation_outbox
+  (
+    id              BIGSERIAL      PRIMARY KEY,
+    root            VARCHAR(66)    NOT NULL,
+    status          outbox_status  NOT NULL DEFAULT 'pending',
+    attempts        INTEGER        NOT NULL DEFAULT 0,
+    transaction_id  VARCHAR(256)   NULL,
+    last_error      TEXT           NULL,
+    created_at      TIMESTAMPTZ    NOT NULL,
+    claimed_at      TIMESTAMPTZ    NULL,
+    sent_at         TIMESTAMPTZ    NULL
+  );
+
+-- At most one propagation in flight
+CREATE UNIQUE INDEX propagation_outbox_open_idx
+  ON propagation_outbox ((status IN ('pending', 'claimed')))
+  WHERE status IN ('pending', 'claimed');
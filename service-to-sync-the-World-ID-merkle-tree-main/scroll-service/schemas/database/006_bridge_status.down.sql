@@ -0,0 +1,6 @@
+DROP TABLE bridge_status_history;
+
+ALTER TABLE service_status
+  ALTER COLUMN status TYPE VARCHAR(50) USING status::TEXT;
+
+DROP TYPE bridge_status;
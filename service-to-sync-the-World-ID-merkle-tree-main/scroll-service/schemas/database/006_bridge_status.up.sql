@@ -0,0 +1,17 @@
+CREATE TYPE bridge_status AS ENUM ('unsynced', 'pending', 'synced');
+
+-- Older versions wrote 'Synced' as text
+ALTER TABLE service_status
+  ALTER COLUMN status TYPE bridge_status USING lower(status)::bridge_status;
+
+-- Every change of the bridge status, with what caused it
+CREATE TABLE bridge_status_history
+  (
+    id           BIGSERIAL      PRIMARY KEY,
+    from_status  bridge_status  NULL,
+    to_status    bridge_status  NOT NULL,
+    cause        TEXT           NOT NULL,
+    changed_at   TIMESTAMPTZ    NOT NULL
+  );
+
+CREATE INDEX bridge_status_history_changed_at_idx ON bridge_status_history (changed_at);
@@ -0,0 +1,6 @@
+-- The rolled up days are lost, their transactions were already pruned
+DROP TABLE daily_propagation_stats;
+
+DROP INDEX transactions_created_at_idx;
+DROP INDEX propagation_decisions_decided_at_idx;
+DROP INDEX propagation_outbox_created_at_idx;
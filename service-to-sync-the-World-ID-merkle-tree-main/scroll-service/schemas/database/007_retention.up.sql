@@ -0,0 +1,20 @@
+-- Daily rollups of transactions pruned by the retention task. Amounts are in
+-- wei, latencies run from sending a transaction to recording its cost.
+CREATE TABLE daily_propagation_stats
+  (
+    day             DATE            PRIMARY KEY,
+    propagations    BIGINT          NOT NULL,
+    mined           BIGINT          NOT NULL,
+    l1_fee          NUMERIC(78, 0)  NOT NULL,
+    message_value   NUMERIC(78, 0)  NOT NULL,
+    message_fee     NUMERIC(78, 0)  NOT NULL,
+    refund          NUMERIC(78, 0)  NOT NULL,
+    latency_p50_ms  BIGINT          NULL,
+    latency_p90_ms  BIGINT          NULL,
+    latency_p99_ms  BIGINT          NULL,
+    rolled_up_at    TIMESTAMPTZ     NOT NULL
+  );
+
+CREATE INDEX transactions_created_at_idx ON transactions (created_at);
+CREATE INDEX propagation_decisions_decided_at_idx ON propagation_decisions (decided_at);
+CREATE INDEX propagation_outbox_created_at_idx ON propagation_outbox (created_at);
@@ -0,0 +1,12 @@
+-- Failed transactions go back to being recorded as mined
+ALTER TABLE transactions ALTER COLUMN status DROP DEFAULT;
+ALTER TYPE tx_status RENAME TO tx_status_old;
+
+CREATE TYPE tx_status AS ENUM ('pending', 'mined', 'finalized');
+
+ALTER TABLE transactions
+  ALTER COLUMN status TYPE tx_status
+    USING (CASE WHEN status = 'failed' THEN 'mined' ELSE status::text END)::tx_status,
+  ALTER COLUMN status SET DEFAULT 'pending';
+
+DROP TYPE tx_status_old;
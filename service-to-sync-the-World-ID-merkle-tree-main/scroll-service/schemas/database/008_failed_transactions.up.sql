@@ -0,0 +1,12 @@
+-- Reverted or lost propagations were recorded as mined. Recreating the type,
+-- since ADD VALUE can't run in a migration's transaction before Postgres 12.
+ALTER TABLE transactions ALTER COLUMN status DROP DEFAULT;
+ALTER TYPE tx_status RENAME TO tx_status_old;
+
+CREATE TYPE tx_status AS ENUM ('pending', 'mined', 'finalized', 'failed');
+
+ALTER TABLE transactions
+  ALTER COLUMN status TYPE tx_status USING status::text::tx_status,
+  ALTER COLUMN status SET DEFAULT 'pending';
+
+DROP TYPE tx_status_old;
@@ -0,0 +1,11 @@
+-- Intents were keyed by their id, which starts over with the database. A
+-- random key can't collide with one the relayer saw from an earlier database.
+-- Intents already sent keep the key they were sent with.
+ALTER TABLE propagation_outbox ADD COLUMN idempotency_key VARCHAR(64) NULL;
+
+UPDATE propagation_outbox SET idempotency_key = 'propagation-' || id;
+
+ALTER TABLE propagation_outbox ALTER COLUMN idempotency_key SET NOT NULL;
+
+CREATE UNIQUE INDEX propagation_outbox_idempotency_key_idx
+  ON propagation_outbox (idempotency_key);
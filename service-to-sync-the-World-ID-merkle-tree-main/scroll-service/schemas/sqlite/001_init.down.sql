@@ -0,0 +1,7 @@
+DROP TABLE daily_propagation_stats;
+DROP TABLE propagation_outbox;
+DROP TABLE propagation_costs;
+DROP TABLE propagation_decisions;
+DROP TABLE transactions;
+DROP TABLE bridge_status_history;
+DROP TABLE service_status;
@@ -0,0 +1,93 @@
+-- The SQLite schema mirrors the Postgres one. Enums are checked text,
+-- amounts in wei are decimal text and timestamps are RFC 3339 text.
+
+CREATE TABLE service_status
+  (
+    id           INTEGER  PRIMARY KEY,
+    status       TEXT     NOT NULL CHECK (status IN ('unsynced', 'pending', 'synced')),
+    last_synced  TEXT     NULL
+  );
+
+-- Every change of the bridge status, with what caused it
+CREATE TABLE bridge_status_history
+  (
+    id           INTEGER  PRIMARY KEY AUTOINCREMENT,
+    from_status  TEXT     NULL,
+    to_status    TEXT     NOT NULL,
+    cause        TEXT     NOT NULL,
+    changed_at   TEXT     NOT NULL
+  );
+
+CREATE INDEX bridge_status_history_changed_at_idx ON bridge_status_history (changed_at);
+
+CREATE TABLE transactions
+  (
+    transaction_id  TEXT  NOT NULL PRIMARY KEY,
+    status          TEXT  NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'mined', 'finalized', 'failed')),
+    created_at      TEXT  NOT NULL
+  );
+
+CREATE INDEX transactions_created_at_idx ON transactions (created_at);
+
+CREATE TABLE propagation_decisions
+  (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    root        TEXT     NOT NULL,
+    propagate   BOOLEAN  NOT NULL,
+    reason      TEXT     NOT NULL,
+    decided_at  TEXT     NOT NULL
+  );
+
+CREATE INDEX propagation_decisions_decided_at_idx ON propagation_decisions (decided_at);
+
+CREATE TABLE propagation_costs
+  (
+    transaction_id       TEXT     NOT NULL PRIMARY KEY REFERENCES transactions (transaction_id),
+    tx_hash              TEXT     NOT NULL,
+    block_number         INTEGER  NOT NULL,
+    gas_used             TEXT     NOT NULL,
+    effective_gas_price  TEXT     NOT NULL,
+    message_value        TEXT     NOT NULL,
+    message_fee          TEXT     NULL,
+    refund               TEXT     NULL,
+    recorded_at          TEXT     NOT NULL
+  );
+
+CREATE INDEX propagation_costs_recorded_at_idx ON propagation_costs (recorded_at);
+
+-- Intents to propagate a root, written before anything is sent
+CREATE TABLE propagation_outbox
+  (
+    id              INTEGER  PRIMARY KEY AUTOINCREMENT,
+    root            TEXT     NOT NULL,
+    status          TEXT     NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'claimed', 'sent', 'failed')),
+    attempts        INTEGER  NOT NULL DEFAULT 0,
+    transaction_id  TEXT     NULL,
+    last_error      TEXT     NULL,
+    created_at      TEXT     NOT NULL,
+    claimed_at      TEXT     NULL,
+    sent_at         TEXT     NULL
+  );
+
+-- At most one propagation in flight
+CREATE UNIQUE INDEX propagation_outbox_open_idx
+  ON propagation_outbox ((status IN ('pending', 'claimed')))
+  WHERE status IN ('pending', 'claimed');
+
+CREATE INDEX propagation_outbox_created_at_idx ON propagation_outbox (created_at);
+
+-- Daily rollups of transactions pruned by the retention task
+CREATE TABLE daily_propagation_stats
+  (
+    day             TEXT     PRIMARY KEY,
+    propagations    INTEGER  NOT NULL,
+    mined           INTEGER  NOT NULL,
+    l1_fee          TEXT     NOT NULL,
+    message_value   TEXT     NOT NULL,
+    message_fee     TEXT     NOT NULL,
+    refund          TEXT     NOT NULL,
+    latency_p50_ms  INTEGER  NULL,
+    latency_p90_ms  INTEGER  NULL,
+    latency_p99_ms  INTEGER  NULL,
+    rolled_up_at    TEXT     NOT NULL
+  );
@@ -0,0 +1,3 @@
+DROP INDEX propagation_outbox_idempotency_key_idx;
+
+ALTER TABLE propagation_outbox DROP COLUMN idempotency_key;
@@ -0,0 +1,9 @@
+-- Intents were keyed by their id, which starts over with the database file.
+-- A random key can't collide with one the relayer saw from an earlier file.
+-- Intents already sent keep the key they were sent with.
+ALTER TABLE propagation_outbox ADD COLUMN idempotency_key TEXT NOT NULL DEFAULT '';
+
+UPDATE propagation_outbox SET idempotency_key = 'propagation-' || id;
+
+CREATE UNIQUE INDEX propagation_outbox_idempotency_key_idx
+  ON propagation_outbox (idempotency_key);
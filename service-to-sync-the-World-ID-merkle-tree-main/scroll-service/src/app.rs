@@ -1,53 +1,153 @@
+use std::fmt;
 use std::sync::Arc;
 
-use tracing::{info, instrument};
-use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{info, instrument, warn};
+
+use crate::config::{Config, ConfigDiff};
 use crate::contracts::ScrollBridge;
-use crate::database::Database;
-use crate::database::query::DatabaseQuery;
+use crate::database::{self, BridgeStore};
 use crate::processor::{Processor, BridgeProcessor};
 use crate::ethereum::Ethereum;
+use crate::leader::LeaderElection;
 use crate::server::data::ServerStatusResponse;
+use crate::task_monitor::balance::BalanceMonitor;
+use crate::task_monitor::deferral::GasDeferral;
+use crate::task_monitor::policy::PropagationTracker;
+
+/// Why the service is running read-only. A read-only service keeps serving
+/// status, roots and metrics, but never sends transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "reason", content = "detail")]
+pub enum ReadOnlyReason {
+    /// `app.read_only` is set
+    Configured,
+    /// There's no `[relayer]` section
+    NoRelayer,
+    /// The relayer client failed to initialize
+    RelayerUnavailable(String),
+    /// A contract the bridge points at has no code deployed
+    MissingContract(String),
+}
+
+impl fmt::Display for ReadOnlyReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Configured => write!(f, "read-only mode is configured"),
+            Self::NoRelayer => write!(f, "no relayer is configured"),
+            Self::RelayerUnavailable(err) => write!(f, "relayer is unavailable: {err}"),
+            Self::MissingContract(contract) => write!(f, "no code deployed for {contract}"),
+        }
+    }
+}
 
 pub struct App {
-    pub config: Config,
-    pub database: Arc<Database>,
-    pub bridge_processor: Arc<dyn Processor>,
+    /// The effective config. Reloadable sections are swapped in on reload,
+    /// so read it on every use rather than caching values.
+    pub config:              watch::Sender<Config>,
+    pub database:            Arc<dyn BridgeStore>,
+    pub bridge_processor:    Arc<dyn Processor>,
+    pub propagation_tracker: Arc<PropagationTracker>,
+    pub gas_deferral:        Arc<GasDeferral>,
+    pub balance_monitor:     Arc<BalanceMonitor>,
+    /// Set when the service started read-only, either because it was
+    /// configured to or because of a recoverable startup problem
+    pub read_only:           Option<ReadOnlyReason>,
+    /// Whether this replica sends and tracks transactions
+    pub leadership:          Arc<LeaderElection>,
 }
 
 use crate::server::error::Error as ServerError;
 
+#[cfg(test)]
+impl App {
+    /// An app around the given store and processor, for testing the task
+    /// loops.
+    pub fn for_tests(
+        config: Config,
+        database: Arc<dyn BridgeStore>,
+        bridge_processor: Arc<dyn Processor>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            database,
+            bridge_processor,
+            propagation_tracker: Arc::new(PropagationTracker::default()),
+            gas_deferral: Arc::new(GasDeferral::new(config.gas_deferral.clone())),
+            balance_monitor: Arc::new(BalanceMonitor::new(config.balance.clone())),
+            read_only: None,
+            leadership: Arc::new(LeaderElection::new(config.leader_election.clone())),
+            config: watch::channel(config).0,
+        })
+    }
+}
+
 impl App {
     /// # Errors
     /// Will return `Err` if the internal Ethereum handler errors
     ///
     #[instrument(name = "App::new", level = "debug", skip_all)]
     pub async fn new(config: Config) -> anyhow::Result<Arc<Self>> {
-        let db = Database::new(&config.database).await?;
-        let database = Arc::new(db);
-        let ethereum = Ethereum::new(&config).await?;
+        let database = database::connect(&config.database).await?;
+        let (config_sender, config_receiver) = watch::channel(config.clone());
+        let ethereum = Ethereum::new(&config, config_receiver).await?;
         let scroll_bridge = Arc::new(ScrollBridge::new(&config, ethereum.clone()).await?);
+        let read_only = scroll_bridge.read_only_reason().cloned();
+        if let Some(reason) = &read_only {
+            warn!(%reason, "Starting in read-only mode, no transactions will be sent");
+        }
         let bridge_processor = Arc::new(
             BridgeProcessor::new(
                 ethereum.clone(),
-                scroll_bridge.clone()   
+                scroll_bridge.clone()
             )
             .await?
         );
+        let gas_deferral = Arc::new(GasDeferral::new(config.gas_deferral.clone()));
+        let balance_monitor = Arc::new(BalanceMonitor::new(config.balance.clone()));
         let app = Arc::new(Self {
-            config,
+            config: config_sender,
             database,
-            bridge_processor
+            bridge_processor,
+            propagation_tracker: Arc::new(PropagationTracker::default()),
+            gas_deferral,
+            balance_monitor,
+            read_only,
+            leadership: Arc::new(LeaderElection::new(config.leader_election.clone())),
         });
         Ok(app)
     }
 
+    /// Applies the reloadable parts of a newly loaded config and reports the
+    /// changes that only take effect after a restart.
+    pub fn reload_config(&self, new: &Config) -> ConfigDiff {
+        let current = self.config.borrow().clone();
+        let diff = current.diff(new);
+
+        if diff.is_empty() {
+            info!("Config reloaded, nothing changed");
+            return diff;
+        }
+
+        self.gas_deferral.set_config(new.gas_deferral.clone());
+        self.balance_monitor.set_config(new.balance.clone());
+        self.config.send_replace(current.with_reloadable(new));
+
+        if !diff.reloadable.is_empty() {
+            info!(applied = ?diff.reloadable, "Config reloaded");
+        }
+        if !diff.restart_required.is_empty() {
+            warn!(sections = ?diff.restart_required, "Config changes require a restart to take effect");
+        }
+
+        diff
+    }
+
     /// Initializes the server state.
     #[instrument(level = "debug", skip(self))]
     pub async fn initialize_server(&self) -> anyhow::Result<()> {
         if !self.database.server_initialized().await? {
             self.database.initialize_server().await?;
-            self.database.mark_status_as_unsynced().await?;
         }
         info!("DB initialized");
         Ok(())
@@ -58,7 +158,12 @@ impl App {
             Some(status) => status,
             None => return Err(ServerError::UNITIALIZED),
         };
-        Ok(status.into())
+        let mut response = ServerStatusResponse::from(status);
+        response.deferral = self.gas_deferral.status().await;
+        response.funds = self.balance_monitor.status().await;
+        response.read_only = self.read_only.clone();
+        response.leader = Some(self.leadership.status());
+        response.relayer = self.balance_monitor.relayer().await;
+        Ok(response)
     }
-    
 }
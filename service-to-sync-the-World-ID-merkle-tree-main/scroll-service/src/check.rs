@@ -0,0 +1,524 @@
+//! `scroll-service check-config`: validates a config file and the services it
+//! points at before the bridge is deployed with it.
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::providers::Middleware;
+use ethers::types::{Address, H256};
+use oz_api::OzApi;
+use serde::Serialize;
+use sqlx::pool::PoolOptions;
+use sqlx::{Postgres, Sqlite};
+use tx_sitter_client::{TxSitterClient, TxSitterError};
+
+use crate::config::{load_config, Config, NetworkConfig, RelayerConfig};
+use crate::contracts::abi::ScrollStateBridge;
+use crate::contracts::{propagate_root_transaction, simulation_error, verify_code_hash};
+use crate::database::Backend;
+use crate::ethereum::ReadProvider;
+
+/// Upper bound on any single connectivity check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Known (L1, Scroll) chain id pairs
+const CHAIN_PAIRS: &[(u64, u64)] = &[
+    // Ethereum mainnet, Scroll
+    (1, 534_352),
+    // Sepolia, Scroll Sepolia
+    (11_155_111, 534_351),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "PASS"),
+            Self::Warn => write!(f, "WARN"),
+            Self::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name:   &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CheckReport {
+    pub checks: Vec<Check>,
+}
+
+impl CheckReport {
+    fn pass(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Pass, detail);
+    }
+
+    fn warn(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Warn, detail);
+    }
+
+    fn fail(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Fail, detail);
+    }
+
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    /// Whether no check failed. Warnings don't fail the report.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Fail)
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}  {:<24} {}", check.status, check.name, check.detail)?;
+        }
+
+        let count = |status| self.checks.iter().filter(|c| c.status == status).count();
+        write!(
+            f,
+            "\n{} passed, {} warnings, {} failed",
+            count(CheckStatus::Pass),
+            count(CheckStatus::Warn),
+            count(CheckStatus::Fail)
+        )
+    }
+}
+
+/// Loads the config at `path` and checks the providers, contracts, relayer
+/// and database it refers to. Checks that depend on a failed one are skipped.
+pub async fn check_config(path: &Path) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    let config = match load_config(Some(path)) {
+        Ok(config) => {
+            report.pass("config", format!("parsed {}", path.display()));
+            config
+        }
+        Err(err) => {
+            report.fail("config", format!("{err:#}"));
+            return report;
+        }
+    };
+
+    let providers = check_providers(&config, &mut report).await;
+
+    if let Some((l1, l2)) = providers {
+        check_contracts(&config, &l1, &l2, &mut report).await;
+    }
+
+    check_relayer(&config, &mut report).await;
+    check_database(&config, &mut report).await;
+
+    report
+}
+
+async fn check_providers(
+    config: &Config,
+    report: &mut CheckReport,
+) -> Option<(Arc<ReadProvider>, Arc<ReadProvider>)> {
+    let Some(providers) = &config.providers else {
+        report.fail("providers", "missing [providers] section");
+        return None;
+    };
+
+    let l1 = connect(
+        "l1_provider",
+        providers.l1_network_provider.expose(),
+        report,
+    )
+    .await;
+    let l2 = connect(
+        "l2_provider",
+        providers.l2_network_provider.expose(),
+        report,
+    )
+    .await;
+    let (l1, l2) = (l1?, l2?);
+
+    let (status, detail) = chain_id_status(
+        l1.chain_id.as_u64(),
+        l2.chain_id.as_u64(),
+        config.network.as_ref(),
+    );
+    report.push("chain_ids", status, detail);
+
+    Some((Arc::new(l1), Arc::new(l2)))
+}
+
+async fn connect(name: &'static str, url: &str, report: &mut CheckReport) -> Option<ReadProvider> {
+    let url = match url.parse() {
+        Ok(url) => url,
+        Err(err) => {
+            report.fail(name, format!("invalid url: {err}"));
+            return None;
+        }
+    };
+
+    // Chain ids are checked separately, so that a mismatch is reported
+    // alongside the rest
+    match with_timeout(ReadProvider::new(url, None)).await {
+        Ok(provider) => {
+            report.pass(name, format!("connected, chain id {}", provider.chain_id));
+            Some(provider)
+        }
+        Err(err) => {
+            report.fail(name, err);
+            None
+        }
+    }
+}
+
+/// Checks the chain ids against the pinned ones, or against the known pairs
+/// if none are pinned.
+fn chain_id_status(l1: u64, l2: u64, network: Option<&NetworkConfig>) -> (CheckStatus, String) {
+    let pins = network.map_or((None, None), |network| {
+        (network.l1_chain_id, network.l2_chain_id)
+    });
+    if pins == (None, None) {
+        return chain_pair_status(l1, l2);
+    }
+
+    let mismatches: Vec<String> = [("L1", l1, pins.0), ("L2", l2, pins.1)]
+        .into_iter()
+        .filter_map(|(layer, actual, pinned)| {
+            pinned
+                .filter(|pinned| *pinned != actual)
+                .map(|pinned| format!("{layer} is on chain {actual}, {pinned} is pinned"))
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        (
+            CheckStatus::Pass,
+            format!("L1 {l1} and L2 {l2} match the pinned chain ids"),
+        )
+    } else {
+        (CheckStatus::Fail, mismatches.join(", "))
+    }
+}
+
+fn chain_pair_status(l1: u64, l2: u64) -> (CheckStatus, String) {
+    if let Some((_, expected)) = CHAIN_PAIRS.iter().find(|(known, _)| *known == l1) {
+        return if l2 == *expected {
+            (CheckStatus::Pass, format!("L1 {l1} is paired with L2 {l2}"))
+        } else {
+            (
+                CheckStatus::Fail,
+                format!("L1 {l1} expects L2 chain id {expected}, got {l2}"),
+            )
+        };
+    }
+
+    if CHAIN_PAIRS.iter().any(|(known_l1, _)| *known_l1 == l2)
+        || CHAIN_PAIRS.iter().any(|(_, known_l2)| *known_l2 == l1)
+    {
+        return (
+            CheckStatus::Fail,
+            format!("L1 {l1} and L2 {l2} look swapped"),
+        );
+    }
+
+    (
+        CheckStatus::Warn,
+        format!("unknown chain pair L1 {l1}, L2 {l2}"),
+    )
+}
+
+async fn check_contracts(
+    config: &Config,
+    l1: &Arc<ReadProvider>,
+    l2: &Arc<ReadProvider>,
+    report: &mut CheckReport,
+) {
+    let Some(network) = &config.network else {
+        report.fail("bridge_code", "missing [network] section");
+        return;
+    };
+
+    let address = network.scroll_bridge_address;
+    if !has_code(
+        "bridge_code",
+        l1,
+        address,
+        network.scroll_bridge_code_hash,
+        report,
+    )
+    .await
+    {
+        return;
+    }
+
+    let bridge = ScrollStateBridge::new(address, l1.clone());
+
+    match with_timeout(bridge.scroll_world_id_address().call()).await {
+        Ok(scroll_world_id) => {
+            has_code(
+                "scroll_world_id_code",
+                l2,
+                scroll_world_id,
+                network.scroll_world_id_code_hash,
+                report,
+            )
+            .await;
+        }
+        Err(err) => report.fail("scroll_world_id_code", err),
+    }
+
+    match with_timeout(bridge.world_id_address().call()).await {
+        Ok(world_id) => {
+            has_code(
+                "world_id_code",
+                l1,
+                world_id,
+                network.world_id_code_hash,
+                report,
+            )
+            .await;
+        }
+        Err(err) => report.fail("world_id_code", err),
+    }
+
+    let Some(relayer) = &config.relayer else {
+        report.fail("relayer_permissions", "missing [relayer] section");
+        return;
+    };
+    let relayer = relayer.address();
+
+    // `propagateRoot` is open to anyone, so not owning the bridge only
+    // matters for admin calls
+    match with_timeout(bridge.owner().call()).await {
+        Ok(owner) if owner == relayer => {
+            report.pass("owner", format!("relayer {owner:?} owns the bridge"))
+        }
+        Ok(owner) => report.warn(
+            "owner",
+            format!("bridge is owned by {owner:?}, not the relayer {relayer:?}"),
+        ),
+        Err(err) => report.fail("owner", err),
+    }
+
+    let mut tx = propagate_root_transaction(&bridge);
+    tx.set_from(relayer);
+    match with_timeout(async { l1.call(&tx, None).await.map_err(simulation_error) }).await {
+        Ok(_) => report.pass(
+            "relayer_permissions",
+            format!("{relayer:?} can call propagateRoot"),
+        ),
+        Err(err) => report.fail("relayer_permissions", err),
+    }
+}
+
+async fn has_code(
+    name: &'static str,
+    provider: &ReadProvider,
+    address: Address,
+    code_hash: Option<H256>,
+    report: &mut CheckReport,
+) -> bool {
+    match with_timeout(provider.get_code(address, None)).await {
+        Ok(code) if code.as_ref().is_empty() => {
+            report.fail(name, format!("no contract code at {address:?}"));
+            false
+        }
+        Ok(code) => {
+            if let Err(err) = verify_code_hash(name, address, &code, code_hash) {
+                report.fail(name, err.to_string());
+                return false;
+            }
+            report.pass(name, format!("{} bytes at {address:?}", code.len()));
+            true
+        }
+        Err(err) => {
+            report.fail(name, err);
+            false
+        }
+    }
+}
+
+async fn check_relayer(config: &Config, report: &mut CheckReport) {
+    match &config.relayer {
+        None => report.fail("relayer", "missing [relayer] section"),
+        Some(RelayerConfig::TxSitter(tx_sitter)) => {
+            let client = match TxSitterClient::with_options(
+                &tx_sitter.tx_sitter_url,
+                tx_sitter.client_options(),
+            ) {
+                Ok(client) => client,
+                Err(err) => return report.fail("relayer", err.to_string()),
+            };
+            let result = with_timeout(async {
+                let relayer = client.get_relayer().await?;
+                let unsent = client.get_unsent_txs().await?.len();
+                Ok::<_, TxSitterError>((relayer, unsent))
+            })
+            .await;
+
+            match result {
+                Ok((relayer, _)) if relayer.address != tx_sitter.tx_sitter_address => report.fail(
+                    "relayer",
+                    format!(
+                        "tx-sitter relayer {} is {:?}, not tx_sitter_address {:?}",
+                        relayer.name, relayer.address, tx_sitter.tx_sitter_address
+                    ),
+                ),
+                Ok((relayer, _)) if !relayer.enabled => {
+                    report.fail(
+                        "relayer",
+                        format!("tx-sitter relayer {} is disabled", relayer.name),
+                    );
+                }
+                Ok((relayer, unsent)) => report.pass(
+                    "relayer",
+                    format!(
+                        "tx-sitter relayer {} reachable at nonce {}, {unsent} unsent txs",
+                        relayer.name, relayer.nonce
+                    ),
+                ),
+                Err(err) => report.fail("relayer", err),
+            }
+        }
+        Some(RelayerConfig::OzDefender(oz)) => {
+            let result = with_timeout(async {
+                let oz_api = if oz.oz_api_key.is_empty() && oz.oz_api_secret.is_empty() {
+                    OzApi::without_auth(&oz.oz_api_url)?
+                } else {
+                    OzApi::new(&oz.oz_api_url, &oz.oz_api_key, &oz.oz_api_secret).await?
+                };
+                anyhow::Ok(oz_api.relayer().await?)
+            })
+            .await;
+
+            match result {
+                Ok(relayer) if relayer.paused => report.fail(
+                    "relayer",
+                    format!("OpenZeppelin relayer {} is paused", relayer.name),
+                ),
+                Ok(relayer) => report.pass(
+                    "relayer",
+                    format!(
+                        "OpenZeppelin relayer {} reachable on {} as {:?}",
+                        relayer.name, relayer.network, relayer.address
+                    ),
+                ),
+                Err(err) => report.fail("relayer", err),
+            }
+        }
+    }
+}
+
+async fn check_database(config: &Config, report: &mut CheckReport) {
+    let database = &config.database;
+
+    // Only connect, migrations are left to the service itself
+    let result = with_timeout(async {
+        let url = database.database.expose();
+        let one: i32 = match Backend::of(&database.database)? {
+            Backend::Postgres => {
+                let pool = PoolOptions::<Postgres>::new()
+                    .max_connections(1)
+                    .connect(url)
+                    .await?;
+                let one = sqlx::query_scalar("SELECT 1").fetch_one(&pool).await?;
+                pool.close().await;
+                one
+            }
+            Backend::Sqlite => {
+                let pool = PoolOptions::<Sqlite>::new()
+                    .max_connections(1)
+                    .connect(url)
+                    .await?;
+                let one = sqlx::query_scalar("SELECT 1").fetch_one(&pool).await?;
+                pool.close().await;
+                one
+            }
+        };
+        anyhow::Ok(one)
+    })
+    .await;
+
+    match result {
+        Ok(_) => report.pass("database", "connected"),
+        Err(err) => report.fail("database", err),
+    }
+}
+
+async fn with_timeout<T, E: fmt::Display>(
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, String> {
+    match tokio::time::timeout(CHECK_TIMEOUT, future).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(err)) => Err(format!("{err:#}")),
+        Err(_) => Err(format!("timed out after {CHECK_TIMEOUT:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_chain_ids() {
+        assert_eq!(chain_pair_status(1, 534_352).0, CheckStatus::Pass);
+        assert_eq!(chain_pair_status(11_155_111, 534_351).0, CheckStatus::Pass);
+        assert_eq!(chain_pair_status(1, 534_351).0, CheckStatus::Fail);
+        assert_eq!(chain_pair_status(534_352, 1).0, CheckStatus::Fail);
+        assert_eq!(chain_pair_status(31_337, 31_338).0, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn prefers_pinned_chain_ids() {
+        let network: NetworkConfig = serde_json::from_value(serde_json::json!({
+            "scroll_bridge_address": Address::zero(),
+            "l1_chain_id": 31_337,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            chain_id_status(31_337, 31_338, Some(&network)).0,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            chain_id_status(1, 534_352, Some(&network)).0,
+            CheckStatus::Fail
+        );
+        assert_eq!(chain_id_status(1, 534_352, None).0, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn warnings_dont_fail_the_report() {
+        let mut report = CheckReport::default();
+        report.pass("config", "ok");
+        report.warn("owner", "not the owner");
+        assert!(report.passed());
+
+        report.fail("database", "connection refused");
+        assert!(!report.passed());
+        assert!(report
+            .to_string()
+            .ends_with("1 passed, 1 warnings, 1 failed"));
+    }
+}
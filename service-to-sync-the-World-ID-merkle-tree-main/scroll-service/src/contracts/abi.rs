@@ -35,5 +35,24 @@ abigen!(
         function owner() public view virtual returns (address)
         function scrollWorldIDAddress() public view returns (address)
         function worldIDAddress() public view returns (address)
+        error AddressZero()
+        error CannotRenounceOwnership()
+        error GasLimitZero()
+        error OwnableInvalidOwner(address owner)
+        error OwnableUnauthorizedAccount(address account)
+    ]"#
+);
+
+abigen!(
+    ScrollMessenger,
+    r#"[
+        event SentMessage(address indexed sender, address indexed target, uint256 value, uint256 messageNonce, uint256 gasLimit, bytes message)
+    ]"#
+);
+
+abigen!(
+    L1MessageQueue,
+    r#"[
+        function estimateCrossDomainMessageFee(uint256 gasLimit) external view returns (uint256)
     ]"#
 );
@@ -1,24 +1,37 @@
 //! Functionality for interacting with smart contracts deployed on chain.
 pub mod abi;
 
-use anyhow::{anyhow, bail};
-use ethers::providers::Middleware;
-use ethers::types::U256;
-use tracing::{error, info, instrument};
+use anyhow::bail;
+use ethers::abi::AbiDecode;
+use ethers::contract::parse_log;
+use ethers::providers::{JsonRpcError, Middleware, ProviderError, RpcError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, TransactionReceipt, H256, U256};
+use ethers::utils::keccak256;
+use tracing::{error, info, instrument, warn};
 
-use self::abi::{ScrollStateBridge, ScrollWorldId, WorldId};
+use self::abi::{
+    L1MessageQueue, ScrollStateBridge, ScrollStateBridgeErrors, ScrollWorldId, SentMessageFilter,
+    WorldId,
+};
+use crate::app::ReadOnlyReason;
 use crate::config::Config;
-use crate::ethereum::{Ethereum, ReadProvider};
+use crate::error::BridgeError;
+use crate::ethereum::{Ethereum, ReadProvider, RevertReason, TxError};
+use crate::processor::cost::PropagationCost;
 use crate::utils::TransactionId;
 
 /// A structure representing the interface to the batch-based identity manager
 /// contract.
 #[derive(Debug)]
 pub struct ScrollBridge {
-    ethereum:       Ethereum,
-    bridge_abi:            ScrollStateBridge<ReadProvider>,
-    scroll_world_id_abi:  ScrollWorldId<ReadProvider>,
-    world_id_abi:   WorldId<ReadProvider>
+    ethereum:            Ethereum,
+    bridge_abi:          ScrollStateBridge<ReadProvider>,
+    scroll_world_id_abi: ScrollWorldId<ReadProvider>,
+    world_id_abi:        WorldId<ReadProvider>,
+    message_queue_abi:   Option<L1MessageQueue<ReadProvider>>,
+    messenger_address:   Option<Address>,
+    read_only:           Option<ReadOnlyReason>,
 }
 
 impl ScrollBridge {
@@ -52,7 +65,14 @@ impl ScrollBridge {
                 ?address,
                 "No contract code is deployed at the provided address."
             );
+            bail!("No contract code is deployed at the state bridge address {address:?}");
         }
+        verify_code_hash(
+            "ScrollStateBridge",
+            address,
+            &code,
+            network_config.scroll_bridge_code_hash,
+        )?;
 
         // Connect to the running batching contract.
         let bridge_abi = ScrollStateBridge::new(
@@ -60,11 +80,16 @@ impl ScrollBridge {
             ethereum.l1_provider().clone(),
         );
 
+        // `propagateRoot` is open to anyone, the owner is only needed for
+        // admin calls
         let owner = bridge_abi.owner().call().await?;
-        // if owner != ethereum.address() {
-        //     error!(?owner, signer = ?ethereum.address(), "Signer is not the owner of the state bridge contract.");
-        //     panic!("Cannot currently continue in read-only mode.")
-        // }
+        if let Some(relayer) = ethereum.address().filter(|relayer| *relayer != owner) {
+            warn!(
+                ?owner,
+                ?relayer,
+                "Relayer is not the owner of the state bridge contract."
+            );
+        }
 
         info!(
             ?address,
@@ -76,14 +101,26 @@ impl ScrollBridge {
         let scroll_world_id_address = bridge_abi.scroll_world_id_address().call().await?;
         info!(?scroll_world_id_address);
 
+        let mut read_only = ethereum.read_only_reason().cloned();
+
         let code = ethereum.l2_provider().get_code(scroll_world_id_address, None).await?;
         if code.as_ref().is_empty() {
             error!(
                 ?scroll_world_id_address,
                 "No contract code is deployed at the scroll world id address."
             );
-            panic!("Cannot continue")
+            read_only.get_or_insert_with(|| {
+                ReadOnlyReason::MissingContract(format!(
+                    "ScrollWorldID at {scroll_world_id_address:?}"
+                ))
+            });
         }
+        verify_code_hash(
+            "ScrollWorldID",
+            scroll_world_id_address,
+            &code,
+            network_config.scroll_world_id_code_hash,
+        )?;
 
         let scroll_world_id_abi = ScrollWorldId::new(
             scroll_world_id_address,
@@ -96,50 +133,191 @@ impl ScrollBridge {
         if code.as_ref().is_empty() {
             error!(
                 ?world_id_address,
-                "No contract code is deployed at the scroll world id address."
+                "No contract code is deployed at the world id address."
             );
-            panic!("Cannot continue");
+            read_only.get_or_insert_with(|| {
+                ReadOnlyReason::MissingContract(format!("WorldID at {world_id_address:?}"))
+            });
         }
+        verify_code_hash("WorldID", world_id_address, &code, network_config.world_id_code_hash)?;
         let world_id_abi = WorldId::new(
             world_id_address,
             ethereum.l1_provider().clone()
         );
 
+        let message_queue_abi = network_config
+            .l1_message_queue_address
+            .map(|address| L1MessageQueue::new(address, ethereum.l1_provider().clone()));
+        let messenger_address = network_config.l1_messenger_address;
+        if message_queue_abi.is_none() || messenger_address.is_none() {
+            warn!(
+                "No L1 message queue or messenger address configured, messaging fees and refunds \
+                 won't be tracked."
+            );
+        }
+
         let scroll_bridge = Self {
             ethereum,
             bridge_abi,
             scroll_world_id_abi,
-            world_id_abi
+            world_id_abi,
+            message_queue_abi,
+            messenger_address,
+            read_only,
         };
 
         Ok(scroll_bridge)
     }
 
+    /// Why the bridge can't propagate roots, if so.
+    #[must_use]
+    pub const fn read_only_reason(&self) -> Option<&ReadOnlyReason> {
+        self.read_only.as_ref()
+    }
+
+    /// Sends `propagateRoot`. Sending twice with the same `idempotency_key`
+    /// sends only once where the relayer supports it.
     #[instrument(level = "debug")]
-    pub async fn propagate_root(&self) -> anyhow::Result<TransactionId> {
-        let mut propagate_root_transaction: ethers::types::transaction::eip2718::TypedTransaction  = self.bridge_abi.propagate_root().tx;
-        let value_in_wei: U256 = ethers::utils::parse_ether("0.1").unwrap();
-        propagate_root_transaction.set_value(value_in_wei);
-        self.ethereum
-            .send_transaction(propagate_root_transaction, true)
-            .await
-            .map_err(|tx_err| anyhow!("{}", tx_err.to_string()))
+    pub async fn propagate_root(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<TransactionId, BridgeError> {
+        if let Some(reason) = &self.read_only {
+            return Err(BridgeError::ReadOnly(reason.clone()));
+        }
+
+        let mut propagate_root_transaction = propagate_root_transaction(&self.bridge_abi);
+
+        self.simulate(&mut propagate_root_transaction).await?;
+
+        Ok(self
+            .ethereum
+            .send_transaction(propagate_root_transaction, true, Some(idempotency_key))
+            .await?)
+    }
+
+    /// Works out what a mined propagation cost from its L1 receipt and the
+    /// `SentMessage` event emitted by the Scroll messenger.
+    ///
+    /// The messenger keeps the L2 execution fee and the forwarded value, and
+    /// refunds the rest of `msg.value` without emitting an event, so the
+    /// refund is whatever the `SentMessage` log and the fee don't account for.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn propagation_cost(
+        &self,
+        receipt: &TransactionReceipt,
+    ) -> Result<PropagationCost, BridgeError> {
+        let tx = self
+            .ethereum
+            .l1_provider()
+            .get_transaction(receipt.transaction_hash)
+            .await?
+            .ok_or_else(|| BridgeError::RpcUnavailable("missing mined transaction".to_string()))?;
+
+        let block_number = receipt.block_number.ok_or_else(|| {
+            BridgeError::RpcUnavailable("missing block number on receipt".to_string())
+        })?;
+
+        let sent_message = self
+            .messenger_address
+            .and_then(|messenger| find_sent_message(receipt, messenger, self.bridge_abi.address()));
+
+        // The fee is charged at the L2 base fee in effect before the block
+        let (message_fee, refund) = match (&self.message_queue_abi, &sent_message) {
+            (Some(message_queue), Some(sent_message)) => {
+                let fee = message_queue
+                    .estimate_cross_domain_message_fee(sent_message.gas_limit)
+                    .block(block_number.saturating_sub(1.into()))
+                    .call()
+                    .await?;
+                (Some(fee), Some(message_refund(tx.value, sent_message, fee)))
+            }
+            _ => (None, None),
+        };
+
+        Ok(PropagationCost {
+            tx_hash: receipt.transaction_hash,
+            block_number: block_number.as_u64(),
+            gas_used: receipt.gas_used.unwrap_or_default(),
+            effective_gas_price: receipt
+                .effective_gas_price
+                .or(tx.gas_price)
+                .unwrap_or_default(),
+            message_value: tx.value,
+            message_fee,
+            refund,
+        })
     }
 
     #[instrument(level = "debug", skip_all)]
-    pub async fn get_scroll_latest_root(&self) -> anyhow::Result<U256> {
+    pub async fn get_scroll_latest_root(&self) -> Result<U256, BridgeError> {
         let latest_root = self.scroll_world_id_abi.latest_root().call().await?;
         Ok(latest_root)
     }
 
     #[instrument(level = "debug", skip_all)]
-    pub async fn get_world_id_latest_root(&self) -> anyhow::Result<U256> {
+    pub async fn get_world_id_latest_root(&self) -> Result<U256, BridgeError> {
         let latest_root = self.world_id_abi.latest_root().call().await?;
         Ok(latest_root)
     }
     
+    /// Dry-runs `tx` from the relayer address with `eth_call` and
+    /// `eth_estimateGas`, so that reverts are caught before paying for them.
+    /// Fills in the gas limit if the transaction doesn't have one yet.
     #[instrument(level = "debug", skip_all)]
-    pub async fn check_sync_state(&self) -> anyhow::Result<bool>{
+    async fn simulate(&self, tx: &mut TypedTransaction) -> Result<(), TxError> {
+        if let Some(from) = self.ethereum.address() {
+            tx.set_from(from);
+        }
+        let provider = self.ethereum.l1_provider();
+
+        provider.call(tx, None).await.map_err(simulation_error)?;
+        let gas = provider
+            .estimate_gas(tx, None)
+            .await
+            .map_err(simulation_error)?;
+        info!(?gas, "Transaction simulated successfully");
+
+        if tx.gas().is_none() {
+            tx.set_gas(gas * 6 / 5);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn get_root_history_expiry(&self) -> Result<U256, BridgeError> {
+        let expiry = self.world_id_abi.get_root_history_expiry().call().await?;
+        Ok(expiry)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn get_l1_gas_price(&self) -> Result<U256, BridgeError> {
+        let gas_price = self.ethereum.l1_provider().get_gas_price().await?;
+        Ok(gas_price)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn get_l1_base_fee(&self) -> Result<U256, BridgeError> {
+        Ok(self.ethereum.l1_provider().next_base_fee().await?)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn get_relayer_balance(&self) -> Result<U256, BridgeError> {
+        let relayer = self
+            .ethereum
+            .address()
+            .ok_or(BridgeError::ReadOnly(ReadOnlyReason::NoRelayer))?;
+        let balance = self
+            .ethereum
+            .l1_provider()
+            .get_balance(relayer, None)
+            .await?;
+        Ok(balance)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn check_sync_state(&self) -> Result<bool, BridgeError> {
         let latest_root_scroll_world_id = match self.get_scroll_latest_root().await {
             Ok(id) => id,
             Err(err) => {
@@ -156,7 +334,7 @@ impl ScrollBridge {
     }
     
     #[instrument(level = "debug", skip_all)]
-    pub async fn is_root_mined(&self, root: U256) -> anyhow::Result<bool> {
+    pub async fn is_root_mined(&self, root: U256) -> Result<bool, BridgeError> {
         let (root_on_mainnet, ..) = self.world_id_abi.query_root(root).call().await?;
 
         if root_on_mainnet.is_zero() {
@@ -178,3 +356,204 @@ impl ScrollBridge {
         Ok(true)
     }
 }
+
+/// Fails if `code` was deployed but doesn't hash to the pinned `expected`
+/// hash. Missing code is left to the caller.
+pub(crate) fn verify_code_hash(
+    contract: &str,
+    address: Address,
+    code: &Bytes,
+    expected: Option<H256>,
+) -> anyhow::Result<()> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    if code.as_ref().is_empty() {
+        return Ok(());
+    }
+
+    let actual = H256::from(keccak256(code));
+    if actual != expected {
+        bail!(BridgeError::ContractMismatch(format!(
+            "{contract} at {address:?} has code hash {actual:?}, but {expected:?} is pinned in \
+             the config"
+        )));
+    }
+
+    Ok(())
+}
+
+/// The `SentMessage` event `messenger` emitted on behalf of `sender`, if any.
+fn find_sent_message(
+    receipt: &TransactionReceipt,
+    messenger: Address,
+    sender: Address,
+) -> Option<SentMessageFilter> {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == messenger)
+        .filter_map(|log| parse_log::<SentMessageFilter>(log.clone()).ok())
+        .find(|sent_message| sent_message.sender == sender)
+}
+
+/// What's left of `msg.value` once the messenger took the L2 execution fee
+/// and the value forwarded to L2.
+fn message_refund(value: U256, sent_message: &SentMessageFilter, fee: U256) -> U256 {
+    value.saturating_sub(sent_message.value).saturating_sub(fee)
+}
+
+/// A `propagateRoot` call, paying for the L2 execution with `msg.value`.
+pub(crate) fn propagate_root_transaction(
+    bridge_abi: &ScrollStateBridge<ReadProvider>,
+) -> TypedTransaction {
+    let mut tx: TypedTransaction = bridge_abi.propagate_root().tx;
+    let value_in_wei: U256 = ethers::utils::parse_ether("0.1").unwrap();
+    tx.set_value(value_in_wei);
+    tx
+}
+
+pub(crate) fn simulation_error(err: ProviderError) -> TxError {
+    match err
+        .as_error_response()
+        .and_then(JsonRpcError::as_revert_data)
+    {
+        Some(data) => {
+            let reason = decode_revert(&data);
+            error!(%reason, "Transaction simulation reverted");
+            TxError::Reverted(reason)
+        }
+        None => TxError::Simulation(err),
+    }
+}
+
+pub(crate) fn decode_revert(data: &Bytes) -> RevertReason {
+    match ScrollStateBridgeErrors::decode(data) {
+        Ok(ScrollStateBridgeErrors::AddressZero(_)) => RevertReason::AddressZero,
+        Ok(ScrollStateBridgeErrors::CannotRenounceOwnership(_)) => {
+            RevertReason::CannotRenounceOwnership
+        }
+        Ok(ScrollStateBridgeErrors::GasLimitZero(_)) => RevertReason::GasLimitZero,
+        Ok(ScrollStateBridgeErrors::OwnableInvalidOwner(e)) => {
+            RevertReason::OwnableInvalidOwner(e.owner)
+        }
+        Ok(ScrollStateBridgeErrors::OwnableUnauthorizedAccount(e)) => {
+            RevertReason::OwnableUnauthorizedAccount(e.account)
+        }
+        Ok(ScrollStateBridgeErrors::RevertString(message)) => RevertReason::Message(message),
+        Err(_) => RevertReason::Unknown(data.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::{AbiEncode, Token};
+    use ethers::contract::EthEvent;
+    use ethers::types::Log;
+
+    use super::abi::{AddressZero, OwnableUnauthorizedAccount};
+    use super::*;
+
+    #[test]
+    fn decodes_custom_errors() {
+        let data = Bytes::from(AddressZero.encode());
+        assert_eq!(decode_revert(&data), RevertReason::AddressZero);
+
+        let account = Address::repeat_byte(0x42);
+        let data = Bytes::from(OwnableUnauthorizedAccount { account }.encode());
+        assert_eq!(
+            decode_revert(&data),
+            RevertReason::OwnableUnauthorizedAccount(account)
+        );
+    }
+
+    #[test]
+    fn verifies_pinned_code_hash() {
+        let address = Address::repeat_byte(0x42);
+        let code = Bytes::from(vec![0x60, 0x80, 0x60, 0x40]);
+        let hash = H256::from(keccak256(&code));
+
+        assert!(verify_code_hash("WorldID", address, &code, None).is_ok());
+        assert!(verify_code_hash("WorldID", address, &code, Some(hash)).is_ok());
+        assert!(verify_code_hash("WorldID", address, &Bytes::new(), Some(hash)).is_ok());
+
+        let err = verify_code_hash("WorldID", address, &code, Some(H256::zero())).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BridgeError>(),
+            Some(BridgeError::ContractMismatch(_))
+        ));
+        assert!(err.to_string().contains("is pinned in the config"));
+    }
+
+    #[test]
+    fn decodes_revert_strings() {
+        let data = Bytes::from(
+            ScrollStateBridgeErrors::RevertString("Insufficient msg.value".to_string()).encode(),
+        );
+        assert_eq!(
+            decode_revert(&data),
+            RevertReason::Message("Insufficient msg.value".to_string())
+        );
+
+        let data = Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(decode_revert(&data), RevertReason::Unknown(data.clone()));
+    }
+
+    fn sent_message_log(emitter: Address, sender: Address, value: u64) -> Log {
+        let data = ethers::abi::encode(&[
+            Token::Uint(U256::from(value)),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::from(200_000)),
+            Token::Bytes(vec![]),
+        ]);
+        Log {
+            address: emitter,
+            topics: vec![
+                SentMessageFilter::signature(),
+                H256::from(sender),
+                H256::from(Address::repeat_byte(0x03)),
+            ],
+            data: data.into(),
+            ..Log::default()
+        }
+    }
+
+    #[test]
+    fn trusts_only_the_messenger() {
+        let messenger = Address::repeat_byte(0x01);
+        let bridge = Address::repeat_byte(0x02);
+        let mut receipt = TransactionReceipt {
+            logs: vec![
+                sent_message_log(Address::repeat_byte(0x66), bridge, 1),
+                sent_message_log(messenger, Address::repeat_byte(0x66), 2),
+            ],
+            ..TransactionReceipt::default()
+        };
+        assert!(find_sent_message(&receipt, messenger, bridge).is_none());
+
+        receipt.logs.push(sent_message_log(messenger, bridge, 3));
+        let sent_message = find_sent_message(&receipt, messenger, bridge).unwrap();
+        assert_eq!(sent_message.value, U256::from(3));
+        assert_eq!(sent_message.gas_limit, U256::from(200_000));
+    }
+
+    #[test]
+    fn refunds_what_the_messenger_did_not_keep() {
+        let messenger = Address::repeat_byte(0x01);
+        let bridge = Address::repeat_byte(0x02);
+        let receipt = TransactionReceipt {
+            logs: vec![sent_message_log(messenger, bridge, 1_000)],
+            ..TransactionReceipt::default()
+        };
+        let sent_message = find_sent_message(&receipt, messenger, bridge).unwrap();
+
+        assert_eq!(
+            message_refund(U256::from(10_000), &sent_message, U256::from(4_000)),
+            U256::from(5_000)
+        );
+        assert_eq!(
+            message_refund(U256::from(10_000), &sent_message, U256::from(20_000)),
+            U256::zero()
+        );
+    }
+}
@@ -0,0 +1,551 @@
+//! An in-memory store, so that the task loops can be tested without a
+//! database. It keeps the same rules as the SQL stores, such as a single
+//! open propagation, but no daily stats.
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::types::U256;
+use serde_json::json;
+
+use crate::database::store::BridgeStore;
+use crate::database::transaction::day_bounds;
+use crate::database::types::{
+    OutboxStatus, PropagationCostRecord, PropagationIntent, RetainedTable, ServerStatus, TxStatus,
+};
+use crate::database::Error;
+use crate::processor::cost::PropagationCost;
+use crate::processor::status::BridgeStatus;
+use crate::task_monitor::policy::PolicyDecision;
+
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    status:       Option<ServerStatus>,
+    history:      Vec<StatusChange>,
+    transactions: Vec<Transaction>,
+    costs:        Vec<PropagationCostRecord>,
+    decisions:    Vec<Decision>,
+    outbox:       Vec<OutboxEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusChange {
+    pub from:       Option<BridgeStatus>,
+    pub to:         BridgeStatus,
+    pub cause:      String,
+    pub changed_at: DateTime<Utc>,
+}
+
+struct Transaction {
+    id:         String,
+    status:     TxStatus,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub root:       U256,
+    pub decision:   PolicyDecision,
+    pub decided_at: DateTime<Utc>,
+}
+
+struct OutboxEntry {
+    id:              i64,
+    root:            String,
+    idempotency_key: String,
+    status:          OutboxStatus,
+    attempts:        i32,
+    transaction_id:  Option<String>,
+    last_error:      Option<String>,
+    created_at:      DateTime<Utc>,
+}
+
+impl MemoryStore {
+    /// An initialized store, as after the service first started.
+    #[must_use]
+    pub fn initialized() -> Self {
+        let store = Self::default();
+        store
+            .state()
+            .set_status(BridgeStatus::Unsynced, "initialized");
+        store
+    }
+
+    /// Every status change so far, oldest first.
+    #[must_use]
+    pub fn history(&self) -> Vec<StatusChange> {
+        self.state().history.clone()
+    }
+
+    /// Every policy decision so far, oldest first.
+    #[must_use]
+    pub fn decisions(&self) -> Vec<Decision> {
+        self.state().decisions.clone()
+    }
+
+    /// The status and relayer transaction of every intent, oldest first.
+    #[must_use]
+    pub fn outbox(&self) -> Vec<(OutboxStatus, Option<String>)> {
+        self.state()
+            .outbox
+            .iter()
+            .map(|entry| (entry.status, entry.transaction_id.clone()))
+            .collect()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl State {
+    fn set_status(&mut self, to: BridgeStatus, cause: &str) {
+        let now = Utc::now();
+        let from = self.status.as_ref().map(|status| status.status);
+        let last_synced = self.status.as_ref().and_then(|status| status.last_synced);
+
+        self.status = Some(ServerStatus {
+            status:      to,
+            last_synced: if to == BridgeStatus::Synced {
+                Some(now)
+            } else {
+                last_synced
+            },
+        });
+        if from != Some(to) {
+            self.history.push(StatusChange {
+                from,
+                to,
+                cause: cause.to_string(),
+                changed_at: now,
+            });
+        }
+    }
+
+    /// Like the SQL stores, status updates do nothing before the service
+    /// is initialized.
+    fn update_status(&mut self, to: BridgeStatus, cause: &str) {
+        if self.status.is_some() {
+            self.set_status(to, cause);
+        }
+    }
+
+    fn latest_transaction(&self) -> Option<&Transaction> {
+        self.transactions.iter().max_by_key(|tx| tx.created_at)
+    }
+
+    fn intent(&mut self, id: i64) -> Option<&mut OutboxEntry> {
+        self.outbox.iter_mut().find(|entry| entry.id == id)
+    }
+
+    /// The write times of the prunable rows of `table`, with their index.
+    fn prunable(&self, table: RetainedTable) -> Vec<(usize, DateTime<Utc>)> {
+        match table {
+            RetainedTable::Transactions => self
+                .transactions
+                .iter()
+                .enumerate()
+                .filter(|(_, tx)| tx.status != TxStatus::Pending)
+                .map(|(i, tx)| (i, tx.created_at))
+                .collect(),
+            RetainedTable::PropagationDecisions => self
+                .decisions
+                .iter()
+                .map(|d| d.decided_at)
+                .enumerate()
+                .collect(),
+            RetainedTable::PropagationOutbox => self
+                .outbox
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| {
+                    matches!(entry.status, OutboxStatus::Sent | OutboxStatus::Failed)
+                })
+                .map(|(i, entry)| (i, entry.created_at))
+                .collect(),
+            RetainedTable::BridgeStatusHistory => self
+                .history
+                .iter()
+                .map(|c| c.changed_at)
+                .enumerate()
+                .collect(),
+        }
+    }
+
+    fn row_json(&self, table: RetainedTable, index: usize) -> String {
+        let value = match table {
+            RetainedTable::Transactions => {
+                let tx = &self.transactions[index];
+                let cost = self.costs.iter().find(|c| c.transaction_id == tx.id);
+                json!({
+                    "transaction": {
+                        "transaction_id": tx.id,
+                        "status": <&str>::from(tx.status),
+                        "created_at": tx.created_at,
+                    },
+                    "cost": cost.map(|c| json!({
+                        "transaction_id": c.transaction_id,
+                        "tx_hash": format!("{:?}", c.cost.tx_hash),
+                        "block_number": c.cost.block_number,
+                        "gas_used": c.cost.gas_used.to_string(),
+                        "effective_gas_price": c.cost.effective_gas_price.to_string(),
+                        "message_value": c.cost.message_value.to_string(),
+                        "message_fee": c.cost.message_fee.map(|fee| fee.to_string()),
+                        "refund": c.cost.refund.map(|refund| refund.to_string()),
+                        "recorded_at": c.recorded_at,
+                    })),
+                })
+            }
+            RetainedTable::PropagationDecisions => {
+                let decision = &self.decisions[index];
+                json!({
+                    "id": index + 1,
+                    "root": format!("{:#x}", decision.root),
+                    "propagate": decision.decision.propagate,
+                    "reason": decision.decision.reason,
+                    "decided_at": decision.decided_at,
+                })
+            }
+            RetainedTable::PropagationOutbox => {
+                let entry = &self.outbox[index];
+                json!({
+                    "id": entry.id,
+                    "root": entry.root,
+                    "status": entry.status,
+                    "attempts": entry.attempts,
+                    "transaction_id": entry.transaction_id,
+                    "last_error": entry.last_error,
+                    "created_at": entry.created_at,
+                })
+            }
+            RetainedTable::BridgeStatusHistory => {
+                let change = &self.history[index];
+                json!({
+                    "id": index + 1,
+                    "from_status": change.from,
+                    "to_status": change.to,
+                    "cause": change.cause,
+                    "changed_at": change.changed_at,
+                })
+            }
+        };
+
+        value.to_string()
+    }
+}
+
+#[async_trait]
+impl BridgeStore for MemoryStore {
+    async fn server_initialized(&self) -> Result<bool, Error> {
+        Ok(self.state().status.is_some())
+    }
+
+    async fn initialize_server(&self) -> Result<(), Error> {
+        self.state()
+            .set_status(BridgeStatus::Unsynced, "initialized");
+        Ok(())
+    }
+
+    async fn get_service_status(&self) -> Result<Option<ServerStatus>, Error> {
+        Ok(self.state().status.clone())
+    }
+
+    async fn get_db_status(&self) -> Result<Option<BridgeStatus>, Error> {
+        Ok(self.state().status.as_ref().map(|status| status.status))
+    }
+
+    async fn mark_status_as_pending(&self, cause: &str) -> Result<(), Error> {
+        self.state().update_status(BridgeStatus::Pending, cause);
+        Ok(())
+    }
+
+    async fn mark_status_as_unsynced(&self, cause: &str) -> Result<(), Error> {
+        self.state().update_status(BridgeStatus::Unsynced, cause);
+        Ok(())
+    }
+
+    async fn mark_status_as_synced(&self, cause: &str) -> Result<(), Error> {
+        self.state().update_status(BridgeStatus::Synced, cause);
+        Ok(())
+    }
+
+    async fn get_last_transaction_status(&self) -> Result<Option<TxStatus>, Error> {
+        Ok(self.state().latest_transaction().map(|tx| tx.status))
+    }
+
+    async fn get_last_transaction_id(&self) -> Result<Option<String>, Error> {
+        Ok(self
+            .state()
+            .transactions
+            .iter()
+            .filter(|tx| tx.status == TxStatus::Pending)
+            .max_by_key(|tx| tx.created_at)
+            .map(|tx| tx.id.clone()))
+    }
+
+    async fn get_last_transaction_time(&self) -> Result<Option<DateTime<Utc>>, Error> {
+        Ok(self.state().latest_transaction().map(|tx| tx.created_at))
+    }
+
+    async fn update_transaction(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+    ) -> Result<(), Error> {
+        if let Some(tx) = self
+            .state()
+            .transactions
+            .iter_mut()
+            .find(|tx| tx.id == transaction_id)
+        {
+            tx.status = status;
+        }
+        Ok(())
+    }
+
+    async fn record_transaction_outcome(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+        cost: Option<&PropagationCost>,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        if let Some(cost) = cost {
+            if !state
+                .costs
+                .iter()
+                .any(|c| c.transaction_id == transaction_id)
+            {
+                state.costs.push(PropagationCostRecord {
+                    transaction_id: transaction_id.to_string(),
+                    cost:           cost.clone(),
+                    recorded_at:    Utc::now(),
+                });
+            }
+        }
+        if let Some(tx) = state
+            .transactions
+            .iter_mut()
+            .find(|tx| tx.id == transaction_id)
+        {
+            tx.status = status;
+        }
+        Ok(())
+    }
+
+    async fn get_propagation_costs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<PropagationCostRecord>, Error> {
+        let mut costs: Vec<_> = self
+            .state()
+            .costs
+            .iter()
+            .filter(|c| from.map_or(true, |from| c.recorded_at >= from))
+            .filter(|c| to.map_or(true, |to| c.recorded_at < to))
+            .cloned()
+            .collect();
+        costs.sort_by_key(|c| c.recorded_at);
+        Ok(costs)
+    }
+
+    async fn insert_propagation_decision(
+        &self,
+        root: U256,
+        decision: &PolicyDecision,
+    ) -> Result<(), Error> {
+        self.state().decisions.push(Decision {
+            root,
+            decision: decision.clone(),
+            decided_at: Utc::now(),
+        });
+        Ok(())
+    }
+
+    async fn enqueue_propagation(&self, root: U256) -> Result<Option<i64>, Error> {
+        let mut state = self.state();
+        let open = state
+            .outbox
+            .iter()
+            .any(|entry| matches!(entry.status, OutboxStatus::Pending | OutboxStatus::Claimed));
+        if open {
+            return Ok(None);
+        }
+
+        let id = state.outbox.last().map_or(1, |entry| entry.id + 1);
+        state.outbox.push(OutboxEntry {
+            id,
+            root: format!("{root:#x}"),
+            idempotency_key: PropagationIntent::new_key(),
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            transaction_id: None,
+            last_error: None,
+            created_at: Utc::now(),
+        });
+        state.update_status(BridgeStatus::Pending, "propagation queued");
+
+        Ok(Some(id))
+    }
+
+    async fn has_open_propagation(&self) -> Result<bool, Error> {
+        Ok(self
+            .state()
+            .outbox
+            .iter()
+            .any(|entry| matches!(entry.status, OutboxStatus::Pending | OutboxStatus::Claimed)))
+    }
+
+    async fn claim_propagation_intent(&self) -> Result<Option<PropagationIntent>, Error> {
+        let mut state = self.state();
+        let entry = state
+            .outbox
+            .iter_mut()
+            .find(|entry| matches!(entry.status, OutboxStatus::Pending | OutboxStatus::Claimed));
+
+        Ok(entry.map(|entry| {
+            entry.status = OutboxStatus::Claimed;
+            entry.attempts += 1;
+            PropagationIntent {
+                id:              entry.id,
+                root:            entry.root.clone(),
+                attempts:        entry.attempts,
+                idempotency_key: entry.idempotency_key.clone(),
+            }
+        }))
+    }
+
+    async fn record_intent_error(
+        &self,
+        intent_id: i64,
+        error: &str,
+        give_up: bool,
+    ) -> Result<(), Error> {
+        if let Some(entry) = self.state().intent(intent_id) {
+            entry.last_error = Some(error.to_string());
+            if give_up {
+                entry.status = OutboxStatus::Failed;
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_propagation_sent(
+        &self,
+        intent: &PropagationIntent,
+        transaction_id: &str,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        if !state.transactions.iter().any(|tx| tx.id == transaction_id) {
+            state.transactions.push(Transaction {
+                id:         transaction_id.to_string(),
+                status:     TxStatus::Pending,
+                created_at: Utc::now(),
+            });
+        }
+        if let Some(entry) = state.intent(intent.id) {
+            entry.status = OutboxStatus::Sent;
+            entry.transaction_id = Some(transaction_id.to_string());
+            entry.last_error = None;
+        }
+        Ok(())
+    }
+
+    async fn record_propagation_failed(
+        &self,
+        intent: &PropagationIntent,
+        error: &str,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        if let Some(entry) = state.intent(intent.id) {
+            entry.status = OutboxStatus::Failed;
+            entry.last_error = Some(error.to_string());
+        }
+        state.update_status(
+            BridgeStatus::Unsynced,
+            &format!("propagation failed: {error}"),
+        );
+        Ok(())
+    }
+
+    async fn oldest_prunable(
+        &self,
+        table: RetainedTable,
+        before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        Ok(self
+            .state()
+            .prunable(table)
+            .into_iter()
+            .map(|(_, at)| at)
+            .filter(|at| *at < before)
+            .min())
+    }
+
+    async fn prunable_rows(
+        &self,
+        table: RetainedTable,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<String>, Error> {
+        let state = self.state();
+        let mut rows = state.prunable(table);
+        rows.retain(|(_, at)| *at >= from && *at < to);
+        rows.sort_by_key(|(_, at)| *at);
+
+        Ok(rows
+            .into_iter()
+            .map(|(index, _)| state.row_json(table, index))
+            .collect())
+    }
+
+    async fn prune_day(&self, table: RetainedTable, day: NaiveDate) -> Result<u64, Error> {
+        let (from, to) = day_bounds(day);
+        let within = |at: DateTime<Utc>| at >= from && at < to;
+        let mut state = self.state();
+        let state = &mut *state;
+
+        let before = match table {
+            RetainedTable::Transactions => state.transactions.len(),
+            RetainedTable::PropagationDecisions => state.decisions.len(),
+            RetainedTable::PropagationOutbox => state.outbox.len(),
+            RetainedTable::BridgeStatusHistory => state.history.len(),
+        };
+        let after = match table {
+            RetainedTable::Transactions => {
+                let pruned: Vec<_> = state
+                    .transactions
+                    .iter()
+                    .filter(|tx| within(tx.created_at) && tx.status != TxStatus::Pending)
+                    .map(|tx| tx.id.clone())
+                    .collect();
+                state.costs.retain(|c| !pruned.contains(&c.transaction_id));
+                state.transactions.retain(|tx| !pruned.contains(&tx.id));
+                state.transactions.len()
+            }
+            RetainedTable::PropagationDecisions => {
+                state.decisions.retain(|d| !within(d.decided_at));
+                state.decisions.len()
+            }
+            RetainedTable::PropagationOutbox => {
+                state.outbox.retain(|entry| {
+                    !(within(entry.created_at)
+                        && matches!(entry.status, OutboxStatus::Sent | OutboxStatus::Failed))
+                });
+                state.outbox.len()
+            }
+            RetainedTable::BridgeStatusHistory => {
+                state.history.retain(|c| !within(c.changed_at));
+                state.history.len()
+            }
+        };
+
+        Ok((before - after) as u64)
+    }
+}
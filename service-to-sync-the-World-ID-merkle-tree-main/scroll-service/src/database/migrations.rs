@@ -0,0 +1,473 @@
+//! The schema migrations embedded in the binary, applied and reverted by the
+//! `scroll-service db` commands and checked on startup.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{bail, Error as ErrReport};
+use async_trait::async_trait;
+use serde::Serialize;
+use sqlx::migrate::{Migrate, MigrateDatabase, Migration, Migrator};
+use sqlx::{Pool, Postgres, Sqlite};
+
+use crate::config::DatabaseConfig;
+use crate::database::{self, Backend};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but from a different version of the migration file
+    Modified,
+    /// Applied by a newer version of the service
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub version:     i64,
+    pub description: String,
+    pub state:       MigrationState,
+}
+
+/// The embedded migrations compared to those applied to the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaStatus {
+    pub migrations: Vec<MigrationStatus>,
+    /// The migration that failed part way, if any
+    pub dirty:      Option<i64>,
+}
+
+impl SchemaStatus {
+    /// Whether the schema is the one this version of the service expects.
+    #[must_use]
+    pub fn is_current(&self) -> bool {
+        self.dirty.is_none()
+            && self
+                .migrations
+                .iter()
+                .all(|migration| migration.state == MigrationState::Applied)
+    }
+
+    /// The latest migration applied to the database.
+    #[must_use]
+    pub fn version(&self) -> Option<i64> {
+        self.migrations
+            .iter()
+            .filter(|migration| migration.state != MigrationState::Pending)
+            .map(|migration| migration.version)
+            .max()
+    }
+
+    /// The latest migration embedded in this version of the service.
+    #[must_use]
+    pub fn expected_version(&self) -> Option<i64> {
+        self.migrations
+            .iter()
+            .filter(|migration| migration.state != MigrationState::Unknown)
+            .map(|migration| migration.version)
+            .max()
+    }
+
+    /// Only compares the latest applied migration to the latest embedded
+    /// one, for a schema that's managed by hand.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the schema is dirty, behind, or ahead of this
+    /// version of the service.
+    pub fn ensure_version(&self) -> Result<(), ErrReport> {
+        if let Some(version) = self.dirty {
+            bail!("migration {version} failed part way, fix the database by hand");
+        }
+
+        match self.version().cmp(&self.expected_version()) {
+            Ordering::Less => bail!(
+                "database is not up to date, it's at version {} instead of {}, apply the \
+                 migrations with `scroll-service db migrate`",
+                self.version().unwrap_or(0),
+                self.expected_version().unwrap_or(0)
+            ),
+            Ordering::Greater => {
+                bail!("database version is newer than this version of the software, please update")
+            }
+            Ordering::Equal => Ok(()),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` describing the first problem found, unless the
+    /// schema is current.
+    pub fn ensure_current(&self) -> Result<(), ErrReport> {
+        if let Some(version) = self.dirty {
+            bail!("migration {version} failed part way, fix the database by hand");
+        }
+
+        let in_state = |state| {
+            self.migrations
+                .iter()
+                .filter(move |migration| migration.state == state)
+                .map(|migration| migration.version.to_string())
+                .collect::<Vec<_>>()
+        };
+        let unknown = in_state(MigrationState::Unknown);
+        if !unknown.is_empty() {
+            bail!(
+                "database has migrations {} applied, which are newer than this version of the \
+                 software, please update",
+                unknown.join(", ")
+            );
+        }
+        let modified = in_state(MigrationState::Modified);
+        if !modified.is_empty() {
+            bail!(
+                "migrations {} were applied from different files than the ones in this version of \
+                 the software",
+                modified.join(", ")
+            );
+        }
+        let pending = in_state(MigrationState::Pending);
+        if !pending.is_empty() {
+            bail!(
+                "database is not up to date, migrations {} are pending, apply them with \
+                 `scroll-service db migrate`",
+                pending.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SchemaStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for migration in &self.migrations {
+            let state = match migration.state {
+                MigrationState::Applied => "applied",
+                MigrationState::Pending => "pending",
+                MigrationState::Modified => "modified",
+                MigrationState::Unknown => "unknown",
+            };
+            writeln!(
+                f,
+                "{state:<8}  {:03}  {}",
+                migration.version, migration.description
+            )?;
+        }
+        if let Some(version) = self.dirty {
+            writeln!(
+                f,
+                "migration {version} failed part way, the database is dirty"
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Looks for the table sqlx records applied migrations in, without creating
+/// it like [`Migrate::ensure_migrations_table`] does.
+#[async_trait]
+pub(crate) trait MigrationsTable: sqlx::Database {
+    async fn has_migrations_table(pool: &Pool<Self>) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+impl MigrationsTable for Postgres {
+    async fn has_migrations_table(pool: &Pool<Self>) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await
+    }
+}
+
+#[async_trait]
+impl MigrationsTable for Sqlite {
+    async fn has_migrations_table(pool: &Pool<Self>) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = \
+             '_sqlx_migrations')",
+        )
+        .fetch_one(pool)
+        .await
+    }
+}
+
+/// Compares the migrations of `migrator` to those applied through `pool`.
+/// Only reads, so that `db status` and dry runs leave the database as is.
+pub(crate) async fn schema_status<DB>(
+    pool: &Pool<DB>,
+    migrator: &Migrator,
+) -> Result<SchemaStatus, ErrReport>
+where
+    DB: MigrationsTable,
+    DB::Connection: Migrate,
+{
+    let (dirty, mut applied) = if DB::has_migrations_table(pool).await? {
+        let mut conn = pool.acquire().await?;
+        let applied: HashMap<_, _> = conn
+            .list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|migration| (migration.version, migration.checksum))
+            .collect();
+        (conn.dirty_version().await?, applied)
+    } else {
+        // Never migrated, so nothing is applied
+        (None, HashMap::new())
+    };
+
+    let mut migrations: Vec<_> = migrator
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| MigrationStatus {
+            version:     migration.version,
+            description: migration.description.to_string(),
+            state:       match applied.remove(&migration.version) {
+                None => MigrationState::Pending,
+                Some(checksum) if checksum == migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::Modified,
+            },
+        })
+        .collect();
+    migrations.extend(applied.into_keys().map(|version| MigrationStatus {
+        version,
+        description: String::new(),
+        state: MigrationState::Unknown,
+    }));
+    migrations.sort_by_key(|migration| migration.version);
+
+    Ok(SchemaStatus { migrations, dirty })
+}
+
+/// # Errors
+///
+/// Will return `Err` if the database can't be connected to.
+pub async fn status(config: &DatabaseConfig) -> Result<SchemaStatus, ErrReport> {
+    match Backend::of(&config.database)? {
+        Backend::Postgres => {
+            let pool = database::connect_pool(config, false).await?;
+            schema_status(&pool, &database::MIGRATOR).await
+        }
+        Backend::Sqlite => {
+            let pool = database::sqlite::connect_pool(config, false).await?;
+            schema_status(&pool, &database::sqlite::MIGRATOR).await
+        }
+    }
+}
+
+/// Applies the pending migrations, creating the database if it's missing.
+/// Returns the migrations applied, or only those that would be with
+/// `dry_run`.
+///
+/// # Errors
+///
+/// Will return `Err` if the database can't be connected to, or a migration
+/// fails.
+pub async fn apply(config: &DatabaseConfig, dry_run: bool) -> Result<Vec<Migration>, ErrReport> {
+    let url = config.database.expose();
+    match Backend::of(&config.database)? {
+        Backend::Postgres if dry_run && !Postgres::database_exists(url).await? => {
+            Ok(up_migrations(&database::MIGRATOR))
+        }
+        Backend::Sqlite if dry_run && !Sqlite::database_exists(url).await? => {
+            Ok(up_migrations(&database::sqlite::MIGRATOR))
+        }
+        Backend::Postgres => {
+            let pool = database::connect_pool(config, !dry_run).await?;
+            apply_with(&pool, &database::MIGRATOR, dry_run).await
+        }
+        Backend::Sqlite => {
+            let pool = database::sqlite::connect_pool(config, !dry_run).await?;
+            apply_with(&pool, &database::sqlite::MIGRATOR, dry_run).await
+        }
+    }
+}
+
+/// Reverts the migrations applied after `target`, by default only the
+/// latest one. Returns the down migrations run, or only those that would be
+/// with `dry_run`.
+///
+/// # Errors
+///
+/// Will return `Err` if the database can't be connected to, or a migration
+/// fails.
+pub async fn revert(
+    config: &DatabaseConfig,
+    target: Option<i64>,
+    dry_run: bool,
+) -> Result<Vec<Migration>, ErrReport> {
+    match Backend::of(&config.database)? {
+        Backend::Postgres => {
+            let pool = database::connect_pool(config, false).await?;
+            revert_with(&pool, &database::MIGRATOR, target, dry_run).await
+        }
+        Backend::Sqlite => {
+            let pool = database::sqlite::connect_pool(config, false).await?;
+            revert_with(&pool, &database::sqlite::MIGRATOR, target, dry_run).await
+        }
+    }
+}
+
+pub(crate) async fn apply_with<DB>(
+    pool: &Pool<DB>,
+    migrator: &Migrator,
+    dry_run: bool,
+) -> Result<Vec<Migration>, ErrReport>
+where
+    DB: MigrationsTable,
+    DB::Connection: Migrate,
+{
+    let status = schema_status(pool, migrator).await?;
+    let pending = plan(migrator, &status, |migration, state| {
+        migration.migration_type.is_up_migration() && state == Some(MigrationState::Pending)
+    });
+
+    if !dry_run {
+        migrator.run(pool).await?;
+    }
+
+    Ok(pending)
+}
+
+pub(crate) async fn revert_with<DB>(
+    pool: &Pool<DB>,
+    migrator: &Migrator,
+    target: Option<i64>,
+    dry_run: bool,
+) -> Result<Vec<Migration>, ErrReport>
+where
+    DB: MigrationsTable,
+    DB::Connection: Migrate,
+{
+    let status = schema_status(pool, migrator).await?;
+    let target = target.unwrap_or_else(|| previous_version(&status));
+    let mut reverted = plan(migrator, &status, |migration, state| {
+        migration.migration_type.is_down_migration()
+            && migration.version > target
+            && state.is_some_and(|state| state != MigrationState::Pending)
+    });
+    reverted.reverse();
+
+    if !dry_run {
+        migrator.undo(pool, target).await?;
+    }
+
+    Ok(reverted)
+}
+
+/// Every migration, for a database that doesn't exist yet.
+fn up_migrations(migrator: &Migrator) -> Vec<Migration> {
+    migrator
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .cloned()
+        .collect()
+}
+
+/// The version before the latest applied migration, 0 if there's only one.
+fn previous_version(status: &SchemaStatus) -> i64 {
+    let mut applied = status
+        .migrations
+        .iter()
+        .filter(|migration| migration.state != MigrationState::Pending)
+        .map(|migration| migration.version)
+        .rev();
+    applied.next();
+    applied.next().unwrap_or(0)
+}
+
+/// The migrations of `migrator` selected by their state in the database.
+fn plan(
+    migrator: &Migrator,
+    status: &SchemaStatus,
+    select: impl Fn(&Migration, Option<MigrationState>) -> bool,
+) -> Vec<Migration> {
+    let states: HashMap<_, _> = status
+        .migrations
+        .iter()
+        .map(|migration| (migration.version, migration.state))
+        .collect();
+
+    migrator
+        .iter()
+        .filter(|migration| select(migration, states.get(&migration.version).copied()))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MigrateMode;
+
+    fn config() -> DatabaseConfig {
+        DatabaseConfig {
+            database:        "sqlite::memory:".parse().unwrap(),
+            migrate:         MigrateMode::Skip,
+            max_connections: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn applies_and_reverts_sqlite_migrations() {
+        let pool = database::sqlite::connect_pool(&config(), true)
+            .await
+            .unwrap();
+        let migrator = &database::sqlite::MIGRATOR;
+
+        let status = schema_status(&pool, migrator).await.unwrap();
+        assert_eq!(status.version(), None);
+        assert!(status
+            .ensure_current()
+            .unwrap_err()
+            .to_string()
+            .contains("db migrate"));
+        assert!(status
+            .ensure_version()
+            .unwrap_err()
+            .to_string()
+            .contains("not up to date"));
+
+        let planned = apply_with(&pool, migrator, true).await.unwrap();
+        assert_eq!(planned.len(), 2);
+        assert!(planned[0].sql.contains("CREATE TABLE service_status"));
+        assert!(!schema_status(&pool, migrator).await.unwrap().is_current());
+
+        // Neither the status nor a dry run write to the database
+        assert!(!Sqlite::has_migrations_table(&pool).await.unwrap());
+
+        apply_with(&pool, migrator, false).await.unwrap();
+        let status = schema_status(&pool, migrator).await.unwrap();
+        assert!(status.is_current());
+        status.ensure_version().unwrap();
+        assert!(apply_with(&pool, migrator, false).await.unwrap().is_empty());
+
+        let reverted = revert_with(&pool, migrator, None, false).await.unwrap();
+        assert_eq!(reverted.len(), 1);
+        assert!(reverted[0].migration_type.is_down_migration());
+        assert_eq!(
+            schema_status(&pool, migrator).await.unwrap().version(),
+            Some(1)
+        );
+
+        let reverted = revert_with(&pool, migrator, Some(0), false).await.unwrap();
+        assert_eq!(reverted.len(), 1);
+        assert_eq!(
+            schema_status(&pool, migrator).await.unwrap().version(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn skipping_migrations_still_checks_the_version() {
+        let Err(err) = database::sqlite::SqliteDatabase::new(&config()).await else {
+            panic!("started on an empty schema");
+        };
+        assert!(err.to_string().contains("not up to date"), "{err}");
+    }
+}
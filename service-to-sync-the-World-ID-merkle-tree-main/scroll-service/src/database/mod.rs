@@ -4,27 +4,70 @@
     clippy::cast_possible_wrap
 )]
 
-use std::cmp::Ordering;
 use std::ops::Deref;
+use std::sync::Arc;
 
-use anyhow::{anyhow, Context, Error as ErrReport};
+use anyhow::{bail, Context, Error as ErrReport};
 use sqlx::migrate::{Migrate, MigrateDatabase, Migrator};
 use sqlx::pool::PoolOptions;
 use sqlx::{Executor, Pool, Postgres, Row};
 use thiserror::Error;
 use tracing::{error, info, instrument, warn};
 
-use crate::config::DatabaseConfig;
+use crate::config::{DatabaseConfig, MigrateMode};
 use crate::database::query::DatabaseQuery;
+use crate::utils::secret::SecretUrl;
 // use crate::identity_tree::Hash;
 
+#[cfg(test)]
+pub mod memory;
+pub mod migrations;
 pub mod query;
+pub mod sqlite;
+mod store;
 pub mod transaction;
 pub mod types;
 
+pub use self::sqlite::SqliteDatabase;
+pub use self::store::BridgeStore;
+
 // Statically link in migration files
-static MIGRATOR: Migrator = sqlx::migrate!("schemas/database");
+pub(crate) static MIGRATOR: Migrator = sqlx::migrate!("schemas/database");
+
+/// Connects to the database the URL scheme points at, `postgres://` or
+/// `sqlite://`.
+///
+/// # Errors
+///
+/// Will return `Err` if the scheme is unknown, or the database can't be
+/// connected to or isn't migrated.
+pub async fn connect(config: &DatabaseConfig) -> Result<Arc<dyn BridgeStore>, ErrReport> {
+    match Backend::of(&config.database)? {
+        Backend::Postgres => Ok(Arc::new(Database::new(config).await?)),
+        Backend::Sqlite => Ok(Arc::new(SqliteDatabase::new(config).await?)),
+    }
+}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Postgres,
+    Sqlite,
+}
+
+impl Backend {
+    /// # Errors
+    ///
+    /// Will return `Err` for URL schemes other than `postgres` and `sqlite`.
+    pub fn of(url: &SecretUrl) -> Result<Self, ErrReport> {
+        match url.scheme() {
+            "postgres" | "postgresql" => Ok(Self::Postgres),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => bail!("unsupported database URL scheme, expected postgres:// or sqlite://"),
+        }
+    }
+}
+
+/// The Postgres store.
 pub struct Database {
     pub pool: Pool<Postgres>,
 }
@@ -44,25 +87,7 @@ impl Database {
     pub async fn new(config: &DatabaseConfig) -> Result<Self, ErrReport> {
         info!(url = %&config.database, "Connecting to database");
 
-        // Create database if requested and does not exist
-        if config.migrate && !Postgres::database_exists(config.database.expose()).await? {
-            warn!(url = %&config.database, "Database does not exist, creating database");
-            Postgres::create_database(config.database.expose()).await?;
-        }
-
-        // Create a connection pool
-        let pool = PoolOptions::<Postgres>::new()
-            .max_connections(config.max_connections)
-            .after_connect(|conn, _| {
-                Box::pin(async move {
-                    conn.execute("SET DEFAULT_TRANSACTION_ISOLATION TO 'SERIALIZABLE'")
-                        .await?;
-                    Ok(())
-                })
-            })
-            .connect(config.database.expose())
-            .await
-            .context("error connecting to database")?;
+        let pool = connect_pool(config, config.migrate == MigrateMode::Run).await?;
 
         let version = pool
             .fetch_one("SELECT version()")
@@ -71,75 +96,461 @@ impl Database {
             .get::<String, _>(0);
         info!(url = %&config.database, ?version, "Connected to database");
 
-        // Run migrations if requested.
-        let latest = MIGRATOR
-            .migrations
-            .last()
-            .expect("Missing migrations")
-            .version;
+        migrate(&pool, &MIGRATOR, config).await?;
 
-        if config.migrate {
-            info!(url = %&config.database, "Running migrations");
-            MIGRATOR.run(&pool).await?;
-        }
+        Ok(Self { pool })
+    }
+}
 
-        // Validate database schema version
-        let mut conn = pool.acquire().await?;
-
-        if conn.dirty_version().await?.is_some() {
-            error!(
-                url = %&config.database,
-                version,
-                expected = latest,
-                "Database is in incomplete migration state.",
-            );
-            return Err(anyhow!("Database is in incomplete migration state."));
-        }
+/// Connects to the database, creating it if it doesn't exist and `create`
+/// is set.
+pub(crate) async fn connect_pool(
+    config: &DatabaseConfig,
+    create: bool,
+) -> Result<Pool<Postgres>, ErrReport> {
+    // Create database if requested and does not exist
+    if create && !Postgres::database_exists(config.database.expose()).await? {
+        warn!(url = %&config.database, "Database does not exist, creating database");
+        Postgres::create_database(config.database.expose()).await?;
+    }
 
-        let version = conn
-            .list_applied_migrations()
-            .await?
-            .last()
-            .expect("Missing migrations")
-            .version;
-
-        match version.cmp(&latest) {
-            Ordering::Less => {
-                error!(
-                    url = %&config.database,
-                    version,
-                    expected = latest,
-                    "Database is not up to date, try rerunning with --database-migrate",);
-                return Err(anyhow!(
-                    "Database is not up to date, try rerunning with --database-migrate"
-                ));
-            }
-            Ordering::Greater => {
-                error!(
-                    url = %&config.database,
-                    version,
-                    latest,
-                    "Database version is newer than this version of the software, please update.",);
-                return Err(anyhow!(
-                    "Database version is newer than this version of the software, please update."
-                ));
-            }
-            Ordering::Equal => {
-                info!(
-                    url = %&config.database,
-                    version,
-                    latest,
-                    "Database version is up to date.",
-                );
-            }
+    // Create a connection pool
+    PoolOptions::<Postgres>::new()
+        .max_connections(config.max_connections)
+        .after_connect(|conn, _| {
+            Box::pin(async move {
+                conn.execute("SET DEFAULT_TRANSACTION_ISOLATION TO 'SERIALIZABLE'")
+                    .await?;
+                Ok(())
+            })
+        })
+        .connect(config.database.expose())
+        .await
+        .context("error connecting to database")
+}
+
+/// Runs the migrations if configured to, then checks that the schema is the
+/// one this version of the service expects.
+async fn migrate<DB>(
+    pool: &Pool<DB>,
+    migrator: &Migrator,
+    config: &DatabaseConfig,
+) -> Result<(), ErrReport>
+where
+    DB: migrations::MigrationsTable,
+    DB::Connection: Migrate,
+{
+    let check = match config.migrate {
+        MigrateMode::Run => {
+            info!(url = %&config.database, "Running migrations");
+            migrator.run(pool).await?;
+            migrations::SchemaStatus::ensure_current
         }
+        MigrateMode::Check => migrations::SchemaStatus::ensure_current,
+        MigrateMode::Skip => migrations::SchemaStatus::ensure_version,
+    };
 
-        Ok(Self { pool })
+    // Validate database schema version
+    let status = migrations::schema_status(pool, migrator).await?;
+    if let Err(err) = check(&status) {
+        error!(url = %&config.database, %status, "{err}");
+        return Err(err);
     }
+    info!(
+        url = %&config.database,
+        version = status.version(),
+        "Database version is up to date.",
+    );
+
+    Ok(())
 }
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("database error: {0}")]
     InternalError(#[from] sqlx::Error),
+    #[error("invalid value in database: {0}")]
+    InvalidValue(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{H256, U256};
+    use postgres_docker_utils::DockerContainer;
+    use serde_json::Value;
+    use sqlx::Sqlite;
+    use testcontainers::clients::Cli;
+
+    use super::*;
+    use crate::database::types::{RetainedTable, TxStatus};
+    use crate::processor::cost::PropagationCost;
+    use crate::processor::status::BridgeStatus;
+
+    /// A store along with its pool, for setting up and checking rows with
+    /// SQL that runs on both backends.
+    struct TestStore {
+        store: Arc<dyn BridgeStore>,
+        pool:  TestPool,
+    }
+
+    enum TestPool {
+        Postgres(Pool<Postgres>),
+        Sqlite(Pool<Sqlite>),
+    }
+
+    impl TestStore {
+        async fn execute(&self, sql: &str) {
+            match &self.pool {
+                TestPool::Postgres(pool) => pool.execute(sql).await.map(drop),
+                TestPool::Sqlite(pool) => pool.execute(sql).await.map(drop),
+            }
+            .unwrap();
+        }
+
+        /// Every column is expected to be cast to text.
+        async fn fetch_text(&self, sql: &str) -> Vec<Vec<Option<String>>> {
+            fn columns<R: Row>(row: &R) -> Vec<Option<String>>
+            where
+                usize: sqlx::ColumnIndex<R>,
+                for<'r> Option<String>: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+            {
+                (0..row.len()).map(|i| row.get(i)).collect()
+            }
+
+            match &self.pool {
+                TestPool::Postgres(pool) => pool
+                    .fetch_all(sql)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(columns)
+                    .collect(),
+                TestPool::Sqlite(pool) => pool
+                    .fetch_all(sql)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(columns)
+                    .collect(),
+            }
+        }
+    }
+
+    fn config(url: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            database:        url.parse().unwrap(),
+            migrate:         MigrateMode::Run,
+            max_connections: 2,
+        }
+    }
+
+    async fn setup_database(docker: &Cli) -> (TestStore, DockerContainer<'_>) {
+        let db_container = postgres_docker_utils::setup(docker).await.unwrap();
+        let url = format!(
+            "postgres://postgres:postgres@{}/postgres",
+            db_container.address()
+        );
+        let database = Database::new(&config(&url)).await.unwrap();
+        database.initialize_server().await.unwrap();
+
+        let pool = TestPool::Postgres(database.pool.clone());
+        (
+            TestStore {
+                store: Arc::new(database),
+                pool,
+            },
+            db_container,
+        )
+    }
+
+    async fn setup_sqlite() -> TestStore {
+        let database = SqliteDatabase::new(&config("sqlite::memory:"))
+            .await
+            .unwrap();
+        database.initialize_server().await.unwrap();
+
+        let pool = TestPool::Sqlite(database.pool.clone());
+        TestStore {
+            store: Arc::new(database),
+            pool,
+        }
+    }
+
+    /// Runs each test against Postgres in docker and an in-memory SQLite
+    /// database.
+    macro_rules! backend_tests {
+        ($($test:ident),* $(,)?) => {
+            mod postgres {
+                $(
+                    #[tokio::test]
+                    #[ignore = "requires docker"]
+                    async fn $test() {
+                        let docker = super::Cli::default();
+                        let (store, _db_container) = super::setup_database(&docker).await;
+                        super::$test(store).await;
+                    }
+                )*
+            }
+
+            mod sqlite {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::setup_sqlite().await).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    backend_tests!(
+        propagation_outbox_steps_are_rerunnable,
+        records_status_transitions,
+        records_outcomes_with_their_cost,
+        rolls_up_transactions_before_pruning,
+        migrations_revert_cleanly,
+    );
+
+    async fn propagation_outbox_steps_are_rerunnable(database: TestStore) {
+        outbox_steps_are_rerunnable(database.store.as_ref()).await;
+    }
+
+    #[tokio::test]
+    async fn memory_store_outbox_steps_are_rerunnable() {
+        outbox_steps_are_rerunnable(&memory::MemoryStore::initialized()).await;
+    }
+
+    async fn outbox_steps_are_rerunnable(database: &dyn BridgeStore) {
+        let intent_id = database.enqueue_propagation(U256::from(1)).await.unwrap();
+        assert!(intent_id.is_some());
+        assert_eq!(
+            database.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Pending)
+        );
+
+        // Only one propagation is open at a time
+        assert_eq!(
+            database.enqueue_propagation(U256::from(2)).await.unwrap(),
+            None
+        );
+
+        // A worker crashing after claiming leaves the intent to be claimed again
+        let first_claim = database.claim_propagation_intent().await.unwrap().unwrap();
+        assert_eq!(first_claim.attempts, 1);
+        let intent = database.claim_propagation_intent().await.unwrap().unwrap();
+        assert_eq!((Some(intent.id), intent.attempts), (intent_id, 2));
+        assert_eq!(intent.idempotency_key, first_claim.idempotency_key);
+
+        database
+            .record_propagation_sent(&intent, "tx")
+            .await
+            .unwrap();
+        database
+            .record_propagation_sent(&intent, "tx")
+            .await
+            .unwrap();
+        assert_eq!(
+            database.get_last_transaction_id().await.unwrap().as_deref(),
+            Some("tx")
+        );
+        assert_eq!(database.claim_propagation_intent().await.unwrap(), None);
+
+        let intent_id = database.enqueue_propagation(U256::from(2)).await.unwrap();
+        let next = database.claim_propagation_intent().await.unwrap().unwrap();
+        assert_eq!(Some(next.id), intent_id);
+        assert_ne!(next.idempotency_key, intent.idempotency_key);
+        database
+            .record_propagation_failed(&next, "reverted")
+            .await
+            .unwrap();
+        assert_eq!(
+            database.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Unsynced)
+        );
+        assert_eq!(database.claim_propagation_intent().await.unwrap(), None);
+    }
+
+    async fn records_status_transitions(database: TestStore) {
+        let store = &database.store;
+
+        store.mark_status_as_unsynced("roots differ").await.unwrap();
+        store
+            .mark_status_as_pending("propagation queued")
+            .await
+            .unwrap();
+        store.mark_status_as_synced("roots match").await.unwrap();
+        store.mark_status_as_synced("roots match").await.unwrap();
+
+        let status = store.get_service_status().await.unwrap().unwrap();
+        assert_eq!(status.status, BridgeStatus::Synced);
+        assert!(status.last_synced.is_some());
+
+        let history = database
+            .fetch_text(
+                "SELECT CAST(from_status AS TEXT), CAST(to_status AS TEXT), cause FROM \
+                 bridge_status_history ORDER BY id",
+            )
+            .await;
+        let row = |from: Option<&str>, to: &str, cause: &str| {
+            vec![
+                from.map(String::from),
+                Some(to.to_string()),
+                Some(cause.to_string()),
+            ]
+        };
+        assert_eq!(history, vec![
+            row(None, "unsynced", "initialized"),
+            row(Some("unsynced"), "pending", "propagation queued"),
+            row(Some("pending"), "synced", "roots match"),
+        ]);
+    }
+
+    async fn records_outcomes_with_their_cost(database: TestStore) {
+        let store = &database.store;
+        store.enqueue_propagation(U256::from(1)).await.unwrap();
+        let intent = store.claim_propagation_intent().await.unwrap().unwrap();
+        store.record_propagation_sent(&intent, "tx").await.unwrap();
+
+        let cost = PropagationCost {
+            tx_hash:             H256::repeat_byte(1),
+            block_number:        1,
+            gas_used:            U256::from(21000),
+            effective_gas_price: U256::from(10),
+            message_value:       U256::from(5),
+            message_fee:         Some(U256::from(2)),
+            refund:              None,
+        };
+        store
+            .record_transaction_outcome("tx", TxStatus::Mined, Some(&cost))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_last_transaction_status().await.unwrap(),
+            Some(TxStatus::Mined)
+        );
+        let costs = store.get_propagation_costs(None, None).await.unwrap();
+        assert_eq!(costs.len(), 1);
+        assert_eq!(
+            (costs[0].transaction_id.as_str(), &costs[0].cost),
+            ("tx", &cost)
+        );
+    }
+
+    async fn rolls_up_transactions_before_pruning(database: TestStore) {
+        database
+            .execute(
+                r#"
+                INSERT INTO transactions (transaction_id, status, created_at) VALUES
+                    ('a', 'mined', '2024-03-10T10:00:00+00:00'),
+                    ('b', 'pending', '2024-03-10T12:00:00+00:00'),
+                    ('d', 'failed', '2024-03-10T13:00:00+00:00'),
+                    ('c', 'mined', '2024-03-11T10:00:00+00:00');
+                INSERT INTO propagation_costs VALUES
+                    ('a', '0x01', 1, 21000, 10, 5, 2, 3, '2024-03-10T10:05:00+00:00');
+                INSERT INTO propagation_outbox (root, idempotency_key, status, created_at) VALUES
+                    ('0x01', 'propagation-1', 'sent', '2024-03-10T09:00:00+00:00'),
+                    ('0x02', 'propagation-2', 'pending', '2024-03-10T11:00:00+00:00');
+                "#,
+            )
+            .await;
+        let store = &database.store;
+
+        let cutoff = "2024-03-11T00:00:00Z".parse().unwrap();
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let oldest = store
+            .oldest_prunable(RetainedTable::Transactions, cutoff)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(oldest.date_naive(), day);
+
+        let (from, to) = transaction::day_bounds(day);
+        let rows = store
+            .prunable_rows(RetainedTable::Transactions, from, to)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        let first: Value = serde_json::from_str(&rows[0]).unwrap();
+        assert_eq!(first["transaction"]["transaction_id"], "a");
+        assert_eq!(first["cost"]["tx_hash"], "0x01");
+
+        assert_eq!(
+            store
+                .prune_day(RetainedTable::Transactions, day)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            store
+                .oldest_prunable(RetainedTable::Transactions, cutoff)
+                .await
+                .unwrap(),
+            None
+        );
+        // Transactions still pending are kept until they're settled
+        let kept = database
+            .fetch_text("SELECT transaction_id FROM transactions ORDER BY transaction_id")
+            .await;
+        assert_eq!(kept, vec![vec![Some("b".to_string())], vec![Some(
+            "c".to_string()
+        )]]);
+
+        let stats = database
+            .fetch_text(
+                "SELECT CAST(propagations AS TEXT), CAST(mined AS TEXT), CAST(l1_fee AS TEXT), \
+                 CAST(latency_p50_ms AS TEXT) FROM daily_propagation_stats",
+            )
+            .await;
+        // The failed transaction counts as a propagation, but not as mined
+        assert_eq!(stats, vec![vec![
+            Some("2".to_string()),
+            Some("1".to_string()),
+            Some("210000".to_string()),
+            Some("300000".to_string()),
+        ]]);
+
+        // Open intents are kept
+        assert_eq!(
+            store
+                .prune_day(RetainedTable::PropagationOutbox, day)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(store
+            .oldest_prunable(RetainedTable::PropagationOutbox, cutoff)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(store.claim_propagation_intent().await.unwrap().is_some());
+    }
+
+    async fn migrations_revert_cleanly(database: TestStore) {
+        async fn round_trip<DB>(pool: &Pool<DB>, migrator: &Migrator)
+        where
+            DB: migrations::MigrationsTable,
+            DB::Connection: Migrate,
+        {
+            let reverted = migrations::revert_with(pool, migrator, Some(0), false)
+                .await
+                .unwrap();
+            let downs = migrator
+                .iter()
+                .filter(|m| m.migration_type.is_down_migration());
+            assert_eq!(reverted.len(), downs.count());
+            let status = migrations::schema_status(pool, migrator).await.unwrap();
+            assert_eq!(status.version(), None);
+
+            migrations::apply_with(pool, migrator, false).await.unwrap();
+            let status = migrations::schema_status(pool, migrator).await.unwrap();
+            assert!(status.is_current());
+        }
+
+        match &database.pool {
+            TestPool::Postgres(pool) => round_trip(pool, &MIGRATOR).await,
+            TestPool::Sqlite(pool) => round_trip(pool, &crate::database::sqlite::MIGRATOR).await,
+        }
+    }
 }
@@ -1,10 +1,15 @@
-// use chrono::{DateTime, Utc};
-use sqlx::{Executor, Postgres, Row};
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::types::{H256, U256};
+use sqlx::{ColumnIndex, Decode, Executor, Postgres, Row, Type};
 
 use crate::database::{types, Error};
 
+use crate::processor::cost::PropagationCost;
 use crate::processor::status::BridgeStatus;
-use types::{ServerStatus, TxStatus};
+use crate::task_monitor::policy::PolicyDecision;
+use types::{
+    OutboxStatus, PropagationCostRecord, PropagationIntent, RetainedTable, ServerStatus, TxStatus,
+};
 
 /// This trait provides the individual and composable queries to the database.
 /// Each method is a single atomic query, and can be composed within a
@@ -16,11 +21,17 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
     ) -> Result<(), Error> {
         let initialize_server_query = sqlx::query(
             r#"
-            INSERT INTO service_status (status, last_synced)
-            VALUES ($1, CURRENT_TIMESTAMP)
+            WITH initialized AS (
+                INSERT INTO service_status (status, last_synced)
+                VALUES ($1, CURRENT_TIMESTAMP)
+                RETURNING status
+            )
+            INSERT INTO bridge_status_history (from_status, to_status, cause, changed_at)
+            SELECT NULL, status, 'initialized', CURRENT_TIMESTAMP
+            FROM initialized
             "#,
         )
-        .bind(<&str>::from(BridgeStatus::Unsynced));
+        .bind(BridgeStatus::Unsynced);
 
         self.execute(initialize_server_query).await?;
         Ok(())
@@ -40,7 +51,7 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
 
     async fn insert_new_transaction(
         self,
-        transaction_id: &String
+        transaction_id: &str
     ) -> Result<(), Error> {
         let query = sqlx::query(
             r#"
@@ -55,9 +66,126 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
         Ok(())
     }
 
+    /// Like `insert_new_transaction`, but a no-op if the transaction is
+    /// already recorded.
+    async fn insert_transaction_if_missing(
+        self,
+        transaction_id: &str,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+            INSERT INTO transactions(
+                transaction_id,
+                created_at
+            ) VALUES ($1, CURRENT_TIMESTAMP)
+            ON CONFLICT (transaction_id) DO NOTHING
+            "#,
+        )
+        .bind(transaction_id);
+        self.execute(query).await?;
+        Ok(())
+    }
+
+    /// Writes an intent to propagate `root`. Returns `None` if another
+    /// intent is still open.
+    async fn insert_propagation_intent(self, root: U256) -> Result<Option<i64>, Error> {
+        let query = sqlx::query(
+            r#"
+            INSERT INTO propagation_outbox(
+                root,
+                idempotency_key,
+                created_at
+            ) VALUES ($1, $2, CURRENT_TIMESTAMP)
+            ON CONFLICT DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(format!("{root:#x}"))
+        .bind(PropagationIntent::new_key());
+        let row = self.fetch_optional(query).await?;
+
+        Ok(row.map(|r| r.get::<i64, _>(0)))
+    }
+
+    async fn has_open_propagation(self) -> Result<bool, Error> {
+        let query = sqlx::query(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM propagation_outbox
+                WHERE status IN ($1, $2)
+            )
+            "#,
+        )
+        .bind(OutboxStatus::Pending)
+        .bind(OutboxStatus::Claimed);
+        let row = self.fetch_one(query).await?;
+
+        Ok(row.get::<bool, _>(0))
+    }
+
+    /// Skips an intent another replica is claiming rather than waiting on it.
+    async fn claim_propagation_intent(self) -> Result<Option<PropagationIntent>, Error> {
+        Ok(sqlx::query_as::<_, PropagationIntent>(
+            r#"
+            UPDATE propagation_outbox
+            SET status = $1, attempts = attempts + 1, claimed_at = CURRENT_TIMESTAMP
+            WHERE id = (
+                SELECT id
+                FROM propagation_outbox
+                WHERE status IN ($2, $1)
+                ORDER BY id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, root, attempts, idempotency_key
+            "#,
+        )
+        .bind(OutboxStatus::Claimed)
+        .bind(OutboxStatus::Pending)
+        .fetch_optional(self)
+        .await?)
+    }
+
+    async fn mark_intent_sent(self, intent_id: i64, transaction_id: &str) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+            UPDATE propagation_outbox
+            SET status = $1, transaction_id = $2, sent_at = CURRENT_TIMESTAMP, last_error = NULL
+            WHERE id = $3
+            "#,
+        )
+        .bind(OutboxStatus::Sent)
+        .bind(transaction_id)
+        .bind(intent_id);
+        self.execute(query).await?;
+        Ok(())
+    }
+
+    async fn record_intent_error(
+        self,
+        intent_id: i64,
+        error: &str,
+        give_up: bool,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+            UPDATE propagation_outbox
+            SET last_error = $1, status = CASE WHEN $2 THEN $3 ELSE status END
+            WHERE id = $4
+            "#,
+        )
+        .bind(error)
+        .bind(give_up)
+        .bind(OutboxStatus::Failed)
+        .bind(intent_id);
+        self.execute(query).await?;
+        Ok(())
+    }
+
     async fn update_transaction(
         self,
-        transaction_id: &String,
+        transaction_id: &str,
         tx_status: TxStatus,
     ) -> Result<(), Error> {
         let query = sqlx::query(
@@ -74,31 +202,34 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
     }
 
 
+    /// Sets the bridge status and records the transition with its `cause`.
+    /// `last_synced` is bumped every time the bridge is found synced.
     async fn update_server_status(
         self,
-        status: BridgeStatus
+        status: BridgeStatus,
+        cause: &str,
     ) -> Result<(), Error> {
-        let query = if matches!(status, BridgeStatus::Synced) {
-            // Update the status and last_synced if the status is Synced
-            sqlx::query(
-                r#"
-                UPDATE service_status
-                SET status = $1, last_synced = CURRENT_TIMESTAMP
+        let query = sqlx::query(
+            r#"
+            WITH previous AS (
+                SELECT status
+                FROM service_status
                 WHERE id = 1
-                "#
-            )
-            .bind(status)
-        } else {
-            // Only update the status if the status is not Synced
-            sqlx::query(
-                r#"
+            ), updated AS (
                 UPDATE service_status
-                SET status = $1
+                SET status = $1,
+                    last_synced = CASE WHEN $1 = 'synced' THEN CURRENT_TIMESTAMP ELSE last_synced END
                 WHERE id = 1
-                "#
+                RETURNING status
             )
-            .bind(<&str>::from(status))
-        };
+            INSERT INTO bridge_status_history (from_status, to_status, cause, changed_at)
+            SELECT previous.status, updated.status, $2, CURRENT_TIMESTAMP
+            FROM previous, updated
+            WHERE previous.status IS DISTINCT FROM updated.status
+            "#
+        )
+        .bind(status)
+        .bind(cause);
 
         self.execute(query).await?;
         Ok(())
@@ -137,6 +268,266 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
         Ok(row.map(|r| r.get::<String, _>(0)))
     }
 
+    async fn get_last_transaction_time(self) -> Result<Option<DateTime<Utc>>, Error> {
+        let query = sqlx::query(
+            r#"
+            SELECT created_at
+            FROM transactions
+            ORDER BY created_at DESC
+            LIMIT 1;
+            "#,
+        );
+        let row = self.fetch_optional(query).await?;
+
+        Ok(row.map(|r| r.get::<DateTime<Utc>, _>(0)))
+    }
+
+    async fn insert_propagation_decision(
+        self,
+        root: U256,
+        decision: &PolicyDecision,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+            INSERT INTO propagation_decisions(
+                root,
+                propagate,
+                reason,
+                decided_at
+            ) VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+            "#,
+        )
+        .bind(format!("{root:#x}"))
+        .bind(decision.propagate)
+        .bind(&decision.reason);
+        self.execute(query).await?;
+        Ok(())
+    }
+
+    async fn insert_propagation_cost(
+        self,
+        transaction_id: &str,
+        cost: &PropagationCost,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+            INSERT INTO propagation_costs(
+                transaction_id,
+                tx_hash,
+                block_number,
+                gas_used,
+                effective_gas_price,
+                message_value,
+                message_fee,
+                refund,
+                recorded_at
+            ) VALUES ($1, $2, $3, $4::NUMERIC, $5::NUMERIC, $6::NUMERIC, $7::NUMERIC, $8::NUMERIC, CURRENT_TIMESTAMP)
+            ON CONFLICT (transaction_id) DO NOTHING
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(format!("{:?}", cost.tx_hash))
+        .bind(cost.block_number as i64)
+        .bind(cost.gas_used.to_string())
+        .bind(cost.effective_gas_price.to_string())
+        .bind(cost.message_value.to_string())
+        .bind(cost.message_fee.map(|fee| fee.to_string()))
+        .bind(cost.refund.map(|refund| refund.to_string()));
+        self.execute(query).await?;
+        Ok(())
+    }
+
+    async fn get_propagation_costs(
+        self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<PropagationCostRecord>, Error> {
+        let query = sqlx::query(
+            r#"
+            SELECT
+                transaction_id,
+                tx_hash,
+                block_number,
+                gas_used::TEXT,
+                effective_gas_price::TEXT,
+                message_value::TEXT,
+                message_fee::TEXT,
+                refund::TEXT,
+                recorded_at
+            FROM propagation_costs
+            WHERE ($1::TIMESTAMPTZ IS NULL OR recorded_at >= $1)
+              AND ($2::TIMESTAMPTZ IS NULL OR recorded_at < $2)
+            ORDER BY recorded_at ASC
+            "#,
+        )
+        .bind(from)
+        .bind(to);
+
+        self.fetch_all(query)
+            .await?
+            .iter()
+            .map(propagation_cost_from_row)
+            .collect()
+    }
+
+    async fn oldest_prunable(
+        self,
+        table: RetainedTable,
+        before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        let sql = format!(
+            "SELECT min({column}) FROM {table} WHERE {column} < $1 AND {prunable}",
+            table = table.name(),
+            column = table.timestamp_column(),
+            prunable = table.prunable(),
+        );
+        let query = sqlx::query(&sql).bind(before);
+        let row = self.fetch_one(query).await?;
+
+        Ok(row.try_get::<Option<DateTime<Utc>>, _>(0)?)
+    }
+
+    /// Transactions are joined with their cost.
+    async fn prunable_rows(
+        self,
+        table: RetainedTable,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<String>, Error> {
+        let sql = match table {
+            RetainedTable::Transactions => format!(
+                r#"
+                SELECT jsonb_build_object('transaction', to_jsonb(t), 'cost', to_jsonb(c))::text
+                FROM transactions t
+                LEFT JOIN propagation_costs c USING (transaction_id)
+                WHERE t.created_at >= $1 AND t.created_at < $2 AND t.{prunable}
+                ORDER BY t.created_at
+                "#,
+                prunable = table.prunable(),
+            ),
+            table => format!(
+                "SELECT to_jsonb(t)::text FROM {table} t WHERE {column} >= $1 AND {column} < $2 \
+                 AND {prunable} ORDER BY {column}",
+                table = table.name(),
+                column = table.timestamp_column(),
+                prunable = table.prunable(),
+            ),
+        };
+        let query = sqlx::query(&sql).bind(from).bind(to);
+
+        self.fetch_all(query)
+            .await?
+            .iter()
+            .map(|row| Ok(row.try_get::<String, _>(0)?))
+            .collect()
+    }
+
+    /// Adds the prunable transactions written within [from, to) to the stats
+    /// of `day`. Latency percentiles are only kept from the first rollup.
+    async fn roll_up_transactions(
+        self,
+        day: NaiveDate,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let sql = format!(
+            r#"
+            WITH latencies AS (
+                SELECT
+                    t.status,
+                    c.gas_used * c.effective_gas_price AS l1_fee,
+                    c.message_value,
+                    c.message_fee,
+                    c.refund,
+                    extract(epoch FROM c.recorded_at - t.created_at) * 1000 AS latency_ms
+                FROM transactions t
+                LEFT JOIN propagation_costs c USING (transaction_id)
+                WHERE t.created_at >= $2 AND t.created_at < $3 AND t.{prunable}
+            )
+            INSERT INTO daily_propagation_stats (
+                day,
+                propagations,
+                mined,
+                l1_fee,
+                message_value,
+                message_fee,
+                refund,
+                latency_p50_ms,
+                latency_p90_ms,
+                latency_p99_ms,
+                rolled_up_at
+            )
+            SELECT
+                $1,
+                count(*),
+                count(*) FILTER (WHERE status IN ('mined', 'finalized')),
+                COALESCE(sum(l1_fee), 0),
+                COALESCE(sum(message_value), 0),
+                COALESCE(sum(message_fee), 0),
+                COALESCE(sum(refund), 0),
+                percentile_cont(0.5) WITHIN GROUP (ORDER BY latency_ms)::BIGINT,
+                percentile_cont(0.9) WITHIN GROUP (ORDER BY latency_ms)::BIGINT,
+                percentile_cont(0.99) WITHIN GROUP (ORDER BY latency_ms)::BIGINT,
+                CURRENT_TIMESTAMP
+            FROM latencies
+            HAVING count(*) > 0
+            ON CONFLICT (day) DO UPDATE SET
+                propagations = daily_propagation_stats.propagations + EXCLUDED.propagations,
+                mined = daily_propagation_stats.mined + EXCLUDED.mined,
+                l1_fee = daily_propagation_stats.l1_fee + EXCLUDED.l1_fee,
+                message_value = daily_propagation_stats.message_value + EXCLUDED.message_value,
+                message_fee = daily_propagation_stats.message_fee + EXCLUDED.message_fee,
+                refund = daily_propagation_stats.refund + EXCLUDED.refund,
+                rolled_up_at = EXCLUDED.rolled_up_at
+            "#,
+            prunable = RetainedTable::Transactions.prunable(),
+        );
+        let query = sqlx::query(&sql).bind(day).bind(from).bind(to);
+        self.execute(query).await?;
+        Ok(())
+    }
+
+    /// Deletes the costs of the prunable transactions written within
+    /// [from, to).
+    async fn delete_propagation_costs(
+        self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<u64, Error> {
+        let sql = format!(
+            r#"
+            DELETE FROM propagation_costs
+            WHERE transaction_id IN (
+                SELECT transaction_id
+                FROM transactions
+                WHERE created_at >= $1 AND created_at < $2 AND {prunable}
+            )
+            "#,
+            prunable = RetainedTable::Transactions.prunable(),
+        );
+        let query = sqlx::query(&sql).bind(from).bind(to);
+
+        Ok(self.execute(query).await?.rows_affected())
+    }
+
+    /// Deletes the prunable rows of `table` written within [from, to).
+    async fn delete_prunable_rows(
+        self,
+        table: RetainedTable,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<u64, Error> {
+        let sql = format!(
+            "DELETE FROM {table} WHERE {column} >= $1 AND {column} < $2 AND {prunable}",
+            table = table.name(),
+            column = table.timestamp_column(),
+            prunable = table.prunable(),
+        );
+        let query = sqlx::query(&sql).bind(from).bind(to);
+
+        Ok(self.execute(query).await?.rows_affected())
+    }
+
     async fn get_service_status(self) -> Result<Option<ServerStatus>, Error> {
         Ok(sqlx::query_as::<_, ServerStatus>(
             r#"
@@ -149,7 +540,7 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
         .await?)
     }
 
-    async fn get_db_status(self) -> Result<Option<String>, Error> {
+    async fn get_db_status(self) -> Result<Option<BridgeStatus>, Error> {
         let query = sqlx::query(
             r#"
             SELECT status
@@ -158,7 +549,7 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
             "#
         );
         let row = self.fetch_optional(query).await?;
-        Ok(row.map(|r| r.get::<String, _>(0)))
+        Ok(row.map(|r| r.get::<BridgeStatus, _>(0)))
     }
 
     // async fn get_last_sync_timestamp(self) -> Result<Option<DateTime<Utc>>, Error> {
@@ -173,3 +564,43 @@ pub trait DatabaseQuery<'a>: Executor<'a, Database = Postgres> {
     //     Ok(row.map(|r| r.get::<DateTime<Utc>, _>(0)))
     // }
 }
+
+/// Reads a cost selected with its amounts as decimal text.
+pub(crate) fn propagation_cost_from_row<R>(row: &R) -> Result<PropagationCostRecord, Error>
+where
+    R: Row,
+    for<'c> &'c str: ColumnIndex<R>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> DateTime<Utc>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let u256 = |column: &str| -> Result<U256, Error> {
+        U256::from_dec_str(&row.try_get::<String, _>(column)?)
+            .map_err(|e| Error::InvalidValue(e.to_string()))
+    };
+    let optional_u256 = |column: &str| -> Result<Option<U256>, Error> {
+        row.try_get::<Option<String>, _>(column)?
+            .map(|value| U256::from_dec_str(&value))
+            .transpose()
+            .map_err(|e| Error::InvalidValue(e.to_string()))
+    };
+
+    let tx_hash: H256 = row
+        .try_get::<String, _>("tx_hash")?
+        .parse()
+        .map_err(|e| Error::InvalidValue(format!("{e}")))?;
+
+    Ok(PropagationCostRecord {
+        transaction_id: row.try_get("transaction_id")?,
+        cost:           PropagationCost {
+            tx_hash,
+            block_number: row.try_get::<i64, _>("block_number")? as u64,
+            gas_used: u256("gas_used")?,
+            effective_gas_price: u256("effective_gas_price")?,
+            message_value: u256("message_value")?,
+            message_fee: optional_u256("message_fee")?,
+            refund: optional_u256("refund")?,
+        },
+        recorded_at:    row.try_get("recorded_at")?,
+    })
+}
@@ -0,0 +1,795 @@
+//! SQLite store for single-node and development deployments.
+//!
+//! SQLite has a single writer, so claims don't need row locks. Timestamps
+//! are bound from the service rather than taken from the database, so that
+//! they're all stored in the same RFC 3339 format and compare as text.
+use std::str::FromStr;
+
+use anyhow::{Context, Error as ErrReport};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::types::U256;
+use sqlx::migrate::Migrator;
+use sqlx::pool::PoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+use sqlx::{Pool, Row, Sqlite, Transaction};
+use tracing::{info, instrument};
+
+use crate::config::{DatabaseConfig, MigrateMode};
+use crate::database::query::propagation_cost_from_row;
+use crate::database::store::BridgeStore;
+use crate::database::transaction::day_bounds;
+use crate::database::types::{
+    OutboxStatus, PropagationCostRecord, PropagationIntent, RetainedTable, ServerStatus, TxStatus,
+};
+use crate::database::Error;
+use crate::processor::cost::PropagationCost;
+use crate::processor::status::BridgeStatus;
+use crate::retry_tx;
+use crate::task_monitor::policy::PolicyDecision;
+
+pub(crate) static MIGRATOR: Migrator = sqlx::migrate!("schemas/sqlite");
+
+pub struct SqliteDatabase {
+    pub pool: Pool<Sqlite>,
+}
+
+impl SqliteDatabase {
+    #[instrument(skip_all)]
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, ErrReport> {
+        info!(url = %&config.database, "Opening SQLite database");
+
+        let pool = connect_pool(config, config.migrate == MigrateMode::Run).await?;
+        super::migrate(&pool, &MIGRATOR, config).await?;
+
+        Ok(Self { pool })
+    }
+}
+
+/// Opens the database file, creating it if it's missing and `create` is
+/// set.
+pub(crate) async fn connect_pool(
+    config: &DatabaseConfig,
+    create: bool,
+) -> Result<Pool<Sqlite>, ErrReport> {
+    let url = config.database.expose();
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(create)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(true);
+
+    // Every connection to an in-memory database opens a new one, so keep
+    // a single connection open for good
+    if url.contains(":memory:") || url.contains("mode=memory") {
+        PoolOptions::<Sqlite>::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        PoolOptions::<Sqlite>::new().max_connections(config.max_connections)
+    }
+    .connect_with(options)
+    .await
+    .context("error opening database")
+}
+
+async fn update_status(
+    tx: &mut Transaction<'_, Sqlite>,
+    status: BridgeStatus,
+    cause: &str,
+) -> Result<(), Error> {
+    let now = Utc::now();
+    let previous: Option<BridgeStatus> =
+        sqlx::query_scalar("SELECT status FROM service_status WHERE id = 1")
+            .fetch_optional(&mut **tx)
+            .await?;
+    let Some(previous) = previous else {
+        return Ok(());
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE service_status
+        SET status = ?1,
+            last_synced = CASE WHEN ?1 = 'synced' THEN ?2 ELSE last_synced END
+        WHERE id = 1
+        "#,
+    )
+    .bind(status)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?;
+
+    if previous != status {
+        insert_status_change(tx, Some(previous), status, cause, now).await?;
+    }
+
+    Ok(())
+}
+
+async fn insert_status_change(
+    tx: &mut Transaction<'_, Sqlite>,
+    from: Option<BridgeStatus>,
+    to: BridgeStatus,
+    cause: &str,
+    at: DateTime<Utc>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO bridge_status_history (from_status, to_status, cause, changed_at)
+        VALUES (?1, ?2, ?3, ?4)
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .bind(cause)
+    .bind(at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_propagation_cost(
+    tx: &mut Transaction<'_, Sqlite>,
+    transaction_id: &str,
+    cost: &PropagationCost,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO propagation_costs(
+            transaction_id,
+            tx_hash,
+            block_number,
+            gas_used,
+            effective_gas_price,
+            message_value,
+            message_fee,
+            refund,
+            recorded_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        ON CONFLICT (transaction_id) DO NOTHING
+        "#,
+    )
+    .bind(transaction_id)
+    .bind(format!("{:?}", cost.tx_hash))
+    .bind(cost.block_number as i64)
+    .bind(cost.gas_used.to_string())
+    .bind(cost.effective_gas_price.to_string())
+    .bind(cost.message_value.to_string())
+    .bind(cost.message_fee.map(|fee| fee.to_string()))
+    .bind(cost.refund.map(|refund| refund.to_string()))
+    .bind(Utc::now())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+async fn record_intent_error(
+    tx: &mut Transaction<'_, Sqlite>,
+    intent_id: i64,
+    error: &str,
+    give_up: bool,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE propagation_outbox
+        SET last_error = ?1, status = CASE WHEN ?2 THEN ?3 ELSE status END
+        WHERE id = ?4
+        "#,
+    )
+    .bind(error)
+    .bind(give_up)
+    .bind(OutboxStatus::Failed)
+    .bind(intent_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Adds the prunable transactions written within [from, to) to the stats of
+/// `day`, like the Postgres rollup but with the sums and percentiles worked out
+/// here.
+async fn roll_up_transactions(
+    tx: &mut Transaction<'_, Sqlite>,
+    day: NaiveDate,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(), Error> {
+    let sql = format!(
+        r#"
+        SELECT
+            t.status,
+            t.created_at,
+            c.gas_used,
+            c.effective_gas_price,
+            c.message_value,
+            c.message_fee,
+            c.refund,
+            c.recorded_at
+        FROM transactions t
+        LEFT JOIN propagation_costs c USING (transaction_id)
+        WHERE t.created_at >= ?1 AND t.created_at < ?2 AND t.{prunable}
+        "#,
+        prunable = RetainedTable::Transactions.prunable(),
+    );
+    let rows = sqlx::query(&sql)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&mut **tx)
+        .await?;
+
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let mut stats = DailyStats::default();
+    let mut latencies = vec![];
+    for row in &rows {
+        stats.propagations += 1;
+        let status: TxStatus = row.try_get("status")?;
+        if matches!(status, TxStatus::Mined | TxStatus::Finalized) {
+            stats.mined += 1;
+        }
+
+        let gas_used = optional_u256(row, "gas_used")?.unwrap_or_default();
+        let gas_price = optional_u256(row, "effective_gas_price")?.unwrap_or_default();
+        stats.l1_fee += gas_used * gas_price;
+        stats.message_value += optional_u256(row, "message_value")?.unwrap_or_default();
+        stats.message_fee += optional_u256(row, "message_fee")?.unwrap_or_default();
+        stats.refund += optional_u256(row, "refund")?.unwrap_or_default();
+
+        if let Some(recorded_at) = row.try_get::<Option<DateTime<Utc>>, _>("recorded_at")? {
+            let created_at: DateTime<Utc> = row.try_get("created_at")?;
+            latencies.push((recorded_at - created_at).num_milliseconds() as f64);
+        }
+    }
+    latencies.sort_by(f64::total_cmp);
+
+    let existing = sqlx::query(
+        r#"
+        SELECT propagations, mined, l1_fee, message_value, message_fee, refund
+        FROM daily_propagation_stats
+        WHERE day = ?1
+        "#,
+    )
+    .bind(day)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    if let Some(existing) = existing {
+        // Percentiles can't be merged, the first rollup's are kept
+        sqlx::query(
+            r#"
+            UPDATE daily_propagation_stats
+            SET propagations = ?1, mined = ?2, l1_fee = ?3, message_value = ?4,
+                message_fee = ?5, refund = ?6, rolled_up_at = ?7
+            WHERE day = ?8
+            "#,
+        )
+        .bind(existing.try_get::<i64, _>("propagations")? + stats.propagations)
+        .bind(existing.try_get::<i64, _>("mined")? + stats.mined)
+        .bind((u256(&existing, "l1_fee")? + stats.l1_fee).to_string())
+        .bind((u256(&existing, "message_value")? + stats.message_value).to_string())
+        .bind((u256(&existing, "message_fee")? + stats.message_fee).to_string())
+        .bind((u256(&existing, "refund")? + stats.refund).to_string())
+        .bind(Utc::now())
+        .bind(day)
+        .execute(&mut **tx)
+        .await?;
+    } else {
+        sqlx::query(
+            r#"
+            INSERT INTO daily_propagation_stats (
+                day,
+                propagations,
+                mined,
+                l1_fee,
+                message_value,
+                message_fee,
+                refund,
+                latency_p50_ms,
+                latency_p90_ms,
+                latency_p99_ms,
+                rolled_up_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+            "#,
+        )
+        .bind(day)
+        .bind(stats.propagations)
+        .bind(stats.mined)
+        .bind(stats.l1_fee.to_string())
+        .bind(stats.message_value.to_string())
+        .bind(stats.message_fee.to_string())
+        .bind(stats.refund.to_string())
+        .bind(percentile(&latencies, 0.5))
+        .bind(percentile(&latencies, 0.9))
+        .bind(percentile(&latencies, 0.99))
+        .bind(Utc::now())
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct DailyStats {
+    propagations:  i64,
+    mined:         i64,
+    l1_fee:        U256,
+    message_value: U256,
+    message_fee:   U256,
+    refund:        U256,
+}
+
+/// Interpolates between the closest values, like Postgres'
+/// `percentile_cont`.
+fn percentile(sorted: &[f64], fraction: f64) -> Option<i64> {
+    let last = sorted.len().checked_sub(1)?;
+    let position = fraction * last as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    let value = sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64);
+
+    Some(value.round() as i64)
+}
+
+fn u256(row: &sqlx::sqlite::SqliteRow, column: &str) -> Result<U256, Error> {
+    U256::from_dec_str(&row.try_get::<String, _>(column)?)
+        .map_err(|e| Error::InvalidValue(e.to_string()))
+}
+
+fn optional_u256(row: &sqlx::sqlite::SqliteRow, column: &str) -> Result<Option<U256>, Error> {
+    row.try_get::<Option<String>, _>(column)?
+        .map(|value| U256::from_dec_str(&value))
+        .transpose()
+        .map_err(|e| Error::InvalidValue(e.to_string()))
+}
+
+/// Arguments to `json_object` for a row of `table`. SQLite has no
+/// `to_jsonb`, so the columns are listed.
+const fn json_columns(table: RetainedTable) -> &'static str {
+    match table {
+        RetainedTable::Transactions => {
+            "'transaction_id', transaction_id, 'status', status, 'created_at', created_at"
+        }
+        RetainedTable::PropagationDecisions => {
+            "'id', id, 'root', root, 'propagate', json(CASE WHEN propagate THEN 'true' ELSE \
+             'false' END), 'reason', reason, 'decided_at', decided_at"
+        }
+        RetainedTable::PropagationOutbox => {
+            "'id', id, 'root', root, 'status', status, 'attempts', attempts, 'transaction_id', \
+             transaction_id, 'last_error', last_error, 'created_at', created_at, 'claimed_at', \
+             claimed_at, 'sent_at', sent_at"
+        }
+        RetainedTable::BridgeStatusHistory => {
+            "'id', id, 'from_status', from_status, 'to_status', to_status, 'cause', cause, \
+             'changed_at', changed_at"
+        }
+    }
+}
+
+#[async_trait]
+impl BridgeStore for SqliteDatabase {
+    async fn server_initialized(&self) -> Result<bool, Error> {
+        Ok(
+            sqlx::query_scalar("SELECT EXISTS (SELECT id FROM service_status WHERE id = 1)")
+                .fetch_one(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn initialize_server(&self) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, {
+            let now = Utc::now();
+            sqlx::query("INSERT INTO service_status (id, status, last_synced) VALUES (1, ?1, ?2)")
+                .bind(BridgeStatus::Unsynced)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+            insert_status_change(&mut tx, None, BridgeStatus::Unsynced, "initialized", now).await
+        })
+        .await
+    }
+
+    async fn get_service_status(&self) -> Result<Option<ServerStatus>, Error> {
+        Ok(sqlx::query_as::<_, ServerStatus>(
+            "SELECT status, last_synced FROM service_status WHERE id = 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn get_db_status(&self) -> Result<Option<BridgeStatus>, Error> {
+        Ok(
+            sqlx::query_scalar("SELECT status FROM service_status WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn mark_status_as_pending(&self, cause: &str) -> Result<(), Error> {
+        retry_tx!(
+            self.pool,
+            tx,
+            update_status(&mut tx, BridgeStatus::Pending, cause).await
+        )
+        .await
+    }
+
+    async fn mark_status_as_unsynced(&self, cause: &str) -> Result<(), Error> {
+        retry_tx!(
+            self.pool,
+            tx,
+            update_status(&mut tx, BridgeStatus::Unsynced, cause).await
+        )
+        .await
+    }
+
+    async fn mark_status_as_synced(&self, cause: &str) -> Result<(), Error> {
+        retry_tx!(
+            self.pool,
+            tx,
+            update_status(&mut tx, BridgeStatus::Synced, cause).await
+        )
+        .await
+    }
+
+    async fn get_last_transaction_status(&self) -> Result<Option<TxStatus>, Error> {
+        Ok(
+            sqlx::query_scalar("SELECT status FROM transactions ORDER BY created_at DESC LIMIT 1")
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_last_transaction_id(&self) -> Result<Option<String>, Error> {
+        Ok(sqlx::query_scalar(
+            "SELECT transaction_id FROM transactions WHERE status = ?1 ORDER BY created_at DESC \
+             LIMIT 1",
+        )
+        .bind(TxStatus::Pending)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn get_last_transaction_time(&self) -> Result<Option<DateTime<Utc>>, Error> {
+        Ok(sqlx::query_scalar(
+            "SELECT created_at FROM transactions ORDER BY created_at DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn update_transaction(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE transactions SET status = ?1 WHERE transaction_id = ?2")
+            .bind(status)
+            .bind(transaction_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_transaction_outcome(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+        cost: Option<&PropagationCost>,
+    ) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, {
+            if let Some(cost) = cost {
+                insert_propagation_cost(&mut tx, transaction_id, cost).await?;
+            }
+
+            sqlx::query("UPDATE transactions SET status = ?1 WHERE transaction_id = ?2")
+                .bind(status)
+                .bind(transaction_id)
+                .execute(&mut *tx)
+                .await?;
+            Ok::<_, Error>(())
+        })
+        .await
+    }
+
+    async fn get_propagation_costs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<PropagationCostRecord>, Error> {
+        sqlx::query(
+            r#"
+            SELECT
+                transaction_id,
+                tx_hash,
+                block_number,
+                gas_used,
+                effective_gas_price,
+                message_value,
+                message_fee,
+                refund,
+                recorded_at
+            FROM propagation_costs
+            WHERE (?1 IS NULL OR recorded_at >= ?1)
+              AND (?2 IS NULL OR recorded_at < ?2)
+            ORDER BY recorded_at ASC
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(propagation_cost_from_row)
+        .collect()
+    }
+
+    async fn insert_propagation_decision(
+        &self,
+        root: U256,
+        decision: &PolicyDecision,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO propagation_decisions (root, propagate, reason, decided_at)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+        )
+        .bind(format!("{root:#x}"))
+        .bind(decision.propagate)
+        .bind(&decision.reason)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn enqueue_propagation(&self, root: U256) -> Result<Option<i64>, Error> {
+        retry_tx!(self.pool, tx, {
+            let intent_id: Option<i64> = sqlx::query_scalar(
+                r#"
+                INSERT INTO propagation_outbox (root, idempotency_key, created_at)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT DO NOTHING
+                RETURNING id
+                "#,
+            )
+            .bind(format!("{root:#x}"))
+            .bind(PropagationIntent::new_key())
+            .bind(Utc::now())
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if intent_id.is_some() {
+                update_status(&mut tx, BridgeStatus::Pending, "propagation queued").await?;
+            }
+            Ok::<_, Error>(intent_id)
+        })
+        .await
+    }
+
+    async fn has_open_propagation(&self) -> Result<bool, Error> {
+        Ok(sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM propagation_outbox WHERE status IN (?1, ?2))",
+        )
+        .bind(OutboxStatus::Pending)
+        .bind(OutboxStatus::Claimed)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn claim_propagation_intent(&self) -> Result<Option<PropagationIntent>, Error> {
+        Ok(sqlx::query_as::<_, PropagationIntent>(
+            r#"
+            UPDATE propagation_outbox
+            SET status = ?1, attempts = attempts + 1, claimed_at = ?3
+            WHERE id = (
+                SELECT id
+                FROM propagation_outbox
+                WHERE status IN (?2, ?1)
+                ORDER BY id
+                LIMIT 1
+            )
+            RETURNING id, root, attempts, idempotency_key
+            "#,
+        )
+        .bind(OutboxStatus::Claimed)
+        .bind(OutboxStatus::Pending)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn record_intent_error(
+        &self,
+        intent_id: i64,
+        error: &str,
+        give_up: bool,
+    ) -> Result<(), Error> {
+        retry_tx!(
+            self.pool,
+            tx,
+            record_intent_error(&mut tx, intent_id, error, give_up).await
+        )
+        .await
+    }
+
+    async fn record_propagation_sent(
+        &self,
+        intent: &PropagationIntent,
+        transaction_id: &str,
+    ) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, {
+            let now = Utc::now();
+            sqlx::query(
+                r#"
+                INSERT INTO transactions (transaction_id, created_at)
+                VALUES (?1, ?2)
+                ON CONFLICT (transaction_id) DO NOTHING
+                "#,
+            )
+            .bind(transaction_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                UPDATE propagation_outbox
+                SET status = ?1, transaction_id = ?2, sent_at = ?3, last_error = NULL
+                WHERE id = ?4
+                "#,
+            )
+            .bind(OutboxStatus::Sent)
+            .bind(transaction_id)
+            .bind(now)
+            .bind(intent.id)
+            .execute(&mut *tx)
+            .await?;
+            Ok::<_, Error>(())
+        })
+        .await
+    }
+
+    async fn record_propagation_failed(
+        &self,
+        intent: &PropagationIntent,
+        error: &str,
+    ) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, {
+            record_intent_error(&mut tx, intent.id, error, true).await?;
+            update_status(
+                &mut tx,
+                BridgeStatus::Unsynced,
+                &format!("propagation failed: {error}"),
+            )
+            .await
+        })
+        .await
+    }
+
+    async fn oldest_prunable(
+        &self,
+        table: RetainedTable,
+        before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        let sql = format!(
+            "SELECT min({column}) FROM {table} WHERE {column} < ?1 AND {prunable}",
+            table = table.name(),
+            column = table.timestamp_column(),
+            prunable = table.prunable(),
+        );
+
+        Ok(sqlx::query_scalar(&sql)
+            .bind(before)
+            .fetch_one(&self.pool)
+            .await?)
+    }
+
+    async fn prunable_rows(
+        &self,
+        table: RetainedTable,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<String>, Error> {
+        let sql = match table {
+            RetainedTable::Transactions => format!(
+                r#"
+                SELECT json_object(
+                    'transaction', json_object({columns}),
+                    'cost', CASE WHEN c.transaction_id IS NULL THEN NULL ELSE json_object(
+                        'transaction_id', c.transaction_id,
+                        'tx_hash', c.tx_hash,
+                        'block_number', c.block_number,
+                        'gas_used', c.gas_used,
+                        'effective_gas_price', c.effective_gas_price,
+                        'message_value', c.message_value,
+                        'message_fee', c.message_fee,
+                        'refund', c.refund,
+                        'recorded_at', c.recorded_at
+                    ) END
+                )
+                FROM transactions t
+                LEFT JOIN propagation_costs c USING (transaction_id)
+                WHERE t.created_at >= ?1 AND t.created_at < ?2 AND t.{prunable}
+                ORDER BY t.created_at
+                "#,
+                columns = json_columns(table),
+                prunable = table.prunable(),
+            ),
+            table => format!(
+                "SELECT json_object({columns}) FROM {table} WHERE {column} >= ?1 AND {column} < \
+                 ?2 AND {prunable} ORDER BY {column}",
+                columns = json_columns(table),
+                table = table.name(),
+                column = table.timestamp_column(),
+                prunable = table.prunable(),
+            ),
+        };
+
+        Ok(sqlx::query_scalar(&sql)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn prune_day(&self, table: RetainedTable, day: NaiveDate) -> Result<u64, Error> {
+        let (from, to) = day_bounds(day);
+        let sql = format!(
+            "DELETE FROM {table} WHERE {column} >= ?1 AND {column} < ?2 AND {prunable}",
+            table = table.name(),
+            column = table.timestamp_column(),
+            prunable = table.prunable(),
+        );
+
+        retry_tx!(self.pool, tx, {
+            if table == RetainedTable::Transactions {
+                roll_up_transactions(&mut tx, day, from, to).await?;
+                sqlx::query(&format!(
+                    r#"
+                    DELETE FROM propagation_costs
+                    WHERE transaction_id IN (
+                        SELECT transaction_id
+                        FROM transactions
+                        WHERE created_at >= ?1 AND created_at < ?2 AND {prunable}
+                    )
+                    "#,
+                    prunable = table.prunable(),
+                ))
+                .bind(from)
+                .bind(to)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            let deleted = sqlx::query(&sql)
+                .bind(from)
+                .bind(to)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            Ok::<_, Error>(deleted)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_percentiles() {
+        assert_eq!(percentile(&[], 0.5), None);
+        assert_eq!(percentile(&[300.0], 0.99), Some(300));
+        assert_eq!(percentile(&[100.0, 200.0, 400.0], 0.5), Some(200));
+        assert_eq!(percentile(&[100.0, 200.0, 400.0], 0.9), Some(360));
+    }
+}
@@ -0,0 +1,257 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::types::U256;
+
+use crate::database::query::DatabaseQuery;
+use crate::database::types::{
+    PropagationCostRecord, PropagationIntent, RetainedTable, ServerStatus, TxStatus,
+};
+use crate::database::{Database, Error};
+use crate::processor::cost::PropagationCost;
+use crate::processor::status::BridgeStatus;
+use crate::task_monitor::policy::PolicyDecision;
+
+/// Everything the service keeps in its database, implemented by each
+/// storage backend.
+#[async_trait]
+pub trait BridgeStore: Send + Sync + 'static {
+    async fn server_initialized(&self) -> Result<bool, Error>;
+
+    /// Writes the initial, unsynced status.
+    async fn initialize_server(&self) -> Result<(), Error>;
+
+    async fn get_service_status(&self) -> Result<Option<ServerStatus>, Error>;
+
+    async fn get_db_status(&self) -> Result<Option<BridgeStatus>, Error>;
+
+    async fn mark_status_as_pending(&self, cause: &str) -> Result<(), Error>;
+
+    async fn mark_status_as_unsynced(&self, cause: &str) -> Result<(), Error>;
+
+    /// Also updates the last synced time.
+    async fn mark_status_as_synced(&self, cause: &str) -> Result<(), Error>;
+
+    async fn get_last_transaction_status(&self) -> Result<Option<TxStatus>, Error>;
+
+    /// The most recent transaction still pending, if any.
+    async fn get_last_transaction_id(&self) -> Result<Option<String>, Error>;
+
+    async fn get_last_transaction_time(&self) -> Result<Option<DateTime<Utc>>, Error>;
+
+    async fn update_transaction(&self, transaction_id: &str, status: TxStatus)
+        -> Result<(), Error>;
+
+    /// Records how a transaction ended, along with its cost if known, in
+    /// one transaction, so that it's never marked mined without its cost.
+    async fn record_transaction_outcome(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+        cost: Option<&PropagationCost>,
+    ) -> Result<(), Error>;
+
+    /// Costs recorded within [from, to), oldest first.
+    async fn get_propagation_costs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<PropagationCostRecord>, Error>;
+
+    async fn insert_propagation_decision(
+        &self,
+        root: U256,
+        decision: &PolicyDecision,
+    ) -> Result<(), Error>;
+
+    /// Writes an intent to propagate `root` and marks the bridge as pending
+    /// in one transaction. Returns `None` if a propagation is already open.
+    async fn enqueue_propagation(&self, root: U256) -> Result<Option<i64>, Error>;
+
+    /// Whether an intent is queued or claimed but not yet sent.
+    async fn has_open_propagation(&self) -> Result<bool, Error>;
+
+    /// Claims the open intent, if any. Intents claimed by a worker that
+    /// crashed before recording the outcome are claimed again.
+    async fn claim_propagation_intent(&self) -> Result<Option<PropagationIntent>, Error>;
+
+    /// Records why sending failed. Unless `give_up` is set, the intent
+    /// stays claimed and is retried.
+    async fn record_intent_error(
+        &self,
+        intent_id: i64,
+        error: &str,
+        give_up: bool,
+    ) -> Result<(), Error>;
+
+    /// Records the relayer's transaction for a sent intent. Safe to repeat.
+    async fn record_propagation_sent(
+        &self,
+        intent: &PropagationIntent,
+        transaction_id: &str,
+    ) -> Result<(), Error>;
+
+    /// Gives up on an intent and marks the bridge as unsynced, so that the
+    /// propagation is decided on again.
+    async fn record_propagation_failed(
+        &self,
+        intent: &PropagationIntent,
+        error: &str,
+    ) -> Result<(), Error>;
+
+    /// When the oldest prunable row of `table` written before `before` was
+    /// written.
+    async fn oldest_prunable(
+        &self,
+        table: RetainedTable,
+        before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, Error>;
+
+    /// The prunable rows of `table` written within [from, to), as JSON.
+    /// Transactions come with their cost.
+    async fn prunable_rows(
+        &self,
+        table: RetainedTable,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<String>, Error>;
+
+    /// Prunes the rows of `table` written on `day`, rolling transactions up
+    /// into the daily stats first. Returns the number of rows deleted.
+    async fn prune_day(&self, table: RetainedTable, day: NaiveDate) -> Result<u64, Error>;
+}
+
+#[async_trait]
+impl BridgeStore for Database {
+    async fn server_initialized(&self) -> Result<bool, Error> {
+        self.pool.server_initialized().await
+    }
+
+    async fn initialize_server(&self) -> Result<(), Error> {
+        self.pool.initialize_server().await
+    }
+
+    async fn get_service_status(&self) -> Result<Option<ServerStatus>, Error> {
+        self.pool.get_service_status().await
+    }
+
+    async fn get_db_status(&self) -> Result<Option<BridgeStatus>, Error> {
+        self.pool.get_db_status().await
+    }
+
+    async fn mark_status_as_pending(&self, cause: &str) -> Result<(), Error> {
+        Self::mark_status_as_pending(self, cause).await
+    }
+
+    async fn mark_status_as_unsynced(&self, cause: &str) -> Result<(), Error> {
+        Self::mark_status_as_unsynced(self, cause).await
+    }
+
+    async fn mark_status_as_synced(&self, cause: &str) -> Result<(), Error> {
+        Self::mark_status_as_synced(self, cause).await
+    }
+
+    async fn get_last_transaction_status(&self) -> Result<Option<TxStatus>, Error> {
+        self.pool.get_last_transaction_status().await
+    }
+
+    async fn get_last_transaction_id(&self) -> Result<Option<String>, Error> {
+        self.pool.get_last_transaction_id().await
+    }
+
+    async fn get_last_transaction_time(&self) -> Result<Option<DateTime<Utc>>, Error> {
+        self.pool.get_last_transaction_time().await
+    }
+
+    async fn update_transaction(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+    ) -> Result<(), Error> {
+        self.pool.update_transaction(transaction_id, status).await
+    }
+
+    async fn record_transaction_outcome(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+        cost: Option<&PropagationCost>,
+    ) -> Result<(), Error> {
+        Self::record_transaction_outcome(self, transaction_id, status, cost).await
+    }
+
+    async fn get_propagation_costs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<PropagationCostRecord>, Error> {
+        self.pool.get_propagation_costs(from, to).await
+    }
+
+    async fn insert_propagation_decision(
+        &self,
+        root: U256,
+        decision: &PolicyDecision,
+    ) -> Result<(), Error> {
+        self.pool.insert_propagation_decision(root, decision).await
+    }
+
+    async fn enqueue_propagation(&self, root: U256) -> Result<Option<i64>, Error> {
+        Self::enqueue_propagation(self, root).await
+    }
+
+    async fn has_open_propagation(&self) -> Result<bool, Error> {
+        self.pool.has_open_propagation().await
+    }
+
+    async fn claim_propagation_intent(&self) -> Result<Option<PropagationIntent>, Error> {
+        self.pool.claim_propagation_intent().await
+    }
+
+    async fn record_intent_error(
+        &self,
+        intent_id: i64,
+        error: &str,
+        give_up: bool,
+    ) -> Result<(), Error> {
+        self.pool
+            .record_intent_error(intent_id, error, give_up)
+            .await
+    }
+
+    async fn record_propagation_sent(
+        &self,
+        intent: &PropagationIntent,
+        transaction_id: &str,
+    ) -> Result<(), Error> {
+        Self::record_propagation_sent(self, intent, transaction_id).await
+    }
+
+    async fn record_propagation_failed(
+        &self,
+        intent: &PropagationIntent,
+        error: &str,
+    ) -> Result<(), Error> {
+        Self::record_propagation_failed(self, intent, error).await
+    }
+
+    async fn oldest_prunable(
+        &self,
+        table: RetainedTable,
+        before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        self.pool.oldest_prunable(table, before).await
+    }
+
+    async fn prunable_rows(
+        &self,
+        table: RetainedTable,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<String>, Error> {
+        self.pool.prunable_rows(table, from, to).await
+    }
+
+    async fn prune_day(&self, table: RetainedTable, day: NaiveDate) -> Result<u64, Error> {
+        Self::prune_day(self, table, day).await
+    }
+}
@@ -1,32 +1,39 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::types::U256;
 use sqlx::{Postgres, Transaction};
 use tracing::instrument;
 
 use crate::database::query::DatabaseQuery;
+use crate::database::types::{PropagationIntent, RetainedTable, TxStatus};
 use crate::database::{Database, Error};
+use crate::processor::cost::PropagationCost;
 use crate::processor::status::BridgeStatus;
 use crate::retry_tx;
 
 pub async fn mark_status_as_pending(
     tx: &mut Transaction<'_, Postgres>,
+    cause: &str,
 ) -> Result<(), Error> {
     let status = BridgeStatus::Pending;
-    tx.update_server_status(status).await?;
+    tx.update_server_status(status, cause).await?;
     Ok(())
 }
 
 pub async fn mark_status_as_unsynced(
     tx: &mut Transaction<'_, Postgres>,
+    cause: &str,
 ) -> Result<(), Error> {
     let status = BridgeStatus::Unsynced;
-    tx.update_server_status(status).await?;
+    tx.update_server_status(status, cause).await?;
     Ok(())
 }
 
 pub async fn mark_status_as_synced(
     tx: &mut Transaction<'_, Postgres>,
+    cause: &str,
 ) -> Result<(), Error> {
     let status = BridgeStatus::Synced;
-    tx.update_server_status(status).await?;
+    tx.update_server_status(status, cause).await?;
     Ok(())
 }
 
@@ -34,19 +41,93 @@ pub async fn mark_status_as_synced(
 impl Database {
     /// marks server status as pending
     #[instrument(skip(self), level = "debug")]
-    pub async fn mark_status_as_pending(&self) -> Result<(), Error> {
-        retry_tx!(self.pool, tx, mark_status_as_pending(&mut tx).await).await
+    pub async fn mark_status_as_pending(&self, cause: &str) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, mark_status_as_pending(&mut tx, cause).await).await
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    pub async fn enqueue_propagation(&self, root: U256) -> Result<Option<i64>, Error> {
+        retry_tx!(self.pool, tx, {
+            let intent_id = tx.insert_propagation_intent(root).await?;
+            if intent_id.is_some() {
+                mark_status_as_pending(&mut tx, "propagation queued").await?;
+            }
+            Ok::<_, Error>(intent_id)
+        })
+        .await
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    pub async fn record_propagation_sent(
+        &self,
+        intent: &PropagationIntent,
+        transaction_id: &str,
+    ) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, {
+            tx.insert_transaction_if_missing(transaction_id).await?;
+            tx.mark_intent_sent(intent.id, transaction_id).await
+        })
+        .await
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    pub async fn record_transaction_outcome(
+        &self,
+        transaction_id: &str,
+        status: TxStatus,
+        cost: Option<&PropagationCost>,
+    ) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, {
+            if let Some(cost) = cost {
+                tx.insert_propagation_cost(transaction_id, cost).await?;
+            }
+            tx.update_transaction(transaction_id, status).await
+        })
+        .await
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    pub async fn record_propagation_failed(
+        &self,
+        intent: &PropagationIntent,
+        error: &str,
+    ) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, {
+            tx.record_intent_error(intent.id, error, true).await?;
+            mark_status_as_unsynced(&mut tx, &format!("propagation failed: {error}")).await
+        })
+        .await
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    pub async fn prune_day(&self, table: RetainedTable, day: NaiveDate) -> Result<u64, Error> {
+        let (from, to) = day_bounds(day);
+        retry_tx!(self.pool, tx, {
+            if table == RetainedTable::Transactions {
+                tx.roll_up_transactions(day, from, to).await?;
+                tx.delete_propagation_costs(from, to).await?;
+            }
+            tx.delete_prunable_rows(table, from, to).await
+        })
+        .await
     }
 
     ///  marks server status as synced and updates last synced time
     #[instrument(skip(self), level = "debug")]
-    pub async fn mark_status_as_synced(&self) -> Result<(), Error> {
-        retry_tx!(self.pool, tx, mark_status_as_synced(&mut tx).await).await
+    pub async fn mark_status_as_synced(&self, cause: &str) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, mark_status_as_synced(&mut tx, cause).await).await
     }
 
     /// marks following server as unsynced
     #[instrument(skip(self), level = "debug")]
-    pub async fn mark_status_as_unsynced(&self) -> Result<(), Error> {
-        retry_tx!(self.pool, tx, mark_status_as_unsynced(&mut tx).await).await
+    pub async fn mark_status_as_unsynced(&self, cause: &str) -> Result<(), Error> {
+        retry_tx!(self.pool, tx, mark_status_as_unsynced(&mut tx, cause).await).await
     }   
 }
+
+/// The start of `day` and of the day after, in UTC.
+#[must_use]
+pub fn day_bounds(day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let from = day.and_time(chrono::NaiveTime::MIN).and_utc();
+    (from, from + chrono::Duration::days(1))
+}
@@ -4,14 +4,56 @@ use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use thiserror::Error;
 
+use crate::processor::cost::PropagationCost;
+use crate::processor::status::BridgeStatus;
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerStatus {
-    pub status: String,
+    pub status:      BridgeStatus,
     pub last_synced: Option<DateTime<Utc>>,
 }
 
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropagationCostRecord {
+    pub transaction_id: String,
+    pub cost:           PropagationCost,
+    pub recorded_at:    DateTime<Utc>,
+}
+
+/// An intent to propagate a root, claimed and sent by the outbox worker.
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct PropagationIntent {
+    pub id:              i64,
+    pub root:            String,
+    pub attempts:        i32,
+    /// Sent along as the relayer's transaction id where the relayer accepts
+    /// one, so that resending after a crash doesn't send twice.
+    pub idempotency_key: String,
+}
+
+impl PropagationIntent {
+    /// A key for a new intent. Random rather than derived from the id, which
+    /// starts over with the database, so a fresh database can't reuse a key
+    /// the relayer has already seen.
+    #[must_use]
+    pub fn new_key() -> String {
+        format!("propagation-{:032x}", rand::random::<u128>())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "camelCase")]
+#[sqlx(type_name = "outbox_status")]
+#[serde(rename_all = "camelCase")]
+pub enum OutboxStatus {
+    Pending,
+    Claimed,
+    Sent,
+    Failed,
+}
+
 #[derive(
     Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, sqlx::Type
 )]
@@ -61,3 +103,55 @@ impl From<TxStatus> for &str {
       }
   }
 }
+
+/// A table pruned by the retention task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetainedTable {
+    /// Rolled up into `daily_propagation_stats` before pruning, along with
+    /// their costs
+    Transactions,
+    PropagationDecisions,
+    /// Only sent and failed intents are pruned
+    PropagationOutbox,
+    BridgeStatusHistory,
+}
+
+impl RetainedTable {
+    pub const ALL: [Self; 4] = [
+        Self::Transactions,
+        Self::PropagationDecisions,
+        Self::PropagationOutbox,
+        Self::BridgeStatusHistory,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Transactions => "transactions",
+            Self::PropagationDecisions => "propagation_decisions",
+            Self::PropagationOutbox => "propagation_outbox",
+            Self::BridgeStatusHistory => "bridge_status_history",
+        }
+    }
+
+    /// When a row was written, rows are pruned by the day of this column.
+    #[must_use]
+    pub const fn timestamp_column(self) -> &'static str {
+        match self {
+            Self::Transactions | Self::PropagationOutbox => "created_at",
+            Self::PropagationDecisions => "decided_at",
+            Self::BridgeStatusHistory => "changed_at",
+        }
+    }
+
+    /// Further restricts which rows may be pruned. Transactions still
+    /// pending and intents still open are kept until they're settled.
+    #[must_use]
+    pub const fn prunable(self) -> &'static str {
+        match self {
+            Self::Transactions => "status <> 'pending'",
+            Self::PropagationOutbox => "status IN ('sent', 'failed')",
+            Self::PropagationDecisions | Self::BridgeStatusHistory => "TRUE",
+        }
+    }
+}
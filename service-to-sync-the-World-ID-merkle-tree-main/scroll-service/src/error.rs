@@ -0,0 +1,295 @@
+//! Errors crossing the processor and task boundaries.
+use std::time::Duration;
+
+use ethers::contract::ContractError;
+use ethers::providers::{JsonRpcError, Middleware, ProviderError, RpcError};
+use hyper::StatusCode;
+use thiserror::Error;
+
+use crate::app::ReadOnlyReason;
+use crate::contracts::decode_revert;
+use crate::database;
+use crate::ethereum::{RevertReason, TxError};
+
+#[derive(Debug, Error)]
+pub enum BridgeError {
+    #[error("RPC unavailable: {0}")]
+    RpcUnavailable(String),
+    #[error("contract mismatch: {0}")]
+    ContractMismatch(String),
+    #[error("relayer rejected transaction: {0}")]
+    RelayerRejected(String),
+    #[error("insufficient funds: {0}")]
+    InsufficientFunds(String),
+    #[error("transaction reverted: {0}")]
+    Reverted(RevertReason),
+    #[error("timed out: {0}")]
+    Timeout(String),
+    #[error("service is read-only: {0}")]
+    ReadOnly(ReadOnlyReason),
+    #[error(transparent)]
+    Database(#[from] database::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// The kind of a [`BridgeError`], used for metric labels and backoff policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    RpcUnavailable,
+    ContractMismatch,
+    RelayerRejected,
+    InsufficientFunds,
+    Reverted,
+    Timeout,
+    ReadOnly,
+    Database,
+    Io,
+}
+
+impl BridgeError {
+    #[must_use]
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Self::RpcUnavailable(_) => ErrorKind::RpcUnavailable,
+            Self::ContractMismatch(_) => ErrorKind::ContractMismatch,
+            Self::RelayerRejected(_) => ErrorKind::RelayerRejected,
+            Self::InsufficientFunds(_) => ErrorKind::InsufficientFunds,
+            Self::Reverted(_) => ErrorKind::Reverted,
+            Self::Timeout(_) => ErrorKind::Timeout,
+            Self::ReadOnly(_) => ErrorKind::ReadOnly,
+            Self::Database(_) => ErrorKind::Database,
+            Self::Io(_) => ErrorKind::Io,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+
+    #[must_use]
+    pub const fn status_code(&self) -> StatusCode {
+        self.kind().status_code()
+    }
+}
+
+impl ErrorKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::RpcUnavailable => "rpc_unavailable",
+            Self::ContractMismatch => "contract_mismatch",
+            Self::RelayerRejected => "relayer_rejected",
+            Self::InsufficientFunds => "insufficient_funds",
+            Self::Reverted => "reverted",
+            Self::Timeout => "timeout",
+            Self::ReadOnly => "read_only",
+            Self::Database => "database",
+            Self::Io => "io",
+        }
+    }
+
+    /// Whether retrying the same operation may succeed without anyone
+    /// stepping in. Contract mismatches and reverts need a config or
+    /// on-chain change first, and a read-only service needs a restart.
+    #[must_use]
+    pub const fn is_retryable(self) -> bool {
+        !matches!(
+            self,
+            Self::ContractMismatch | Self::Reverted | Self::ReadOnly
+        )
+    }
+
+    #[must_use]
+    pub const fn status_code(self) -> StatusCode {
+        match self {
+            Self::RpcUnavailable | Self::RelayerRejected => StatusCode::BAD_GATEWAY,
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::InsufficientFunds | Self::ReadOnly | Self::Database => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            Self::ContractMismatch | Self::Reverted | Self::Io => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Scales a task's base backoff. Transient failures are retried quickly,
+    /// while errors that need someone to step in back off for much longer.
+    #[must_use]
+    pub fn backoff(self, base: Duration) -> Duration {
+        match self {
+            Self::RpcUnavailable | Self::Timeout | Self::Database => base,
+            Self::RelayerRejected | Self::Io => base * 4,
+            Self::InsufficientFunds => base * 12,
+            Self::ContractMismatch | Self::Reverted | Self::ReadOnly => base * 60,
+        }
+    }
+}
+
+impl From<ProviderError> for BridgeError {
+    fn from(err: ProviderError) -> Self {
+        Self::RpcUnavailable(err.to_string())
+    }
+}
+
+impl<M: Middleware> From<ContractError<M>> for BridgeError {
+    fn from(err: ContractError<M>) -> Self {
+        match err {
+            ContractError::MiddlewareError { e } => Self::RpcUnavailable(e.to_string()),
+            ContractError::ProviderError { e } => e.into(),
+            ContractError::Revert(data) => Self::Reverted(decode_revert(&data)),
+            err => Self::ContractMismatch(err.to_string()),
+        }
+    }
+}
+
+impl From<TxError> for BridgeError {
+    fn from(err: TxError) -> Self {
+        match err {
+            TxError::Reverted(reason) => Self::Reverted(reason),
+            TxError::ReadOnly(reason) => Self::ReadOnly(reason),
+            TxError::SendTimeout | TxError::ConfirmationTimeout => Self::Timeout(err.to_string()),
+            TxError::Simulation(e) | TxError::Confirmation(e) => e.into(),
+            TxError::Fetch(_) => Self::RpcUnavailable(err.to_string()),
+            TxError::Send(ref e) if is_insufficient_funds(e) => {
+                Self::InsufficientFunds(err.to_string())
+            }
+            TxError::Fill(_)
+            | TxError::Send(_)
+            | TxError::Dropped(_)
+            | TxError::Failed(_)
+            | TxError::Parse(_)
+            | TxError::Other(_) => Self::RelayerRejected(err.to_string()),
+        }
+    }
+}
+
+impl From<tokio::time::error::Elapsed> for BridgeError {
+    fn from(err: tokio::time::error::Elapsed) -> Self {
+        Self::Timeout(err.to_string())
+    }
+}
+
+/// EIP-1474's "transaction rejected" code, which some nodes use for
+/// transactions the sender can't pay for.
+const TRANSACTION_REJECTED: i64 = -32003;
+
+/// Geth's catch-all code, which it uses for insufficient funds among many
+/// other rejections.
+const SERVER_ERROR: i64 = -32000;
+
+/// Whether sending failed because the relayer can't pay for the transaction.
+///
+/// JSON-RPC errors anywhere in the chain decide on their code. Relayers
+/// pass the node's rejection on in a plain HTTP body instead, so only if
+/// there is no JSON-RPC error are the messages matched as a fallback.
+fn is_insufficient_funds(err: &anyhow::Error) -> bool {
+    if let Some(err) = err.chain().find_map(json_rpc_error) {
+        return match err.code {
+            TRANSACTION_REJECTED => true,
+            SERVER_ERROR => mentions_insufficient_funds(&err.message),
+            _ => false,
+        };
+    }
+
+    err.chain()
+        .any(|cause| mentions_insufficient_funds(&cause.to_string()))
+}
+
+fn json_rpc_error<'a>(err: &'a (dyn std::error::Error + 'static)) -> Option<&'a JsonRpcError> {
+    if let Some(err) = err.downcast_ref::<JsonRpcError>() {
+        return Some(err);
+    }
+    if let Some(err) = err.downcast_ref::<ProviderError>() {
+        return err.as_error_response();
+    }
+    match err.downcast_ref::<oz_api::Error>() {
+        Some(oz_api::Error::JsonRpc(err)) => Some(err),
+        _ => None,
+    }
+}
+
+fn mentions_insufficient_funds(message: &str) -> bool {
+    message.to_lowercase().contains("insufficient funds")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_tx_errors() {
+        let err = BridgeError::from(TxError::Send(anyhow::anyhow!(
+            "insufficient funds for gas * price + value"
+        )));
+        assert_eq!(err.kind(), ErrorKind::InsufficientFunds);
+        assert!(err.is_retryable());
+
+        let err = BridgeError::from(TxError::Reverted(RevertReason::GasLimitZero));
+        assert_eq!(err.kind(), ErrorKind::Reverted);
+        assert!(!err.is_retryable());
+        assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let err = BridgeError::from(TxError::ConfirmationTimeout);
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert_eq!(err.status_code(), StatusCode::GATEWAY_TIMEOUT);
+
+        let err = BridgeError::from(TxError::ReadOnly(ReadOnlyReason::NoRelayer));
+        assert_eq!(err.kind(), ErrorKind::ReadOnly);
+        assert!(!err.is_retryable());
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    fn rpc_error(code: i64, message: &str) -> ProviderError {
+        ProviderError::JsonRpcClientError(Box::new(
+            ethers::providers::HttpClientError::JsonRpcError(JsonRpcError {
+                code,
+                message: message.to_string(),
+                data: None,
+            }),
+        ))
+    }
+
+    #[test]
+    fn classifies_insufficient_funds_by_rpc_code() {
+        let send = |err: tx_sitter_client::TxSitterError| {
+            BridgeError::from(TxError::Send(
+                anyhow::Error::new(err).context("Error sending transaction"),
+            ))
+            .kind()
+        };
+
+        let err = rpc_error(-32003, "transaction rejected").into();
+        assert_eq!(send(err), ErrorKind::InsufficientFunds);
+
+        let err = rpc_error(-32000, "insufficient funds for gas * price + value").into();
+        assert_eq!(send(err), ErrorKind::InsufficientFunds);
+
+        // The code decides over a message that happens to match
+        let err = rpc_error(-32602, "invalid params: insufficient funds").into();
+        assert_eq!(send(err), ErrorKind::RelayerRejected);
+
+        let err = rpc_error(-32000, "nonce too low").into();
+        assert_eq!(send(err), ErrorKind::RelayerRejected);
+
+        // Without a JSON-RPC error, the relayer's response is matched
+        let err = tx_sitter_client::TxSitterError::from_response(
+            hyper::StatusCode::BAD_REQUEST,
+            "insufficient funds for transfer".to_string(),
+        );
+        assert_eq!(send(err), ErrorKind::InsufficientFunds);
+    }
+
+    #[test]
+    fn backoff_scales_with_kind() {
+        let base = Duration::from_secs(5);
+
+        assert_eq!(ErrorKind::RpcUnavailable.backoff(base), base);
+        assert!(
+            ErrorKind::InsufficientFunds.backoff(base) > ErrorKind::RelayerRejected.backoff(base)
+        );
+        assert!(
+            ErrorKind::ContractMismatch.backoff(base) > ErrorKind::InsufficientFunds.backoff(base)
+        );
+    }
+}
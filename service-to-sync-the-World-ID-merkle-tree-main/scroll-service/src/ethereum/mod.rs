@@ -1,16 +1,42 @@
 use std::sync::Arc;
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use ethers::types::transaction::eip2718::TypedTransaction;
-use ethers::types::Address;
+use ethers::types::{Address, TransactionReceipt, U256};
 pub use read::ReadProvider;
-use tracing::instrument;
-pub use write::TxError;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{error, instrument};
+pub use write::{RevertReason, TxError};
 
 use self::write_provider::WriteProvider;
-use crate::config::Config;
+use crate::app::ReadOnlyReason;
+use crate::config::{Config, RelayerConfig};
 pub type TransactionId = String;
 
+/// The outcome of waiting for a relayed transaction to be mined.
+#[derive(Clone, Debug)]
+pub struct MinedTransaction {
+    pub succeeded: bool,
+    /// Missing when the relayer gave up on the transaction before it was mined
+    pub receipt:   Option<TransactionReceipt>,
+}
+
+/// The relayer's account as the relayer itself reports it, for relayers
+/// that do.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayerStatus {
+    pub name:        String,
+    pub enabled:     bool,
+    /// The nonce the next transaction is sent with
+    pub nonce:       u64,
+    /// The nonce of the latest mined transaction
+    pub mined_nonce: u64,
+    /// For relayers that report the balance they send from
+    pub balance:     Option<U256>,
+}
+
 pub mod read;
 pub mod write;
 
@@ -18,37 +44,85 @@ mod write_provider;
 
 #[derive(Clone, Debug)]
 pub struct Ethereum {
-    l1_read_provider:   Arc<ReadProvider>,
-    l1_write_provider:  Arc<WriteProvider>,
-    l2_read_provider:   Arc<ReadProvider>,
+    l1_read_provider:  Arc<ReadProvider>,
+    /// Missing when read-only, see `read_only`
+    l1_write_provider: Option<Arc<WriteProvider>>,
+    l2_read_provider:  Arc<ReadProvider>,
+    relayer_address:   Option<Address>,
+    read_only:         Option<ReadOnlyReason>,
 }
 
 impl Ethereum {
     #[instrument(name = "Ethereum::new", level = "debug", skip_all)]
-    pub async fn new(config: &Config) -> anyhow::Result<Self> {
+    pub async fn new(
+        config: &Config,
+        live_config: watch::Receiver<Config>,
+    ) -> anyhow::Result<Self> {
         let Some(providers_config) = &config.providers else {
             bail!("Providers config is required for Ethereum.");
         };
 
-        let Some(relayer_config) = &config.relayer else {
-            bail!("Relayer config is required for Ethereum.");
+        let network_config = config.network.as_ref();
+
+        let l1_read_provider = ReadProvider::new(
+            providers_config.l1_network_provider.clone().into(),
+            network_config.and_then(|network| network.l1_chain_id),
+        )
+        .await
+        .context("Failed to connect to the L1 provider")?;
+
+        let l2_read_provider = ReadProvider::new(
+            providers_config.l2_network_provider.clone().into(),
+            network_config.and_then(|network| network.l2_chain_id),
+        )
+        .await
+        .context("Failed to connect to the L2 provider")?;
+
+        // A relayer that fails to initialize is recoverable, the service
+        // starts read-only instead
+        let (l1_write_provider, read_only) = match &config.relayer {
+            _ if config.app.read_only => (None, Some(ReadOnlyReason::Configured)),
+            None => (None, Some(ReadOnlyReason::NoRelayer)),
+            Some(relayer_config) => {
+                match WriteProvider::new(l1_read_provider.clone(), relayer_config, live_config)
+                    .await
+                {
+                    Ok(write_provider) => (Some(Arc::new(write_provider)), None),
+                    Err(err) => {
+                        error!(?err, "Failed to initialize the relayer");
+                        (
+                            None,
+                            Some(ReadOnlyReason::RelayerUnavailable(err.to_string())),
+                        )
+                    }
+                }
+            }
         };
 
-        let l1_read_provider =
-            ReadProvider::new(providers_config.l1_network_provider.clone().into()).await?;
-
-        let l2_read_provider = ReadProvider::new(providers_config.l2_network_provider.clone().into()).await?;
-
-        let l1_write_provider: Arc<WriteProvider> =
-            Arc::new(WriteProvider::new(l1_read_provider.clone(), relayer_config).await?);
-
         Ok(Self {
             l1_read_provider: Arc::new(l1_read_provider),
             l2_read_provider: Arc::new(l2_read_provider),
             l1_write_provider,
+            relayer_address: config.relayer.as_ref().map(RelayerConfig::address),
+            read_only,
         })
     }
 
+    /// Why no transactions can be sent, if so.
+    #[must_use]
+    pub const fn read_only_reason(&self) -> Option<&ReadOnlyReason> {
+        self.read_only.as_ref()
+    }
+
+    fn write_provider(&self) -> Result<&WriteProvider, TxError> {
+        match (&self.l1_write_provider, &self.read_only) {
+            (Some(write_provider), None) => Ok(write_provider),
+            (_, reason) => Err(TxError::ReadOnly(
+                reason.clone().unwrap_or(ReadOnlyReason::NoRelayer),
+            )),
+        }
+    }
+
     #[must_use]
     pub const fn l1_provider(&self) -> &Arc<ReadProvider> {
         &self.l1_read_provider
@@ -59,29 +133,42 @@ impl Ethereum {
         &self.l2_read_provider
     }
 
+    /// The relayer's address, known even when read-only as long as a
+    /// relayer is configured.
     #[must_use]
-    pub fn address(&self) -> Address {
-        self.l1_write_provider.address()
+    pub const fn address(&self) -> Option<Address> {
+        self.relayer_address
     }
 
     pub async fn send_transaction(
         &self,
         tx: TypedTransaction,
         only_once: bool,
+        idempotency_key: Option<&str>,
     ) -> Result<TransactionId, TxError> {
-        tracing::info!(?tx, "Sending transaction");
-        self.l1_write_provider.send_transaction(tx, only_once).await
+        tracing::info!(?tx, ?idempotency_key, "Sending transaction");
+        self.write_provider()?
+            .send_transaction(tx, only_once, idempotency_key)
+            .await
     }
 
     pub async fn fetch_pending_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
-        self.l1_write_provider.fetch_pending_transactions().await
+        self.write_provider()?.fetch_pending_transactions().await
     }
 
     pub async fn fetch_mined_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
-        self.l1_write_provider.fetch_mined_transactions().await
+        self.write_provider()?.fetch_mined_transactions().await
+    }
+
+    pub async fn mine_transaction(&self, tx: TransactionId) -> Result<MinedTransaction, TxError> {
+        self.write_provider()?.mine_transaction(tx).await
     }
 
-    pub async fn mine_transaction(&self, tx: TransactionId) -> Result<bool, TxError> {
-        self.l1_write_provider.mine_transaction(tx).await
+    /// Reported even when read-only, since the relayer is still there.
+    pub async fn relayer_status(&self) -> Result<Option<RelayerStatus>, TxError> {
+        match &self.l1_write_provider {
+            Some(write_provider) => write_provider.relayer_status().await,
+            None => Ok(None),
+        }
     }
 }
@@ -1,7 +1,7 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use chrono::{Duration as ChronoDuration, Utc};
 use ethers::abi::Error as AbiError;
-use ethers::providers::{Http, Middleware, Provider};
+use ethers::providers::{Http, Middleware, Provider, ProviderError};
 use ethers::types::{BlockId, BlockNumber, Chain, U256};
 use futures::{try_join, FutureExt};
 use thiserror::Error;
@@ -9,6 +9,7 @@ use tracing::{error, info};
 use url::Url;
 
 use self::rpc_logger::RpcLogger;
+use crate::error::BridgeError;
 
 pub mod rpc_logger;
 
@@ -22,7 +23,9 @@ pub struct ReadProvider {
 }
 
 impl ReadProvider {
-    pub async fn new(url: Url) -> anyhow::Result<Self> {
+    /// Connects to the provider at `url`, failing if it isn't on
+    /// `expected_chain_id` when one is pinned.
+    pub async fn new(url: Url, expected_chain_id: Option<u64>) -> anyhow::Result<Self> {
         // Connect to the Ethereum provider
         // TODO: Allow multiple providers with failover / broadcast.
         // TODO: Requests don't seem to process in parallel. Check if this is
@@ -65,6 +68,15 @@ impl ReadProvider {
             let block_time = latest_block.time()?;
             info!(%version, %chain_id, %chain, %eip1559, %block_number, ?block_hash, %block_time, "Connected to Ethereum provider");
 
+            if let Some(expected) = expected_chain_id {
+                if chain_id != U256::from(expected) {
+                    bail!(BridgeError::ContractMismatch(format!(
+                        "Provider is on chain {chain_id} ({chain}), but chain {expected} is \
+                         pinned in the config"
+                    )));
+                }
+            }
+
             // Sanity check the block timestamp
             let now = Utc::now();
             let block_age = now - block_time;
@@ -88,6 +100,24 @@ impl ReadProvider {
     }
 }
 
+impl ReadProvider {
+    /// Returns the base fee expected for the next block, as reported by
+    /// `eth_feeHistory`. Falls back to `eth_gasPrice` on legacy chains.
+    pub async fn next_base_fee(&self) -> Result<U256, ProviderError> {
+        if self.legacy {
+            return self.get_gas_price().await;
+        }
+
+        let history = self.fee_history(1, BlockNumber::Latest, &[]).await?;
+
+        history
+            .base_fee_per_gas
+            .last()
+            .copied()
+            .ok_or_else(|| ProviderError::CustomError("Empty fee history".to_string()))
+    }
+}
+
 impl Middleware for ReadProvider {
     type Error = <InnerProvider as Middleware>::Error;
     type Inner = InnerProvider;
@@ -103,3 +133,47 @@ pub enum EventError {
     #[error("Error parsing log event: {0}")]
     Parsing(#[from] AbiError),
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use ethers::types::{Block, TxHash, H256};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::task_monitor::tasks::testing;
+
+    /// Answers the queries made on connecting, as chain 31337.
+    async fn json_rpc(Json(request): Json<Value>) -> Json<Value> {
+        let result = match request["method"].as_str() {
+            Some("web3_clientVersion") => json!("stub"),
+            Some("eth_chainId") => json!("0x7a69"),
+            Some("eth_getBlockByNumber") => serde_json::to_value(Block::<TxHash> {
+                hash: Some(H256::repeat_byte(0x11)),
+                number: Some(1.into()),
+                timestamp: Utc::now().timestamp().unsigned_abs().into(),
+                ..Block::default()
+            })
+            .unwrap(),
+            _ => Value::Null,
+        };
+
+        Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+    }
+
+    #[tokio::test]
+    async fn rejects_a_mismatched_chain_id() {
+        let addr = testing::serve(Router::new().route("/", post(json_rpc)));
+        let url: Url = format!("http://{addr}").parse().unwrap();
+
+        let provider = ReadProvider::new(url.clone(), Some(31337)).await.unwrap();
+        assert_eq!(provider.chain_id, U256::from(31337));
+
+        let err = ReadProvider::new(url, Some(1)).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BridgeError>(),
+            Some(BridgeError::ContractMismatch(_))
+        ));
+    }
+}
@@ -1,9 +1,42 @@
 use std::error::Error;
+use std::fmt;
 
 use ethers::providers::ProviderError;
-use ethers::types::{TransactionReceipt, H256};
+use ethers::types::{Address, Bytes, TransactionReceipt, H256};
 use thiserror::Error;
 
+use crate::app::ReadOnlyReason;
+
+/// Why a transaction reverted, decoded against the bridge's custom errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    AddressZero,
+    CannotRenounceOwnership,
+    GasLimitZero,
+    OwnableInvalidOwner(Address),
+    OwnableUnauthorizedAccount(Address),
+    /// A plain `revert("...")`, e.g. from the Scroll messenger
+    Message(String),
+    /// Revert data that doesn't match any known error
+    Unknown(Bytes),
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddressZero => write!(f, "AddressZero()"),
+            Self::CannotRenounceOwnership => write!(f, "CannotRenounceOwnership()"),
+            Self::GasLimitZero => write!(f, "GasLimitZero()"),
+            Self::OwnableInvalidOwner(owner) => write!(f, "OwnableInvalidOwner({owner:?})"),
+            Self::OwnableUnauthorizedAccount(account) => {
+                write!(f, "OwnableUnauthorizedAccount({account:?})")
+            }
+            Self::Message(message) => write!(f, "{message}"),
+            Self::Unknown(data) => write!(f, "unknown revert data {data}"),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 #[allow(dead_code)] // Unused variants
 pub enum TxError {
@@ -31,6 +64,15 @@ pub enum TxError {
     #[error("Transaction failed: {0:?}.")]
     Failed(Option<TransactionReceipt>),
 
+    #[error("Error simulating transaction: {0}")]
+    Simulation(ProviderError),
+
+    #[error("Transaction would revert: {0}")]
+    Reverted(RevertReason),
+
+    #[error("Not sending transactions, the service is read-only: {0}")]
+    ReadOnly(ReadOnlyReason),
+
     #[error("Error parsing transaction id: {0}")]
     Parse(Box<dyn Error + Send + Sync + 'static>),
 
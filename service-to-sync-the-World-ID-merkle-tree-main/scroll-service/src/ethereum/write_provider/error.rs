@@ -5,10 +5,10 @@ use thiserror::Error;
 pub enum Error {
     #[error("Transport error")]
     Transport(#[from] ethers::providers::HttpClientError),
-    #[error("Authentication error")]
-    Authentication,
-    #[error("Request failed")]
-    RequestFailed,
+    #[error("Authentication error: {0}")]
+    Authentication(#[source] oz_api::Error),
+    #[error("Request failed: {0}")]
+    RequestFailed(#[source] oz_api::Error),
     #[error("Unknown response format")]
     UnknownResponseFormat,
     #[error("Missing transaction id")]
@@ -40,11 +40,16 @@ impl From<Error> for ProviderError {
 impl From<oz_api::Error> for Error {
     fn from(value: oz_api::Error) -> Self {
         match value {
-            oz_api::Error::AuthFailed(_) | oz_api::Error::Unauthorized => Self::Authentication,
+            oz_api::Error::AuthFailed(_)
+            | oz_api::Error::Unauthorized
+            | oz_api::Error::Forbidden { .. } => Self::Authentication(value),
             oz_api::Error::Reqwest(_)
             | oz_api::Error::Headers(_)
             | oz_api::Error::UrlParseError(_)
-            | oz_api::Error::InvalidResponse(_) => Self::RequestFailed,
+            | oz_api::Error::NotFound(_)
+            | oz_api::Error::Rejected { .. }
+            | oz_api::Error::Server { .. }
+            | oz_api::Error::JsonRpc(_) => Self::RequestFailed(value),
             oz_api::Error::ParseError(_) => Self::UnknownResponseFormat,
         }
     }
@@ -1,15 +1,18 @@
 use ethers::types::transaction::eip2718::TypedTransaction;
 use ethers::types::H256;
 
-use crate::ethereum::TxError;
+use crate::ethereum::{RelayerStatus, TxError};
 use crate::utils::TransactionId;
 
 #[async_trait::async_trait]
 pub trait Inner: Send + Sync + 'static {
+    /// Relayers that accept an `idempotency_key` use it as the transaction
+    /// id, so that sending the same key twice sends only once.
     async fn send_transaction(
         &self,
         tx: TypedTransaction,
         only_once: bool,
+        idempotency_key: Option<&str>,
     ) -> Result<TransactionId, TxError>;
 
     async fn fetch_pending_transactions(&self) -> Result<Vec<TransactionId>, TxError>;
@@ -17,6 +20,10 @@ pub trait Inner: Send + Sync + 'static {
     async fn mine_transaction(&self, tx: TransactionId) -> Result<TransactionResult, TxError>;
 
     async fn fetch_mined_transactions(&self) -> Result<Vec<TransactionId>, TxError>;
+
+    async fn relayer_status(&self) -> Result<Option<RelayerStatus>, TxError> {
+        Ok(None)
+    }
 }
 
 pub struct TransactionResult {
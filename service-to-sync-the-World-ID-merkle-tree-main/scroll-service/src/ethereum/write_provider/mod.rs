@@ -4,13 +4,14 @@ use std::sync::Arc;
 use ethers::providers::Middleware;
 use ethers::types::transaction::eip2718::TypedTransaction;
 use ethers::types::{Address, U64};
+use tokio::sync::watch;
 use tracing::{info, warn};
 
 use self::inner::Inner;
 use self::openzeppelin::OzRelay;
 use self::tx_sitter::TxSitter;
-use super::{ReadProvider, TxError};
-use crate::config::RelayerConfig;
+use super::{MinedTransaction, ReadProvider, RelayerStatus, TxError};
+use crate::config::{Config, RelayerConfig};
 use crate::utils::TransactionId;
 
 
@@ -36,17 +37,21 @@ impl fmt::Debug for WriteProvider {
 }
 
 impl WriteProvider {
-    pub async fn new(read_provider: ReadProvider, config: &RelayerConfig) -> anyhow::Result<Self> {
+    pub async fn new(
+        read_provider: ReadProvider,
+        config: &RelayerConfig,
+        live_config: watch::Receiver<Config>,
+    ) -> anyhow::Result<Self> {
         let address = config.address();
 
         let inner: Arc<dyn Inner> = match config {
             RelayerConfig::OzDefender(oz_config) => {
                 tracing::info!("Initializing OZ Relayer");
-                Arc::new(OzRelay::new(oz_config).await?)
+                Arc::new(OzRelay::new(oz_config, live_config).await?)
             }
             RelayerConfig::TxSitter(tx_sitter_config) => {
                 tracing::info!("Initializing TxSitter");
-                Arc::new(TxSitter::new(tx_sitter_config))
+                Arc::new(TxSitter::new(tx_sitter_config, live_config)?)
             }
         };
 
@@ -61,8 +66,11 @@ impl WriteProvider {
         &self,
         tx: TypedTransaction,
         only_once: bool,
+        idempotency_key: Option<&str>,
     ) -> Result<TransactionId, TxError> {
-        self.inner.send_transaction(tx, only_once).await
+        self.inner
+            .send_transaction(tx, only_once, idempotency_key)
+            .await
     }
 
     pub async fn fetch_pending_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
@@ -73,13 +81,20 @@ impl WriteProvider {
         self.inner.fetch_mined_transactions().await
     }
 
-    pub async fn mine_transaction(&self, tx: TransactionId) -> Result<bool, TxError> {
+    pub async fn relayer_status(&self) -> Result<Option<RelayerStatus>, TxError> {
+        self.inner.relayer_status().await
+    }
+
+    pub async fn mine_transaction(&self, tx: TransactionId) -> Result<MinedTransaction, TxError> {
         let oz_transaction_result = self.inner.mine_transaction(tx.clone()).await;
 
-        if let Err(TxError::Failed(_)) = oz_transaction_result {
+        if let Err(TxError::Failed(receipt)) = oz_transaction_result {
             warn!(?tx, "Transaction failed in OZ Relayer");
 
-            return Ok(false);
+            return Ok(MinedTransaction {
+                succeeded: false,
+                receipt,
+            });
         }
 
         let oz_transaction = oz_transaction_result?;
@@ -106,16 +121,14 @@ impl WriteProvider {
             )))
         })?;
 
-        if tx.status == Some(U64::from(1u64)) {
-            Ok(true)
-        } else {
+        let succeeded = tx.status == Some(U64::from(1u64));
+        if !succeeded {
             warn!(?tx, "Transaction failed");
-
-            Ok(false)
         }
-    }
 
-    pub fn address(&self) -> Address {
-        self.address
+        Ok(MinedTransaction {
+            succeeded,
+            receipt: Some(tx),
+        })
     }
 }
@@ -3,16 +3,19 @@ use std::time::Duration;
 
 use ethers::types::transaction::eip2718::TypedTransaction;
 use once_cell::sync::Lazy;
-use oz_api::data::transactions::{RelayerTransactionBase, SendBaseTransactionRequest, Status};
+use oz_api::data::transactions::{
+    RelayerTransactionBase, SendBaseTransactionRequest, Status, StatusFilter,
+};
 use oz_api::OzApi;
 use prometheus::{register_int_counter_vec, IntCounterVec};
+use tokio::sync::watch;
 use tokio::time::timeout;
 use tracing::{error, info, info_span, Instrument};
 
 use super::error::Error;
 use super::inner::{Inner, TransactionResult};
-use crate::config::OzDefenderConfig;
-use crate::ethereum::TxError;
+use crate::config::{Config, OzDefenderConfig, RelayerConfig};
+use crate::ethereum::{RelayerStatus, TxError};
 use crate::utils::TransactionId;
 
 
@@ -29,11 +32,15 @@ pub struct OzRelay {
     transaction_validity: chrono::Duration,
     send_timeout:         Duration,
     mine_timeout:         Duration,
-    gas_limit:            Option<u64>,
+    /// Read on every use, so that reloaded poll intervals and gas limits apply
+    live_config:          watch::Receiver<Config>,
 }
 
 impl OzRelay {
-    pub async fn new(options: &OzDefenderConfig) -> anyhow::Result<Self> {
+    pub async fn new(
+        options: &OzDefenderConfig,
+        live_config: watch::Receiver<Config>,
+    ) -> anyhow::Result<Self> {
         let oz_api = if options.oz_api_key.is_empty() && options.oz_api_secret.is_empty() {
             tracing::warn!(
                 "OpenZeppelin Defender API Key and Secret are empty. Connection will operate \
@@ -55,7 +62,7 @@ impl OzRelay {
             transaction_validity: chrono::Duration::from_std(options.oz_transaction_validity)?,
             send_timeout: options.oz_send_timeout,
             mine_timeout: options.oz_mine_timeout,
-            gas_limit: options.oz_gas_limit,
+            live_config,
         })
     }
 
@@ -65,8 +72,17 @@ impl OzRelay {
         Ok(tx)
     }
 
-    async fn list_recent_transactions(&self) -> Result<Vec<RelayerTransactionBase>, Error> {
-        let transactions = self.oz_api.list_transactions(None, Some(10)).await?;
+    /// Transactions with `status` that could still be valid, which is every
+    /// one sent within the validity period.
+    async fn list_recent_transactions(
+        &self,
+        status: StatusFilter,
+    ) -> Result<Vec<RelayerTransactionBase>, Error> {
+        let since = chrono::Utc::now() - self.transaction_validity;
+        let transactions = self
+            .oz_api
+            .list_all_transactions(Some(status), Some(since))
+            .await?;
 
         Ok(transactions)
     }
@@ -89,8 +105,9 @@ impl OzRelay {
                 Status::Failed => return Err(TxError::Failed(None)),
                 Status::Mined | Status::Confirmed => return Ok(transaction),
                 _ => {
-                    info!("waiting 5 s to mine");
-                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    let poll_interval = self.live_config.borrow().tasks.oz_poll_interval;
+                    info!(?poll_interval, "waiting to mine");
+                    tokio::time::sleep(poll_interval).await;
                 }
             }
         }
@@ -113,6 +130,7 @@ impl OzRelay {
             gas_limit:   tx.gas(),
             data:        tx.data(),
             valid_until: Some(chrono::Utc::now() + self.transaction_validity),
+            speed:       None,
         };
 
         let tx = self.oz_api.send_transaction(api_tx).await?;
@@ -122,8 +140,10 @@ impl OzRelay {
 
     /// When `only_once` is set to true, this method tries to be idempotent.
     ///
-    /// Before submiting a transaction, it'll query `OpenZepellin` for the list
-    /// of 10 most recent transactions to see if it's not processing already
+    /// Before submiting a transaction, it'll query `OpenZepellin` for the
+    /// transactions pending within the validity period to see if it's not
+    /// processing already. Mined ones aren't matched, since every
+    /// propagation has the same calldata
     ///
     /// `OpenZeppelin` doesn't provide guarantees on how fast transactions will
     /// show up on the list of recent transactions ("order of seconds to be
@@ -135,17 +155,26 @@ impl OzRelay {
         mut tx: TypedTransaction,
         only_once: bool,
     ) -> Result<TransactionId, TxError> {
-        if let Some(gas_limit) = self.gas_limit {
+        let gas_limit = self
+            .live_config
+            .borrow()
+            .relayer
+            .as_ref()
+            .and_then(RelayerConfig::gas_limit);
+        if let Some(gas_limit) = gas_limit {
             tx.set_gas(gas_limit);
         }
 
         if only_once {
             info!("checking if can resubmit");
 
-            let existing_transactions = self.list_recent_transactions().await.map_err(|e| {
-                error!(?e, "error occurred");
-                TxError::Send(e.into())
-            })?;
+            let existing_transactions = self
+                .list_recent_transactions(StatusFilter::Pending)
+                .await
+                .map_err(|e| {
+                    error!(?e, "error occurred");
+                    TxError::Send(e.into())
+                })?;
 
             let existing_transaction =
                 existing_transactions
@@ -202,31 +231,46 @@ impl OzRelay {
     }
 
     pub async fn fetch_pending_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
-        let recent_pending_txs = self
-            .list_recent_transactions()
+        self.fetch_transactions(StatusFilter::Pending).await
+    }
+
+    pub async fn fetch_mined_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
+        self.fetch_transactions(StatusFilter::Mined).await
+    }
+
+    async fn fetch_transactions(
+        &self,
+        status: StatusFilter,
+    ) -> Result<Vec<TransactionId>, TxError> {
+        let transactions = self
+            .list_recent_transactions(status)
             .await
             .map_err(|err| TxError::Fetch(Box::new(err)))?;
 
-        let pending_txs = recent_pending_txs
+        Ok(transactions
             .into_iter()
             .map(|tx| tx.transaction_id)
-            .collect();
-
-        Ok(pending_txs)
+            .collect())
     }
 
-    pub async fn fetch_mined_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
-        let recent_pending_txs = self
-            .list_recent_transactions()
+    /// Defender doesn't report a mined nonce, so it's taken to be the one
+    /// before its pending transactions.
+    pub async fn relayer_status(&self) -> Result<RelayerStatus, TxError> {
+        let status = self
+            .oz_api
+            .relayer_status()
             .await
             .map_err(|err| TxError::Fetch(Box::new(err)))?;
 
-        let pending_txs = recent_pending_txs
-            .into_iter()
-            .map(|tx| tx.transaction_id)
-            .collect();
-
-        Ok(pending_txs)
+        Ok(RelayerStatus {
+            name:        status.name,
+            enabled:     !status.paused,
+            nonce:       status.nonce,
+            mined_nonce: status
+                .nonce
+                .saturating_sub(status.number_of_pending_transactions),
+            balance:     None,
+        })
     }
 }
 
@@ -236,7 +280,9 @@ impl Inner for OzRelay {
         &self,
         tx: TypedTransaction,
         only_once: bool,
+        _idempotency_key: Option<&str>,
     ) -> Result<TransactionId, TxError> {
+        // Defender picks its own ids, resends are caught by `only_once`
         self.send_transaction(tx, only_once).await
     }
 
@@ -256,4 +302,127 @@ impl Inner for OzRelay {
     async fn fetch_mined_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
         self.fetch_mined_transactions().await
     }
+
+    async fn relayer_status(&self) -> Result<Option<RelayerStatus>, TxError> {
+        Ok(Some(self.relayer_status().await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Query, State};
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use ethers::types::{Address, Bytes, TransactionRequest, H256};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::task_monitor::tasks::testing;
+
+    /// A Defender relayer that lists one transaction mined within the
+    /// validity period, and `pending` as pending.
+    #[derive(Default)]
+    struct StubDefender {
+        sent:    AtomicUsize,
+        pending: Mutex<Vec<Value>>,
+    }
+
+    fn relayer_tx(id: &str, data: &str, status: &str) -> Value {
+        json!({
+            "transactionId": id,
+            "hash": H256::repeat_byte(0x11),
+            "to": Address::repeat_byte(0x01),
+            "gasLimit": 100_000,
+            "data": data,
+            "validUntil": chrono::Utc::now() + chrono::Duration::hours(1),
+            "status": status
+        })
+    }
+
+    async fn list_txs(
+        State(stub): State<Arc<StubDefender>>,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> Json<Value> {
+        let items = match query.get("status").map(String::as_str) {
+            Some("pending") => stub.pending.lock().unwrap().clone(),
+            Some("mined") => vec![relayer_tx("mined-1", "0x01020304", "mined")],
+            _ => vec![],
+        };
+
+        Json(json!({ "items": items }))
+    }
+
+    async fn send_tx(State(stub): State<Arc<StubDefender>>) -> Json<Value> {
+        let sent = stub.sent.fetch_add(1, Ordering::SeqCst) + 1;
+
+        Json(relayer_tx(&format!("sent-{sent}"), "0x01020304", "pending"))
+    }
+
+    async fn relay(stub: Arc<StubDefender>) -> OzRelay {
+        let router = Router::new()
+            .route("/txs", get(list_txs).post(send_tx))
+            .route(
+                "/txs/:id",
+                get(|| async { Json(relayer_tx("mined-1", "0x01020304", "mined")) }),
+            )
+            .with_state(stub);
+        let url = format!("http://{}", testing::serve(router));
+
+        let config: OzDefenderConfig = toml::from_str(&indoc::formatdoc! {r#"
+            oz_api_url = "{url}"
+            oz_api_key = ""
+            oz_api_secret = ""
+            oz_address = "0x0000000000000000000000000000000000000042"
+        "#})
+        .unwrap();
+
+        OzRelay::new(&config, watch::channel(Config::for_tests()).1)
+            .await
+            .unwrap()
+    }
+
+    fn tx(data: &'static [u8]) -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::repeat_byte(0x01))
+            .gas(100_000)
+            .data(Bytes::from_static(data))
+            .into()
+    }
+
+    #[tokio::test]
+    async fn only_once_skips_a_pending_transaction() {
+        let stub = Arc::new(StubDefender::default());
+        stub.pending
+            .lock()
+            .unwrap()
+            .push(relayer_tx("pending-1", "0x05060708", "pending"));
+        let relay = relay(stub.clone()).await;
+
+        let tx_id = relay
+            .send_transaction(tx(&[5, 6, 7, 8]), true)
+            .await
+            .unwrap();
+        assert_eq!(tx_id, "pending-1");
+        assert_eq!(stub.sent.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn sends_propagations_one_after_another() {
+        let stub = Arc::new(StubDefender::default());
+        let relay = relay(stub.clone()).await;
+
+        // Same calldata as the transaction mined before them
+        for expected in ["sent-1", "sent-2"] {
+            let tx_id = relay
+                .send_transaction(tx(&[1, 2, 3, 4]), true)
+                .await
+                .unwrap();
+            assert_eq!(tx_id, expected);
+        }
+        assert_eq!(stub.sent.load(Ordering::SeqCst), 2);
+    }
 }
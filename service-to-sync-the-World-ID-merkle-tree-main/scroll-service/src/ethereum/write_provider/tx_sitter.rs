@@ -1,36 +1,46 @@
-use std::time::Duration;
-
 use anyhow::Context;
 use async_trait::async_trait;
 use ethers::types::transaction::eip2718::TypedTransaction;
 use ethers::types::U256;
+use tokio::sync::watch;
+use tokio::time::Instant;
 use tx_sitter_client::data::{SendTxRequest, TransactionPriority, TxStatus};
-use tx_sitter_client::TxSitterClient;
+use tx_sitter_client::{TxSitterClient, TxSitterError};
 
 use super::inner::{Inner, TransactionResult};
-use crate::config::TxSitterConfig;
-use crate::ethereum::TxError;
+use crate::config::{Config, RelayerConfig, TxSitterConfig};
+use crate::ethereum::{RelayerStatus, TxError};
 use crate::utils::TransactionId;
 
-const MINING_TIMEOUT: Duration = Duration::from_secs(60);
-
 pub struct TxSitter {
-    client:    TxSitterClient,
-    gas_limit: Option<u64>,
+    client:      TxSitterClient,
+    /// Read on every use, so that reloaded timeouts and gas limits apply
+    live_config: watch::Receiver<Config>,
 }
 
 impl TxSitter {
-    pub fn new(config: &TxSitterConfig) -> Self {
-        Self {
-            client:    TxSitterClient::new(&config.tx_sitter_url),
-            gas_limit: config.tx_sitter_gas_limit,
-        }
+    pub fn new(
+        config: &TxSitterConfig,
+        live_config: watch::Receiver<Config>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: TxSitterClient::with_options(&config.tx_sitter_url, config.client_options())?,
+            live_config,
+        })
     }
 
+    /// Polls until `tx_id` is mined, raising its priority every escalation
+    /// interval it spends pending. Once it's pending at the highest priority
+    /// too, the relayer's nonce is taken to be stuck, and the relayer is
+    /// reset once.
     pub async fn mine_transaction_inner(
         &self,
         tx_id: TransactionId,
     ) -> Result<TransactionResult, TxError> {
+        let mut priority = TransactionPriority::Regular;
+        let mut pending_since = Instant::now();
+        let mut reset = false;
+
         loop {
             let tx = self
                 .client
@@ -50,9 +60,50 @@ impl TxSitter {
                 });
             }
 
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            let (poll_interval, escalation_interval) = {
+                let config = self.live_config.borrow();
+                (
+                    config.tasks.tx_sitter_poll_interval,
+                    config.tasks.tx_sitter_escalation_interval,
+                )
+            };
+
+            if pending_since.elapsed() >= escalation_interval {
+                if let Some(escalated) = priority.escalated() {
+                    match self.client.update_tx_priority(&tx_id, escalated).await {
+                        Ok(()) => {
+                            tracing::info!(tx_id, ?escalated, "Raised transaction priority");
+                            priority = escalated;
+                        }
+                        Err(err) => {
+                            tracing::warn!(tx_id, %err, "Failed to raise transaction priority")
+                        }
+                    }
+                } else if !reset {
+                    match self.reset_relayer().await {
+                        Ok(()) => {
+                            tracing::warn!(tx_id, "Reset the relayer to clear a stuck nonce");
+                            reset = true;
+                        }
+                        Err(err) => tracing::warn!(tx_id, %err, "Failed to reset the relayer"),
+                    }
+                }
+                pending_since = Instant::now();
+            }
+
+            tokio::time::sleep(poll_interval).await;
         }
     }
+
+    async fn reset_relayer(&self) -> Result<(), TxSitterError> {
+        let relayer = self.client.get_relayer().await?;
+
+        self.client.reset_relayer(&relayer.id).await
+    }
+}
+
+fn send_error(err: TxSitterError) -> TxError {
+    TxError::Send(anyhow::Error::new(err).context("Error sending transaction"))
 }
 
 #[async_trait]
@@ -61,13 +112,20 @@ impl Inner for TxSitter {
         &self,
         mut tx: TypedTransaction,
         _only_once: bool,
+        idempotency_key: Option<&str>,
     ) -> Result<TransactionId, TxError> {
-        if let Some(gas_limit) = self.gas_limit {
+        let gas_limit = self
+            .live_config
+            .borrow()
+            .relayer
+            .as_ref()
+            .and_then(RelayerConfig::gas_limit);
+        if let Some(gas_limit) = gas_limit {
             tx.set_gas(gas_limit);
         }
 
         // TODO: Handle only_once
-        let tx = self
+        let sent = self
             .client
             .send_tx(&SendTxRequest {
                 to:        *tx
@@ -81,13 +139,23 @@ impl Inner for TxSitter {
                     .context("Missing tx gas limit")
                     .map_err(TxError::Send)?,
                 priority:  TransactionPriority::Regular,
-                tx_id:     None,
+                tx_id:     idempotency_key.map(ToString::to_string),
             })
-            .await
-            .context("Error sending transaction")
-            .map_err(TxError::Send)?;
+            .await;
 
-        Ok(tx.tx_id)
+        match (sent, idempotency_key) {
+            (Ok(tx), _) => Ok(tx.tx_id),
+            // The tx-sitter rejects a known id, which means an earlier
+            // attempt got through before we could record it
+            (Err(err), Some(tx_id)) => match self.client.get_tx(tx_id).await {
+                Ok(tx) => {
+                    tracing::info!(tx_id, "Transaction was already sent");
+                    Ok(tx.tx_id)
+                }
+                Err(_) => Err(send_error(err)),
+            },
+            (Err(err), None) => Err(send_error(err)),
+        }
     }
 
     async fn fetch_pending_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
@@ -132,9 +200,212 @@ impl Inner for TxSitter {
         Ok(tx_ids)
     }
 
+    async fn relayer_status(&self) -> Result<Option<RelayerStatus>, TxError> {
+        let relayer = self
+            .client
+            .get_relayer()
+            .await
+            .context("Error fetching relayer")
+            .map_err(TxError::Other)?;
+
+        // Read through the tx-sitter's RPC passthrough. The status is still
+        // worth reporting without it
+        let balance = match self.client.get_balance(relayer.address).await {
+            Ok(balance) => Some(balance),
+            Err(err) => {
+                tracing::warn!(%err, "Failed to fetch the relayer balance");
+                None
+            }
+        };
+
+        Ok(Some(RelayerStatus {
+            name: relayer.name,
+            enabled: relayer.enabled,
+            nonce: relayer.nonce,
+            mined_nonce: relayer.current_nonce,
+            balance,
+        }))
+    }
+
     async fn mine_transaction(&self, tx: TransactionId) -> Result<TransactionResult, TxError> {
-        tokio::time::timeout(MINING_TIMEOUT, self.mine_transaction_inner(tx))
+        let mining_timeout = self.live_config.borrow().tasks.tx_sitter_mining_timeout;
+        tokio::time::timeout(mining_timeout, self.mine_transaction_inner(tx))
             .await
             .map_err(|_| TxError::ConfirmationTimeout)?
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use ethers::types::{Address, H256};
+    use serde_json::{json, Value};
+    use tx_sitter_client::data::{GetTxResponse, RelayerInfo, UpdatePriorityRequest};
+
+    use super::*;
+    use crate::task_monitor::tasks::testing;
+
+    /// A tx-sitter that keeps a transaction pending until `mined` is set,
+    /// and fails the first attempt to raise its priority.
+    #[derive(Default)]
+    struct StubTxSitter {
+        mined:    AtomicBool,
+        /// Every priority asked for, and whether it was accepted
+        attempts: Mutex<Vec<(TransactionPriority, bool)>>,
+        /// The relayers reset
+        resets:   Mutex<Vec<String>>,
+    }
+
+    impl StubTxSitter {
+        fn serve() -> (Arc<Self>, TxSitter) {
+            let stub = Arc::new(Self::default());
+            let router = Router::new()
+                .route("/tx/:id", get(get_tx))
+                .route("/tx/:id/priority", post(update_priority))
+                .route("/relayer", get(get_relayer))
+                .route("/relayer/:id/reset", post(reset_relayer))
+                .route("/rpc", post(json_rpc))
+                .with_state(stub.clone());
+            let url = format!("http://{}", testing::serve(router));
+
+            let config: TxSitterConfig = toml::from_str(&indoc::formatdoc! {r#"
+                tx_sitter_url = "{url}"
+                tx_sitter_address = "0x0000000000000000000000000000000000000042"
+                tx_sitter_max_retries = 0
+            "#})
+            .unwrap();
+            let live_config = watch::channel(Config::for_tests()).1;
+
+            (stub, TxSitter::new(&config, live_config).unwrap())
+        }
+
+        fn attempts(&self) -> Vec<(TransactionPriority, bool)> {
+            self.attempts.lock().unwrap().clone()
+        }
+
+        fn resets(&self) -> Vec<String> {
+            self.resets.lock().unwrap().clone()
+        }
+    }
+
+    async fn get_tx(
+        State(stub): State<Arc<StubTxSitter>>,
+        Path(tx_id): Path<String>,
+    ) -> Json<GetTxResponse> {
+        let status = if stub.mined.load(Ordering::SeqCst) {
+            TxStatus::Mined
+        } else {
+            TxStatus::Pending
+        };
+
+        Json(GetTxResponse {
+            tx_id,
+            to: Address::zero(),
+            data: None,
+            value: U256::zero(),
+            gas_limit: U256::from(100_000),
+            nonce: 0,
+            tx_hash: Some(H256::repeat_byte(0x11)),
+            status: Some(status),
+        })
+    }
+
+    async fn update_priority(
+        State(stub): State<Arc<StubTxSitter>>,
+        Json(request): Json<UpdatePriorityRequest>,
+    ) -> StatusCode {
+        let mut attempts = stub.attempts.lock().unwrap();
+        let accepted = !attempts.is_empty();
+        attempts.push((request.priority, accepted));
+
+        if accepted {
+            StatusCode::OK
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+
+    async fn get_relayer() -> Json<RelayerInfo> {
+        Json(RelayerInfo {
+            id:               "relayer-1".to_string(),
+            name:             "bridge".to_string(),
+            chain_id:         1,
+            address:          Address::repeat_byte(0x42),
+            nonce:            12,
+            current_nonce:    10,
+            max_inflight_txs: 5,
+            enabled:          true,
+        })
+    }
+
+    async fn reset_relayer(
+        State(stub): State<Arc<StubTxSitter>>,
+        Path(relayer_id): Path<String>,
+    ) -> StatusCode {
+        stub.resets.lock().unwrap().push(relayer_id);
+
+        StatusCode::OK
+    }
+
+    /// Answers balance queries for the relayer's address only.
+    async fn json_rpc(Json(request): Json<Value>) -> Json<Value> {
+        let relayer = json!(Address::repeat_byte(0x42));
+        let result = match request["method"].as_str() {
+            Some("eth_getBalance") if request["params"][0] == relayer => json!("0x2a"),
+            _ => Value::Null,
+        };
+
+        Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn escalates_up_to_fastest_then_resets_the_relayer() {
+        let (stub, tx_sitter) = StubTxSitter::serve();
+        let escalation_interval = Config::for_tests().tasks.tx_sitter_escalation_interval;
+        let tx_sitter = Arc::new(tx_sitter);
+
+        let mining = tokio::spawn({
+            let tx_sitter = tx_sitter.clone();
+            async move {
+                tx_sitter
+                    .mine_transaction_inner("propagation-1".to_string())
+                    .await
+            }
+        });
+
+        // Well past the three escalations there are to make
+        tokio::time::sleep(escalation_interval * 10).await;
+        assert_eq!(stub.attempts(), [
+            (TransactionPriority::Fast, false),
+            (TransactionPriority::Fast, true),
+            (TransactionPriority::Fastest, true),
+        ]);
+        assert_eq!(stub.resets(), ["relayer-1"]);
+
+        stub.mined.store(true, Ordering::SeqCst);
+        let mined = mining.await.unwrap().unwrap();
+        assert_eq!(mined.transaction_id, "propagation-1");
+        assert_eq!(mined.hash, Some(H256::repeat_byte(0x11)));
+    }
+
+    #[tokio::test]
+    async fn reports_the_relayer_with_its_balance() {
+        let (_stub, tx_sitter) = StubTxSitter::serve();
+
+        let relayer = tx_sitter.relayer_status().await.unwrap().unwrap();
+
+        assert_eq!(relayer, RelayerStatus {
+            name:        "bridge".to_string(),
+            enabled:     true,
+            nonce:       12,
+            mined_nonce: 10,
+            balance:     Some(U256::from(42)),
+        });
+    }
+}
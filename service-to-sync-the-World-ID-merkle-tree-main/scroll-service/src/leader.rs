@@ -0,0 +1,330 @@
+//! Leader election between replicas sharing a database.
+//!
+//! Replicas compete for a Postgres advisory lock held on a dedicated
+//! connection. The lock connection sets `idle_session_timeout` to the lease,
+//! so if the leader stops renewing, Postgres drops its session and releases
+//! the lock for a follower to take.
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use prometheus::{register_gauge, register_int_counter_vec, Gauge, IntCounterVec};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, PgConnection};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use tokio::time;
+use tracing::{info, warn};
+
+use crate::config::LeaderElectionConfig;
+use crate::database::Backend;
+use crate::utils::secret::SecretUrl;
+
+static IS_LEADER: Lazy<Gauge> =
+    Lazy::new(|| register_gauge!("is_leader", "1 while this replica is the leader").unwrap());
+
+static LEADERSHIP_CHANGES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "leadership_changes",
+        "Number of times this replica gained or lost leadership.",
+        &["change"]
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderStatus {
+    pub instance_id: String,
+    pub is_leader:   bool,
+}
+
+#[derive(Debug)]
+pub struct LeaderElection {
+    config:      LeaderElectionConfig,
+    instance_id: String,
+    is_leader:   watch::Sender<bool>,
+}
+
+impl LeaderElection {
+    /// Without election, this replica is always the leader.
+    #[must_use]
+    pub fn new(config: LeaderElectionConfig) -> Self {
+        let instance_id = config
+            .instance_id
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .unwrap_or_else(|| format!("{:08x}", rand::thread_rng().gen::<u32>()));
+        let (is_leader, _) = watch::channel(!config.enabled);
+        IS_LEADER.set(if config.enabled { 0.0 } else { 1.0 });
+
+        Self {
+            config,
+            instance_id,
+            is_leader,
+        }
+    }
+
+    #[must_use]
+    pub fn is_leader(&self) -> bool {
+        *self.is_leader.borrow()
+    }
+
+    /// Notified whenever this replica gains or loses leadership.
+    #[must_use]
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.is_leader.subscribe()
+    }
+
+    #[must_use]
+    pub fn status(&self) -> LeaderStatus {
+        LeaderStatus {
+            instance_id: self.instance_id.clone(),
+            is_leader:   self.is_leader(),
+        }
+    }
+
+    /// Competes for leadership until `shutdown_receiver` fires, then
+    /// releases the lock so that a follower can take over right away.
+    pub fn campaign(
+        self: &Arc<Self>,
+        database: SecretUrl,
+        mut shutdown_receiver: broadcast::Receiver<()>,
+    ) -> Option<JoinHandle<()>> {
+        if !self.config.enabled {
+            info!("Leader election is disabled, acting as the leader");
+            return None;
+        }
+        if !matches!(Backend::of(&database), Ok(Backend::Postgres)) {
+            info!("Leader election needs Postgres, acting as the leader");
+            self.set_leader(true);
+            return None;
+        }
+
+        let election = self.clone();
+        Some(tokio::spawn(async move {
+            let mut lock: Option<PgConnection> = None;
+            let mut timer = time::interval(election.config.renew_interval);
+
+            loop {
+                tokio::select! {
+                    _ = timer.tick() => {}
+                    _ = shutdown_receiver.recv() => break,
+                }
+
+                let leading = match lock.as_mut() {
+                    Some(conn) => election.renew(conn).await,
+                    None => match election.connect(&database).await {
+                        Ok(conn) => election.try_acquire(lock.insert(conn)).await,
+                        Err(err) => Err(err),
+                    },
+                };
+
+                match leading {
+                    Ok(leading) => election.set_leader(leading),
+                    Err(err) => {
+                        warn!(?err, "Leader election connection failed");
+                        election.set_leader(false);
+                        lock = None;
+                    }
+                }
+            }
+
+            if let Some(mut conn) = lock {
+                if election.is_leader() {
+                    info!("Releasing leadership");
+                    _ = sqlx::query("SELECT pg_advisory_unlock($1)")
+                        .bind(election.config.lock_key)
+                        .execute(&mut conn)
+                        .await;
+                }
+                _ = conn.close().await;
+            }
+            election.set_leader(false);
+        }))
+    }
+
+    async fn connect(&self, database: &SecretUrl) -> Result<PgConnection, sqlx::Error> {
+        let mut conn = PgConnection::connect(database.expose()).await?;
+
+        // Postgres before 14 doesn't know the setting, the lock is then only
+        // released once the connection drops
+        let lease_ms = self.config.lease.as_millis();
+        if let Err(err) = sqlx::query(&format!("SET idle_session_timeout = {lease_ms}"))
+            .execute(&mut conn)
+            .await
+        {
+            warn!(
+                ?err,
+                "Failed to set idle_session_timeout, leases won't expire"
+            );
+        }
+
+        Ok(conn)
+    }
+
+    /// Tries to take the lock, returns whether this replica now leads.
+    async fn try_acquire(&self, conn: &mut PgConnection) -> Result<bool, sqlx::Error> {
+        self.with_timeout(
+            sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+                .bind(self.config.lock_key)
+                .fetch_one(conn),
+        )
+        .await
+    }
+
+    /// Keeps the session busy so it doesn't expire, and checks that the lock
+    /// is still ours. Followers retry taking it.
+    async fn renew(&self, conn: &mut PgConnection) -> Result<bool, sqlx::Error> {
+        if !self.is_leader() {
+            return self.try_acquire(conn).await;
+        }
+
+        // A bigint key is split into `classid` and `objid`, with `objsubid` 1
+        self.with_timeout(
+            sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM pg_locks WHERE locktype = 'advisory' AND granted \
+                 AND pid = pg_backend_pid() AND objsubid = 1 AND ((classid::bigint << 32) | \
+                 objid::bigint) = $1)",
+            )
+            .bind(self.config.lock_key)
+            .fetch_one(conn),
+        )
+        .await
+    }
+
+    /// Bounds a lock query, so that a stalled connection is given up on
+    /// well before the lease runs out.
+    async fn with_timeout<T>(
+        &self,
+        query: impl std::future::Future<Output = Result<T, sqlx::Error>>,
+    ) -> Result<T, sqlx::Error> {
+        time::timeout(self.config.renew_interval, query)
+            .await
+            .map_err(|_| {
+                sqlx::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "leader election query timed out",
+                ))
+            })?
+    }
+
+    fn set_leader(&self, leading: bool) {
+        let changed = self.is_leader.send_if_modified(|is_leader| {
+            let changed = *is_leader != leading;
+            *is_leader = leading;
+            changed
+        });
+        if !changed {
+            return;
+        }
+
+        if leading {
+            info!(instance_id = %self.instance_id, "Became the leader");
+            LEADERSHIP_CHANGES.with_label_values(&["acquired"]).inc();
+        } else {
+            warn!(instance_id = %self.instance_id, "No longer the leader");
+            LEADERSHIP_CHANGES.with_label_values(&["lost"]).inc();
+        }
+        IS_LEADER.set(if leading { 1.0 } else { 0.0 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::time::Duration;
+
+    use testcontainers::clients::Cli;
+
+    use super::*;
+
+    fn config(instance_id: &str) -> LeaderElectionConfig {
+        LeaderElectionConfig {
+            enabled:        true,
+            lock_key:       42,
+            lease:          Duration::from_secs(2),
+            renew_interval: Duration::from_millis(200),
+            instance_id:    Some(instance_id.to_string()),
+        }
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        time::timeout(Duration::from_secs(10), async {
+            while !condition() {
+                time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("Condition not met in time");
+    }
+
+    async fn with_database<F, Fut>(test: F)
+    where
+        F: FnOnce(SecretUrl) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let docker = Cli::default();
+        let db_container = postgres_docker_utils::setup(&docker).await.unwrap();
+        let url = format!(
+            "postgres://postgres:postgres@{}/postgres",
+            db_container.address()
+        );
+
+        test(url.parse().unwrap()).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires docker"]
+    async fn only_one_instance_leads() {
+        with_database(|url| async move {
+            let a = Arc::new(LeaderElection::new(config("a")));
+            let b = Arc::new(LeaderElection::new(config("b")));
+            let (stop_a, _) = broadcast::channel(1);
+            let (stop_b, _) = broadcast::channel(1);
+
+            let handle_a = a.campaign(url.clone(), stop_a.subscribe()).unwrap();
+            wait_for(|| a.is_leader()).await;
+
+            let handle_b = b.campaign(url, stop_b.subscribe()).unwrap();
+            time::sleep(Duration::from_secs(1)).await;
+            assert!(a.is_leader());
+            assert!(!b.is_leader());
+
+            // Stepping down releases the lock right away
+            stop_a.send(()).unwrap();
+            handle_a.await.unwrap();
+            assert!(!a.is_leader());
+            wait_for(|| b.is_leader()).await;
+
+            stop_b.send(()).unwrap();
+            handle_b.await.unwrap();
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires docker"]
+    async fn fails_over_when_the_lease_expires() {
+        with_database(|url| async move {
+            // A leader that takes the lock and then goes silent
+            let silent = LeaderElection::new(config("silent"));
+            let mut silent_conn = silent.connect(&url).await.unwrap();
+            assert!(silent.try_acquire(&mut silent_conn).await.unwrap());
+
+            let b = Arc::new(LeaderElection::new(config("b")));
+            let (stop_b, _) = broadcast::channel(1);
+            let handle_b = b.campaign(url, stop_b.subscribe()).unwrap();
+
+            time::sleep(Duration::from_secs(1)).await;
+            assert!(!b.is_leader());
+
+            // Postgres ends the idle session once the lease is over
+            wait_for(|| b.is_leader()).await;
+
+            stop_b.send(()).unwrap();
+            handle_b.await.unwrap();
+        })
+        .await;
+    }
+}
@@ -8,8 +8,14 @@ mod contracts;
 mod ethereum;
 mod processor;
 mod database;
+pub mod error;
+pub mod leader;
 pub mod config;
 pub mod task_monitor;
 pub mod app;
+pub mod check;
+pub mod reload;
 pub mod server;
 pub mod utils;
+
+pub use database::migrations;
@@ -9,35 +9,117 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use scroll_service::app::App;
-use scroll_service::config::{load_config, ServiceConfig};
+use scroll_service::check::check_config;
+use scroll_service::config::{load_config, Config, ServiceConfig};
+use scroll_service::migrations;
+use scroll_service::reload::watch_config_reloads;
 use scroll_service::server;
 use scroll_service::utils::shutdown::{watch_shutdown_signals, Shutdown};
 use scroll_service::task_monitor::TaskMonitor;
+use sqlx::migrate::Migration;
 use telemetry_batteries::tracing::datadog::DatadogBattery;
 use telemetry_batteries::tracing::stdout::StdoutBattery;
 use telemetry_batteries::tracing::TracingShutdownHandle;
 
 #[derive(Debug, Clone, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
     /// Path to the optional config file
     config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    /// Validate a config file and check connectivity to the providers,
+    /// contracts, relayer and database it points at
+    CheckConfig {
+        /// Path to the config file
+        config: PathBuf,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Inspect and change the database schema
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
 }
 
-#[tokio::main]
-async fn main() -> eyre::Result<()> {
+#[derive(Debug, Clone, Subcommand)]
+enum DbCommand {
+    /// Show the applied and embedded migrations, exiting with an error
+    /// unless the schema is current
+    Status {
+        /// Path to the optional config file
+        config: Option<PathBuf>,
+    },
+    /// Apply the pending migrations
+    Migrate {
+        /// Path to the optional config file
+        config: Option<PathBuf>,
+
+        /// Print the SQL instead of running it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Roll back the latest migration
+    Revert {
+        /// Path to the optional config file
+        config: Option<PathBuf>,
+
+        /// Roll back every migration after this version instead
+        #[arg(long)]
+        to: Option<i64>,
+
+        /// Print the SQL instead of running it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+fn main() -> eyre::Result<()> {
     let args = Args::parse();
+
+    if let Some(command) = args.command {
+        return tokio::runtime::Runtime::new()?.block_on(run_command(command));
+    }
+
+    let config = load_config(args.config.as_deref()).map_err(|e| eyre::eyre!("{:?}", e))?;
+    // Read by the telemetry from the environment, which is only safe to
+    // change before the runtime starts its worker threads
+    if let Some(log_level) = &config.service.log_level {
+        std::env::set_var("RUST_LOG", log_level);
+    }
+
     // telemetry_batteries::init()?;
-    scroll_service(args)
-        .await
+    tokio::runtime::Runtime::new()?
+        .block_on(scroll_service(config, args.config))
         .map_err(|e| eyre::eyre!("{:?}", e))
 }
 
-async fn scroll_service(args: Args) -> anyhow::Result<()> {
-
-    let config = load_config(args.config.as_deref())?;
+async fn run_command(command: Command) -> eyre::Result<()> {
+    match command {
+        Command::CheckConfig { config, json } => {
+            let report = check_config(&config).await;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{report}");
+            }
+            std::process::exit(i32::from(!report.passed()));
+        }
+        Command::Db { command } => db(command).await.map_err(|e| eyre::eyre!("{:?}", e)),
+    }
+}
 
+async fn scroll_service(config: Config, config_path: Option<PathBuf>) -> anyhow::Result<()> {
     let _tracing_shutdown_handle = init_telemetry(&config.service)?;
 
     let shutdown = Arc::new(Shutdown::new());
@@ -46,7 +128,7 @@ async fn scroll_service(args: Args) -> anyhow::Result<()> {
 
     let version = env!("GIT_VERSION");
 
-    tracing::info!(?config, version, "Starting the app");
+    tracing::info!(config = %config.redacted(), version, "Starting the app");
 
     let server_config = config.server.clone();
 
@@ -60,6 +142,9 @@ async fn scroll_service(args: Args) -> anyhow::Result<()> {
     // Process to push new identities to Ethereum
     task_monitor.start().await;
 
+    // Apply config changes on SIGHUP or when the config file is modified
+    watch_config_reloads(app.clone(), config_path, shutdown.clone());
+
     // Start server (will stop on shutdown signal)
     server::run(app, server_config, shutdown.clone()).await?;
 
@@ -69,6 +154,49 @@ async fn scroll_service(args: Args) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn db(command: DbCommand) -> anyhow::Result<()> {
+    match command {
+        DbCommand::Status { config } => {
+            let config = load_config(config.as_deref())?;
+            let status = migrations::status(&config.database).await?;
+            print!("{status}");
+            std::process::exit(i32::from(!status.is_current()));
+        }
+        DbCommand::Migrate { config, dry_run } => {
+            let config = load_config(config.as_deref())?;
+            let applied = migrations::apply(&config.database, dry_run).await?;
+            print_migrations(&applied, dry_run);
+        }
+        DbCommand::Revert {
+            config,
+            to,
+            dry_run,
+        } => {
+            let config = load_config(config.as_deref())?;
+            let reverted = migrations::revert(&config.database, to, dry_run).await?;
+            print_migrations(&reverted, dry_run);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_migrations(migrations: &[Migration], dry_run: bool) {
+    if migrations.is_empty() {
+        println!("Nothing to do");
+    }
+    for migration in migrations {
+        if dry_run {
+            println!(
+                "-- {:03} {}\n{}",
+                migration.version, migration.description, migration.sql
+            );
+        } else {
+            println!("{:03} {}", migration.version, migration.description);
+        }
+    }
+}
+
 fn init_telemetry(service: &ServiceConfig) -> anyhow::Result<TracingShutdownHandle> {
     if let Some(ref datadog) = service.datadog {
         Ok(DatadogBattery::init(
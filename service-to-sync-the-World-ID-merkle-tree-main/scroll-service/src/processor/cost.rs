@@ -0,0 +1,93 @@
+use ethers::types::{H256, U256};
+use once_cell::sync::Lazy;
+use prometheus::{register_counter_vec, CounterVec};
+
+static WEI_SPENT: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        "propagation_wei",
+        "Cumulative wei spent on and refunded from propagations, by kind.",
+        &["kind"]
+    )
+    .unwrap()
+});
+
+/// What a single propagation transaction cost the relayer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropagationCost {
+    pub tx_hash:             H256,
+    pub block_number:        u64,
+    pub gas_used:            U256,
+    pub effective_gas_price: U256,
+    /// The `msg.value` forwarded to the Scroll messenger
+    pub message_value:       U256,
+    /// The part of `message_value` kept by Scroll as the L2 execution fee.
+    /// Unknown unless the L1 message queue address is configured.
+    pub message_fee:         Option<U256>,
+    /// The part of `message_value` credited back to the refund address
+    pub refund:              Option<U256>,
+}
+
+impl PropagationCost {
+    #[must_use]
+    pub fn l1_fee(&self) -> U256 {
+        self.gas_used * self.effective_gas_price
+    }
+
+    /// The total amount of wei that left the relayer for good.
+    #[must_use]
+    pub fn net(&self) -> U256 {
+        (self.l1_fee() + self.message_value).saturating_sub(self.refund.unwrap_or_default())
+    }
+
+    pub fn record_metrics(&self) {
+        WEI_SPENT
+            .with_label_values(&["l1_gas"])
+            .inc_by(to_f64(self.l1_fee()));
+        WEI_SPENT
+            .with_label_values(&["message_value"])
+            .inc_by(to_f64(self.message_value));
+        if let Some(refund) = self.refund {
+            WEI_SPENT
+                .with_label_values(&["refund"])
+                .inc_by(to_f64(refund));
+        }
+    }
+}
+
+/// The outcome of mining a propagation transaction.
+#[derive(Debug, Clone)]
+pub struct TransactionOutcome {
+    pub succeeded: bool,
+    /// Missing if the transaction never made it on chain
+    pub cost:      Option<PropagationCost>,
+}
+
+fn to_f64(value: U256) -> f64 {
+    // Precision loss is fine for metrics, the exact values live in the database
+    value.to_string().parse().unwrap_or(f64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_cost() {
+        let mut cost = PropagationCost {
+            tx_hash:             H256::zero(),
+            block_number:        1,
+            gas_used:            U256::from(100_000),
+            effective_gas_price: U256::from(10),
+            message_value:       U256::from(5_000_000),
+            message_fee:         None,
+            refund:              None,
+        };
+
+        assert_eq!(cost.l1_fee(), U256::from(1_000_000));
+        assert_eq!(cost.net(), U256::from(6_000_000));
+
+        cost.message_fee = Some(U256::from(1_000_000));
+        cost.refund = Some(U256::from(4_000_000));
+        assert_eq!(cost.net(), U256::from(2_000_000));
+    }
+}
@@ -0,0 +1,67 @@
+//! Runs the processor against the scriptable chains of `fake-chain`.
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ethers::types::U256;
+use fake_chain::{FakeChain, FakeError};
+
+use super::cost::TransactionOutcome;
+use super::{Processor, TransactionId};
+use crate::error::BridgeError;
+
+impl From<FakeError> for BridgeError {
+    fn from(err: FakeError) -> Self {
+        match err {
+            FakeError::Unavailable(msg) => Self::RpcUnavailable(msg),
+            FakeError::Rejected(msg) => Self::RelayerRejected(msg),
+            FakeError::UnknownTransaction(_) => Self::RelayerRejected(err.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl Processor for FakeChain {
+    async fn propagate_root(&self, idempotency_key: &str) -> Result<TransactionId, BridgeError> {
+        Ok(self.send(idempotency_key)?)
+    }
+
+    async fn check_sync_state(&self) -> Result<bool, BridgeError> {
+        Ok(self.is_synced())
+    }
+
+    async fn get_mined_transactions(&self) -> Result<Vec<TransactionId>, BridgeError> {
+        Ok(self.mined())
+    }
+
+    async fn mine_transaction(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionOutcome, BridgeError> {
+        let receipt = self.wait_for_receipt(&transaction_id).await?;
+
+        Ok(TransactionOutcome {
+            succeeded: receipt.succeeded,
+            cost:      None,
+        })
+    }
+
+    async fn latest_root(&self) -> Result<U256, BridgeError> {
+        Ok(self.l1_root())
+    }
+
+    async fn gas_price(&self) -> Result<U256, BridgeError> {
+        Ok(FakeChain::gas_price(self))
+    }
+
+    async fn base_fee(&self) -> Result<U256, BridgeError> {
+        Ok(FakeChain::base_fee(self))
+    }
+
+    async fn relayer_balance(&self) -> Result<U256, BridgeError> {
+        Ok(FakeChain::relayer_balance(self))
+    }
+
+    async fn root_history_expiry(&self) -> Result<Duration, BridgeError> {
+        Ok(FakeChain::root_history_expiry(self))
+    }
+}
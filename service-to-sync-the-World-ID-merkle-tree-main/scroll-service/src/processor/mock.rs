@@ -0,0 +1,122 @@
+//! A scriptable processor, so that the task loops can be tested without
+//! chains or a relayer.
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ethers::types::U256;
+
+use super::cost::TransactionOutcome;
+use super::{Processor, TransactionId};
+use crate::error::BridgeError;
+use crate::ethereum::RelayerStatus;
+
+#[derive(Default)]
+pub struct MockProcessor {
+    state: Mutex<MockState>,
+}
+
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub l1_root:             U256,
+    pub l2_root:             U256,
+    pub gas_price:           U256,
+    pub base_fee:            U256,
+    pub relayer_balance:     U256,
+    pub relayer:             Option<RelayerStatus>,
+    pub root_history_expiry: Duration,
+    /// Reported as mined by the relayer
+    pub mined:               Vec<TransactionId>,
+    /// Idempotency keys of the propagations sent so far
+    pub sent:                Vec<String>,
+    /// Returned by the next propagation instead of sending it
+    pub propagation_error:   Option<BridgeError>,
+    /// Returned by the next gas price query
+    pub gas_price_error:     Option<BridgeError>,
+    /// Returned by the next wait for a transaction to be mined
+    pub mine_error:          Option<BridgeError>,
+}
+
+impl MockProcessor {
+    #[must_use]
+    pub fn new(state: MockState) -> Self {
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// The relayer's transaction id for a propagation sent with `key`.
+    #[must_use]
+    pub fn transaction_id(key: &str) -> TransactionId {
+        format!("tx-{key}")
+    }
+}
+
+#[async_trait]
+impl Processor for MockProcessor {
+    async fn propagate_root(&self, idempotency_key: &str) -> Result<TransactionId, BridgeError> {
+        let mut state = self.state();
+        if let Some(err) = state.propagation_error.take() {
+            return Err(err);
+        }
+        state.sent.push(idempotency_key.to_string());
+
+        Ok(Self::transaction_id(idempotency_key))
+    }
+
+    async fn check_sync_state(&self) -> Result<bool, BridgeError> {
+        let state = self.state();
+        Ok(state.l1_root == state.l2_root)
+    }
+
+    async fn get_mined_transactions(&self) -> Result<Vec<TransactionId>, BridgeError> {
+        Ok(self.state().mined.clone())
+    }
+
+    async fn mine_transaction(
+        &self,
+        _transaction_id: TransactionId,
+    ) -> Result<TransactionOutcome, BridgeError> {
+        if let Some(err) = self.state().mine_error.take() {
+            return Err(err);
+        }
+
+        Ok(TransactionOutcome {
+            succeeded: true,
+            cost:      None,
+        })
+    }
+
+    async fn latest_root(&self) -> Result<U256, BridgeError> {
+        Ok(self.state().l1_root)
+    }
+
+    async fn gas_price(&self) -> Result<U256, BridgeError> {
+        let mut state = self.state();
+        if let Some(err) = state.gas_price_error.take() {
+            return Err(err);
+        }
+
+        Ok(state.gas_price)
+    }
+
+    async fn base_fee(&self) -> Result<U256, BridgeError> {
+        Ok(self.state().base_fee)
+    }
+
+    async fn relayer_balance(&self) -> Result<U256, BridgeError> {
+        Ok(self.state().relayer_balance)
+    }
+
+    async fn relayer_status(&self) -> Result<Option<RelayerStatus>, BridgeError> {
+        Ok(self.state().relayer.clone())
+    }
+
+    async fn root_history_expiry(&self) -> Result<Duration, BridgeError> {
+        Ok(self.state().root_history_expiry)
+    }
+}
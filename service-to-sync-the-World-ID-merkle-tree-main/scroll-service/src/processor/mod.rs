@@ -1,22 +1,44 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use ethers::types::U256;
 
 use tracing::{error, info, instrument};
 
+pub mod cost;
+#[cfg(test)]
+pub mod fake;
+#[cfg(test)]
+pub mod mock;
 pub mod status;
 
+use self::cost::TransactionOutcome;
 use crate::contracts::ScrollBridge;
-use crate::ethereum::Ethereum;
+use crate::error::BridgeError;
+use crate::ethereum::{Ethereum, RelayerStatus};
 
 pub type TransactionId = String;
 
 #[async_trait]
 pub trait Processor: Send + Sync + 'static {
-    async fn propagate_root(&self) -> anyhow::Result<TransactionId>;
-    async fn check_sync_state(&self) -> anyhow::Result<bool>;
-    async fn get_mined_transactions(&self) -> anyhow::Result<Vec<TransactionId>>;
-    async fn mine_transaction(&self, transaction_id: TransactionId) -> anyhow::Result<bool>;
+    async fn propagate_root(&self, idempotency_key: &str) -> Result<TransactionId, BridgeError>;
+    async fn check_sync_state(&self) -> Result<bool, BridgeError>;
+    async fn get_mined_transactions(&self) -> Result<Vec<TransactionId>, BridgeError>;
+    async fn mine_transaction(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionOutcome, BridgeError>;
+    async fn latest_root(&self) -> Result<U256, BridgeError>;
+    async fn gas_price(&self) -> Result<U256, BridgeError>;
+    async fn base_fee(&self) -> Result<U256, BridgeError>;
+    async fn relayer_balance(&self) -> Result<U256, BridgeError>;
+    async fn root_history_expiry(&self) -> Result<Duration, BridgeError>;
+
+    /// The relayer's own report on its account, if it gives one.
+    async fn relayer_status(&self) -> Result<Option<RelayerStatus>, BridgeError> {
+        Ok(None)
+    }
 }
 
 pub struct BridgeProcessor {
@@ -26,24 +48,67 @@ pub struct BridgeProcessor {
 
 #[async_trait]
 impl Processor for BridgeProcessor {
-    async fn propagate_root(&self) -> anyhow::Result<TransactionId> {
-        self.propagate_root().await
+    async fn propagate_root(&self, idempotency_key: &str) -> Result<TransactionId, BridgeError> {
+        self.propagate_root(idempotency_key).await
     }
 
-    async fn check_sync_state(&self) -> anyhow::Result<bool> {
+    async fn check_sync_state(&self) -> Result<bool, BridgeError> {
         self.check_sync_state().await
     }
 
-    async fn get_mined_transactions(&self) -> anyhow::Result<Vec<TransactionId>>{
+    async fn get_mined_transactions(&self) -> Result<Vec<TransactionId>, BridgeError> {
         // Await for all mined transactions
         let mined_transactions = self.fetch_mined_transactions().await?;
         Ok(mined_transactions)
     }
 
     #[instrument(level = "debug", skip(self))]
-    async fn mine_transaction(&self, transaction_id: TransactionId) -> anyhow::Result<bool> {
+    async fn mine_transaction(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionOutcome, BridgeError> {
         let result = self.ethereum.mine_transaction(transaction_id).await?;
-        Ok(result)
+
+        let cost = match &result.receipt {
+            Some(receipt) => match self.scroll_bridge.propagation_cost(receipt).await {
+                Ok(cost) => Some(cost),
+                Err(e) => {
+                    error!(?e, "Failed to compute propagation cost");
+                    None
+                }
+            },
+            None => None,
+        };
+
+        Ok(TransactionOutcome {
+            succeeded: result.succeeded,
+            cost,
+        })
+    }
+
+    async fn latest_root(&self) -> Result<U256, BridgeError> {
+        self.scroll_bridge.get_world_id_latest_root().await
+    }
+
+    async fn gas_price(&self) -> Result<U256, BridgeError> {
+        self.scroll_bridge.get_l1_gas_price().await
+    }
+
+    async fn base_fee(&self) -> Result<U256, BridgeError> {
+        self.scroll_bridge.get_l1_base_fee().await
+    }
+
+    async fn relayer_balance(&self) -> Result<U256, BridgeError> {
+        self.scroll_bridge.get_relayer_balance().await
+    }
+
+    async fn root_history_expiry(&self) -> Result<Duration, BridgeError> {
+        let expiry = self.scroll_bridge.get_root_history_expiry().await?;
+        Ok(Duration::from_secs(expiry.low_u64()))
+    }
+
+    async fn relayer_status(&self) -> Result<Option<RelayerStatus>, BridgeError> {
+        Ok(self.ethereum.relayer_status().await?)
     }
 }
 
@@ -62,13 +127,14 @@ impl BridgeProcessor {
     #[instrument(level = "info", skip_all)]
     async fn propagate_root(
         &self,
-    ) -> anyhow::Result<TransactionId> {
+        idempotency_key: &str,
+    ) -> Result<TransactionId, BridgeError> {
 
         info!("Creating propagate root txn");
 
         let transaction_id = self
             .scroll_bridge
-            .propagate_root()
+            .propagate_root(idempotency_key)
             .await
             .map_err(|e| {
                 error!(?e, "Failed to propagate root");
@@ -85,7 +151,7 @@ impl BridgeProcessor {
 
     async fn check_sync_state(
         &self,
-    ) -> anyhow::Result<bool> {
+    ) -> Result<bool, BridgeError> {
 
         let result = self
             .scroll_bridge.check_sync_state()
@@ -98,7 +164,7 @@ impl BridgeProcessor {
     }
 
     #[instrument(level = "debug", skip_all)]
-    async fn fetch_mined_transactions(&self) -> anyhow::Result<Vec<TransactionId>> {
+    async fn fetch_mined_transactions(&self) -> Result<Vec<TransactionId>, BridgeError> {
         let pending_transactions = self.ethereum.fetch_mined_transactions().await?;
         Ok(pending_transactions)
     }
@@ -6,7 +6,7 @@ use thiserror::Error;
   Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, sqlx::Type
 )]
 #[sqlx(rename_all = "camelCase")]
-#[sqlx(type_name = "tx_status")]
+#[sqlx(type_name = "bridge_status")]
 #[serde(rename_all = "camelCase")]
 pub enum BridgeStatus {
     Unsynced,
@@ -44,7 +44,26 @@ impl From<BridgeStatus> for &str {
       match scope {
         BridgeStatus::Pending => "pending",
         BridgeStatus::Unsynced => "unsynced",
-        BridgeStatus::Synced => "Synced",
+        BridgeStatus::Synced => "synced",
       }
   }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_str() {
+        for status in [
+            BridgeStatus::Unsynced,
+            BridgeStatus::Pending,
+            BridgeStatus::Synced,
+        ] {
+            assert_eq!(
+                BridgeStatus::from_str(<&str>::from(status)).unwrap(),
+                status
+            );
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Reloads the config on SIGHUP or when the config file changes.
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter_vec, IntCounterVec};
+use tokio::time;
+use tracing::{error, info};
+
+use crate::app::App;
+use crate::config::load_config;
+use crate::utils::shutdown::Shutdown;
+
+/// How often to check the config file for changes
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+static CONFIG_RELOADS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!("config_reloads", "Config reload attempts by result.", &[
+        "result"
+    ])
+    .unwrap()
+});
+
+/// Spawns a task that re-runs `load_config` whenever the process receives
+/// SIGHUP or the config file is modified, and applies the result to `app`.
+pub fn watch_config_reloads(app: Arc<App>, path: Option<PathBuf>, shutdown: Arc<Shutdown>) {
+    tokio::spawn(async move {
+        let mut last_modified = path.as_deref().and_then(modified_at);
+        let mut file_timer = time::interval(FILE_POLL_INTERVAL);
+        let mut hangup = hangup_signal();
+
+        loop {
+            tokio::select! {
+                () = shutdown.await_shutdown() => return,
+                () = hangup.recv() => {
+                    info!("SIGHUP received, reloading config");
+                }
+                _ = file_timer.tick(), if path.is_some() => {
+                    let modified = path.as_deref().and_then(modified_at);
+                    if modified == last_modified {
+                        continue;
+                    }
+                    last_modified = modified;
+                    info!(?path, "Config file changed, reloading config");
+                }
+            }
+
+            reload(&app, path.as_deref());
+        }
+    });
+}
+
+fn reload(app: &App, path: Option<&Path>) {
+    match load_config(path) {
+        Ok(config) => {
+            let diff = app.reload_config(&config);
+            let result = if diff.restart_required.is_empty() {
+                "applied"
+            } else {
+                "restart_required"
+            };
+            CONFIG_RELOADS.with_label_values(&[result]).inc();
+        }
+        Err(err) => {
+            // Keep running with the current config
+            error!(?err, "Invalid config, ignoring reload");
+            CONFIG_RELOADS.with_label_values(&["invalid"]).inc();
+        }
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(unix)]
+struct Hangup(Option<tokio::signal::unix::Signal>);
+
+#[cfg(unix)]
+fn hangup_signal() -> Hangup {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::hangup()) {
+        Ok(signal) => Hangup(Some(signal)),
+        Err(err) => {
+            error!(
+                ?err,
+                "Failed to listen for SIGHUP, reload on file change only"
+            );
+            Hangup(None)
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Hangup {
+    async fn recv(&mut self) {
+        match &mut self.0 {
+            Some(signal) => {
+                signal.recv().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Hangup;
+
+#[cfg(not(unix))]
+const fn hangup_signal() -> Hangup {
+    Hangup
+}
+
+#[cfg(not(unix))]
+impl Hangup {
+    async fn recv(&mut self) {
+        std::future::pending().await
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Requires `Authorization: Bearer <admin_token>` on `/admin` routes. They
+/// aren't served at all unless an admin token is configured.
+///
+/// Runs before `remove_auth_layer` strips the header.
+pub async fn middleware<B>(
+    State(admin_token): State<Option<Arc<str>>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    if !request.uri().path().starts_with("/admin/") {
+        return Ok(next.run(request).await);
+    }
+
+    let Some(admin_token) = admin_token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token.as_bytes(), admin_token.as_bytes()));
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Compares in constant time, so that the token can't be guessed byte by
+/// byte from response times.
+fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::{middleware, Router};
+
+    use super::*;
+    use crate::task_monitor::tasks::testing;
+
+    fn serve(admin_token: Option<&str>) -> String {
+        let router = Router::new()
+            .route("/admin/config", get(|| async { "config" }))
+            .route("/health", get(|| async {}))
+            .layer(middleware::from_fn_with_state(
+                admin_token.map(Arc::from),
+                super::middleware,
+            ));
+        format!("http://{}", testing::serve(router))
+    }
+
+    async fn get_status(url: &str, token: Option<&str>) -> StatusCode {
+        let mut request = reqwest::Client::new().get(url);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn requires_the_admin_token() {
+        let url = serve(Some("admin-token"));
+
+        assert_eq!(
+            get_status(&format!("{url}/health"), None).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            get_status(&format!("{url}/admin/config"), None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            get_status(&format!("{url}/admin/config"), Some("admin-tokem")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            get_status(&format!("{url}/admin/config"), Some("admin-token")).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn hides_admin_routes_without_a_token() {
+        let url = serve(None);
+
+        assert_eq!(
+            get_status(&format!("{url}/health"), None).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            get_status(&format!("{url}/admin/config"), Some("")).await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
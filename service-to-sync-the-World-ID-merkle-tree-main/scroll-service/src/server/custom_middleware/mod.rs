@@ -1,3 +1,4 @@
+pub mod admin_auth_layer;
 pub mod api_metrics_layer;
 pub mod logging_layer;
 pub mod remove_auth_layer;
@@ -1,14 +1,47 @@
+use crate::app::ReadOnlyReason;
+use crate::database::types::{PropagationCostRecord, ServerStatus};
+use crate::ethereum::RelayerStatus;
+use crate::leader::LeaderStatus;
+use crate::task_monitor::balance::BalanceStatus;
+use crate::task_monitor::deferral::DeferralStatus;
+use chrono::{DateTime, Utc};
+use ethers::types::{H256, U256};
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
-use crate::database::types::ServerStatus;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct ServerStatusResponse(pub ServerStatus);
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatusResponse {
+    #[serde(flatten)]
+    pub status:    ServerStatus,
+    /// Set while a propagation is held back by a high L1 base fee
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deferral:  Option<DeferralStatus>,
+    /// Latest relayer balance reading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funds:     Option<BalanceStatus>,
+    /// Set while the service isn't sending transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<ReadOnlyReason>,
+    /// Whether this replica is the one sending transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leader:    Option<LeaderStatus>,
+    /// The relayer's own report on its account, for relayers that give one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relayer:   Option<RelayerStatus>,
+}
 
 
 impl From<ServerStatus> for ServerStatusResponse {
     fn from(value: ServerStatus) -> Self {
-        Self(value)
+        Self {
+            status:    value,
+            deferral:  None,
+            funds:     None,
+            read_only: None,
+            leader:    None,
+            relayer:   None,
+        }
     }
 }
 
@@ -19,6 +52,95 @@ impl ToResponseCode for ServerStatusResponse {
 }
 
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CostsQuery {
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to:   Option<DateTime<Utc>>,
+}
+
+/// A single propagation's cost. Amounts are decimal strings in wei.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PropagationCostEntry {
+    pub transaction_id:      String,
+    pub tx_hash:             H256,
+    pub block_number:        u64,
+    pub gas_used:            String,
+    pub effective_gas_price: String,
+    pub l1_fee:              String,
+    pub message_value:       String,
+    pub message_fee:         Option<String>,
+    pub refund:              Option<String>,
+    pub recorded_at:         DateTime<Utc>,
+}
+
+impl From<&PropagationCostRecord> for PropagationCostEntry {
+    fn from(record: &PropagationCostRecord) -> Self {
+        let cost = &record.cost;
+        Self {
+            transaction_id:      record.transaction_id.clone(),
+            tx_hash:             cost.tx_hash,
+            block_number:        cost.block_number,
+            gas_used:            cost.gas_used.to_string(),
+            effective_gas_price: cost.effective_gas_price.to_string(),
+            l1_fee:              cost.l1_fee().to_string(),
+            message_value:       cost.message_value.to_string(),
+            message_fee:         cost.message_fee.map(|fee| fee.to_string()),
+            refund:              cost.refund.map(|refund| refund.to_string()),
+            recorded_at:         record.recorded_at,
+        }
+    }
+}
+
+/// Sums over all propagations in the requested range, in wei.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CostTotals {
+    pub propagations:  usize,
+    pub l1_fee:        String,
+    pub message_value: String,
+    pub refund:        String,
+    pub net:           String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CostsResponse {
+    pub totals:       CostTotals,
+    pub propagations: Vec<PropagationCostEntry>,
+}
+
+impl From<Vec<PropagationCostRecord>> for CostsResponse {
+    fn from(records: Vec<PropagationCostRecord>) -> Self {
+        let sum = |f: fn(&PropagationCostRecord) -> U256| {
+            records
+                .iter()
+                .fold(U256::zero(), |acc, record| acc.saturating_add(f(record)))
+                .to_string()
+        };
+
+        Self {
+            totals:       CostTotals {
+                propagations:  records.len(),
+                l1_fee:        sum(|r| r.cost.l1_fee()),
+                message_value: sum(|r| r.cost.message_value),
+                refund:        sum(|r| r.cost.refund.unwrap_or_default()),
+                net:           sum(|r| r.cost.net()),
+            },
+            propagations: records.iter().map(PropagationCostEntry::from).collect(),
+        }
+    }
+}
+
+impl ToResponseCode for CostsResponse {
+    fn to_response_code(&self) -> StatusCode {
+        StatusCode::OK
+    }
+}
+
 pub trait ToResponseCode {
     fn to_response_code(&self) -> StatusCode;
 }
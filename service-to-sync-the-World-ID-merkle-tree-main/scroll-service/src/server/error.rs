@@ -4,6 +4,7 @@ use hyper::StatusCode;
 use thiserror::Error;
 
 use crate::database;
+use crate::error::BridgeError;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -30,6 +31,8 @@ pub enum Error {
     #[error(transparent)]
     Database(#[from] database::Error),
     #[error(transparent)]
+    Bridge(#[from] BridgeError),
+    #[error(transparent)]
     Other(#[from] EyreError),
 }
 
@@ -42,6 +45,7 @@ impl Error {
             Self::InvalidSerialization(_) => {
                 StatusCode::BAD_REQUEST
             }
+            Self::Bridge(err) => err.status_code(),
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
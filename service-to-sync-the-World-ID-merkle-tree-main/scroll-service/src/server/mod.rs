@@ -4,7 +4,7 @@ use std::net::TcpListener;
 use std::sync::Arc;
 use std::time::Duration;
 
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::response::Response;
 use axum::routing::get;
 use axum::{middleware, Json, Router};
@@ -17,7 +17,7 @@ use tracing::info;
 use crate::app::App;
 use crate::config::ServerConfig;
 use crate::utils::shutdown::Shutdown;
-use self::data::{ToResponseCode, ServerStatusResponse};
+use self::data::{CostsQuery, CostsResponse, ToResponseCode, ServerStatusResponse};
 
 mod custom_middleware;
 pub mod data;
@@ -30,6 +30,23 @@ async fn fetch_service_status(
     Ok((result.to_response_code(), Json(result)))
 }
 
+async fn fetch_costs(
+    State(app): State<Arc<App>>,
+    Query(query): Query<CostsQuery>,
+) -> Result<(StatusCode, Json<CostsResponse>), Error> {
+    let records = app
+        .database
+        .get_propagation_costs(query.from, query.to)
+        .await?;
+    let result = CostsResponse::from(records);
+    Ok((result.to_response_code(), Json(result)))
+}
+
+async fn fetch_config(State(app): State<Arc<App>>) -> Json<serde_json::Value> {
+    let config = app.config.borrow().redacted();
+    Json(config)
+}
+
 async fn health() -> Result<(), Error> {
     Ok(())
 }
@@ -79,9 +96,22 @@ pub async fn bind_from_listener(
     listener: TcpListener,
     shutdown: Arc<Shutdown>,
 ) -> anyhow::Result<()> {
+    let admin_token = app
+        .config
+        .borrow()
+        .server
+        .admin_token
+        .as_deref()
+        .map(Arc::from);
+
     let router = Router::new()
         // Return service status
         .route("/serviceStatus", get(fetch_service_status))
+        // Return propagation costs, optionally within a time range
+        .route("/costs", get(fetch_costs))
+        // Return the effective config with secrets redacted, given the
+        // admin token
+        .route("/admin/config", get(fetch_config))
         // Health check, return 200 OK
         .route("/health", get(health))
         .route("/metrics", get(metrics))
@@ -98,6 +128,10 @@ pub async fn bind_from_listener(
         .layer(middleware::from_fn(
             custom_middleware::remove_auth_layer::middleware,
         ))
+        .layer(middleware::from_fn_with_state(
+            admin_token,
+            custom_middleware::admin_auth_layer::middleware,
+        ))
         .with_state(app.clone());
 
     let server = axum::Server::from_tcp(listener)?
@@ -0,0 +1,193 @@
+use std::sync::RwLock as StdRwLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use ethers::types::U256;
+use once_cell::sync::Lazy;
+use prometheus::{register_gauge, Gauge};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::config::BalanceConfig;
+use crate::ethereum::RelayerStatus;
+use crate::task_monitor::GWEI;
+
+static RELAYER_BALANCE: Lazy<Gauge> =
+    Lazy::new(|| register_gauge!("relayer_balance_gwei", "Relayer balance on L1 in gwei").unwrap());
+
+static RELAYER_RUNWAY: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "relayer_runway_seconds",
+        "Estimated time until the relayer balance runs out at the recent spend rate"
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FundsStatus {
+    Ok,
+    Low,
+    InsufficientFunds,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceStatus {
+    pub status:         FundsStatus,
+    /// Relayer balance in wei, as a decimal string
+    pub balance:        String,
+    /// Unknown when nothing was spent within the runway window
+    pub runway_seconds: Option<u64>,
+    pub checked_at:     DateTime<Utc>,
+}
+
+/// Keeps the latest relayer balance reading so that propagations can be
+/// refused before they fail for lack of funds, along with the relayer's own
+/// report on its account.
+#[derive(Debug)]
+pub struct BalanceMonitor {
+    config:  StdRwLock<BalanceConfig>,
+    status:  RwLock<Option<BalanceStatus>>,
+    relayer: RwLock<Option<RelayerStatus>>,
+}
+
+impl BalanceMonitor {
+    #[must_use]
+    pub const fn new(config: BalanceConfig) -> Self {
+        Self {
+            config:  StdRwLock::new(config),
+            status:  RwLock::const_new(None),
+            relayer: RwLock::const_new(None),
+        }
+    }
+
+    #[must_use]
+    pub fn config(&self) -> BalanceConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Swaps in a reloaded config, applied from the next balance reading.
+    pub fn set_config(&self, config: BalanceConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Records a new balance reading together with the wei spent within the
+    /// runway window.
+    pub async fn update(&self, balance: U256, spent: U256, now: DateTime<Utc>) -> BalanceStatus {
+        let config = self.config();
+        let status = if balance < U256::from(config.min_balance_gwei) * GWEI {
+            FundsStatus::InsufficientFunds
+        } else if balance < U256::from(config.warn_balance_gwei) * GWEI {
+            FundsStatus::Low
+        } else {
+            FundsStatus::Ok
+        };
+
+        let runway_seconds = runway(balance, spent, config.runway_window);
+
+        #[allow(clippy::cast_precision_loss)]
+        {
+            RELAYER_BALANCE.set((balance / GWEI).low_u64() as f64);
+            RELAYER_RUNWAY.set(runway_seconds.map_or(f64::INFINITY, |s| s as f64));
+        }
+
+        let balance_status = BalanceStatus {
+            status,
+            balance: balance.to_string(),
+            runway_seconds,
+            checked_at: now,
+        };
+
+        *self.status.write().await = Some(balance_status.clone());
+
+        balance_status
+    }
+
+    pub async fn status(&self) -> Option<BalanceStatus> {
+        self.status.read().await.clone()
+    }
+
+    /// Records the relayer's report, `None` when it gave none or couldn't be
+    /// reached.
+    pub async fn set_relayer(&self, relayer: Option<RelayerStatus>) {
+        *self.relayer.write().await = relayer;
+    }
+
+    pub async fn relayer(&self) -> Option<RelayerStatus> {
+        self.relayer.read().await.clone()
+    }
+
+    /// Whether the last reading was below the hard floor. Sending is allowed
+    /// until the balance has been checked at least once.
+    pub async fn has_insufficient_funds(&self) -> bool {
+        self.status
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|s| s.status == FundsStatus::InsufficientFunds)
+    }
+}
+
+fn runway(balance: U256, spent: U256, window: Duration) -> Option<u64> {
+    if spent.is_zero() {
+        return None;
+    }
+
+    let runway = balance.saturating_mul(U256::from(window.as_secs())) / spent;
+
+    Some(if runway > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        runway.low_u64()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> BalanceMonitor {
+        BalanceMonitor::new(BalanceConfig {
+            poll_interval:     Duration::from_secs(60),
+            warn_balance_gwei: 1_000_000_000,
+            min_balance_gwei:  150_000_000,
+            runway_window:     Duration::from_secs(7 * 24 * 3600),
+        })
+    }
+
+    #[tokio::test]
+    async fn funds_status() {
+        let monitor = monitor();
+        let now = Utc::now();
+        let eth = U256::from(GWEI) * GWEI;
+
+        assert!(!monitor.has_insufficient_funds().await);
+
+        let status = monitor.update(eth * 2, U256::zero(), now).await;
+        assert_eq!(status.status, FundsStatus::Ok);
+        assert_eq!(status.runway_seconds, None);
+
+        let status = monitor.update(eth / 2, U256::zero(), now).await;
+        assert_eq!(status.status, FundsStatus::Low);
+
+        let status = monitor.update(eth / 10, U256::zero(), now).await;
+        assert_eq!(status.status, FundsStatus::InsufficientFunds);
+        assert!(monitor.has_insufficient_funds().await);
+    }
+
+    #[test]
+    fn runway_estimate() {
+        let week = Duration::from_secs(7 * 24 * 3600);
+
+        // Spending the whole balance each week leaves a week of runway
+        assert_eq!(
+            runway(U256::from(100), U256::from(100), week),
+            Some(week.as_secs())
+        );
+        assert_eq!(
+            runway(U256::from(100), U256::from(200), week),
+            Some(week.as_secs() / 2)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use ethers::types::U256;
+use once_cell::sync::Lazy;
+use prometheus::{register_gauge, register_int_counter, Gauge, IntCounter};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::GasDeferralConfig;
+use crate::task_monitor::GWEI;
+
+static DEFERRALS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "propagation_deferrals",
+        "Number of times a propagation was deferred due to a high L1 base fee."
+    )
+    .unwrap()
+});
+
+static DEFERRED: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "propagation_deferred",
+        "1 while a propagation is being deferred"
+    )
+    .unwrap()
+});
+
+static L1_BASE_FEE: Lazy<Gauge> =
+    Lazy::new(|| register_gauge!("l1_base_fee_gwei", "Last observed L1 base fee in gwei").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeferralStatus {
+    pub deferred_since:    DateTime<Utc>,
+    pub send_by:           DateTime<Utc>,
+    pub base_fee_gwei:     u64,
+    pub max_base_fee_gwei: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeferralOutcome {
+    Send,
+    Defer,
+}
+
+/// Holds back propagations while the L1 base fee is above the configured
+/// ceiling, but never for longer than `max_deferral`.
+#[derive(Debug)]
+pub struct GasDeferral {
+    config: RwLock<GasDeferralConfig>,
+    state:  Mutex<Option<DeferralStatus>>,
+}
+
+impl GasDeferral {
+    #[must_use]
+    pub fn new(config: GasDeferralConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            state:  Mutex::new(None),
+        }
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.config().max_base_fee_gwei.is_some()
+    }
+
+    #[must_use]
+    pub fn config(&self) -> GasDeferralConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Swaps in a reloaded config. An ongoing deferral keeps its deadline.
+    pub fn set_config(&self, config: GasDeferralConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Decides whether a propagation may be sent at the given base fee.
+    pub async fn check(&self, base_fee: U256, now: DateTime<Utc>) -> DeferralOutcome {
+        let config = self.config();
+        let Some(max_base_fee_gwei) = config.max_base_fee_gwei else {
+            return DeferralOutcome::Send;
+        };
+
+        let base_fee_gwei = (base_fee / GWEI).low_u64();
+        #[allow(clippy::cast_precision_loss)]
+        L1_BASE_FEE.set(base_fee_gwei as f64);
+
+        let mut state = self.state.lock().await;
+
+        if base_fee <= U256::from(max_base_fee_gwei) * GWEI {
+            if let Some(deferral) = state.take() {
+                info!(base_fee_gwei, deferred_since = %deferral.deferred_since, "Base fee back below ceiling");
+                DEFERRED.set(0.0);
+            }
+
+            return DeferralOutcome::Send;
+        }
+
+        let max_deferral = chrono::Duration::from_std(config.max_deferral).unwrap_or_default();
+        let started = state.is_none();
+        let deferral = state.get_or_insert_with(|| DeferralStatus {
+            deferred_since: now,
+            send_by: now + max_deferral,
+            base_fee_gwei,
+            max_base_fee_gwei,
+        });
+        deferral.base_fee_gwei = base_fee_gwei;
+
+        if now >= deferral.send_by {
+            warn!(
+                base_fee_gwei,
+                max_base_fee_gwei,
+                deferred_since = %deferral.deferred_since,
+                "Maximum deferral reached, sending despite high base fee"
+            );
+
+            return DeferralOutcome::Send;
+        }
+
+        info!(
+            base_fee_gwei,
+            max_base_fee_gwei,
+            send_by = %deferral.send_by,
+            "Deferring propagation due to high base fee"
+        );
+        // Counted once per deferral, not on every recheck
+        if started {
+            DEFERRALS.inc();
+        }
+        DEFERRED.set(1.0);
+
+        DeferralOutcome::Defer
+    }
+
+    /// Clears any ongoing deferral, e.g. after a propagation was sent.
+    pub async fn reset(&self) {
+        if self.state.lock().await.take().is_some() {
+            DEFERRED.set(0.0);
+        }
+    }
+
+    pub async fn status(&self) -> Option<DeferralStatus> {
+        self.state.lock().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn deferral() -> GasDeferral {
+        GasDeferral::new(GasDeferralConfig {
+            max_base_fee_gwei: Some(30),
+            max_deferral:      Duration::from_secs(3600),
+            recheck_interval:  Duration::from_secs(60),
+        })
+    }
+
+    #[tokio::test]
+    async fn defers_until_max_deferral() {
+        let deferral = deferral();
+        let start = Utc::now();
+        let high = U256::from(50) * GWEI;
+
+        assert_eq!(deferral.check(high, start).await, DeferralOutcome::Defer);
+        assert_eq!(
+            deferral
+                .check(high, start + chrono::Duration::minutes(59))
+                .await,
+            DeferralOutcome::Defer
+        );
+        assert_eq!(
+            deferral
+                .check(high, start + chrono::Duration::minutes(60))
+                .await,
+            DeferralOutcome::Send
+        );
+
+        let status = deferral.status().await.unwrap();
+        assert_eq!(status.deferred_since, start);
+        assert_eq!(status.base_fee_gwei, 50);
+    }
+
+    #[tokio::test]
+    async fn low_base_fee_clears_deferral() {
+        let deferral = deferral();
+        let now = Utc::now();
+
+        assert_eq!(
+            deferral.check(U256::from(50) * GWEI, now).await,
+            DeferralOutcome::Defer
+        );
+        assert_eq!(
+            deferral.check(U256::from(30) * GWEI, now).await,
+            DeferralOutcome::Send
+        );
+        assert!(deferral.status().await.is_none());
+    }
+}
@@ -1,4 +1,3 @@
-use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -7,20 +6,35 @@ use prometheus::{ register_gauge, Gauge};
 
 use tokio::sync::{broadcast, mpsc, Mutex, RwLock, Notify};
 use tokio::task::JoinHandle;
-use tracing::{info, instrument, warn};
-use crate::database::query::DatabaseQuery;
+use tokio::time::{self, Instant, Interval};
+use tracing::{error, info, instrument, warn};
 use crate::database::types::TxStatus;
-use crate::database::Database;
+use crate::database::BridgeStore;
 use crate::processor::status::BridgeStatus;
 use crate::utils::shutdown::Shutdown;
 use crate::app::App;
+use crate::error::BridgeError;
 
+pub mod balance;
+pub mod deferral;
+pub mod policy;
+#[cfg(test)]
+mod read_only;
+#[cfg(test)]
+mod scenarios;
 pub mod tasks;
 
-const PROPAGATE_ROOT_BACKOFF: Duration = Duration::from_secs(5);
-const CHECK_SYNC_STATE_BACKOFF: Duration = Duration::from_secs(5);
-const MONITOR_TXNS_BACKOFF: Duration = Duration::from_secs(5);
-const FINALIZE_TXNS_BACKOFF: Duration = Duration::from_secs(5);
+/// Gas prices, base fees and balances are configured in gwei.
+pub(crate) const GWEI: u64 = 1_000_000_000;
+
+/// Restarts `timer` with `period` if the configured period changed, so that
+/// config reloads apply to running tasks.
+pub(crate) fn reset_period(timer: &mut Interval, period: Duration) {
+    if timer.period() != period {
+        info!(?period, "Task interval changed");
+        *timer = time::interval_at(Instant::now() + period, period);
+    }
+}
 
 struct RunningInstance {
     handles:         Vec<JoinHandle<()>>,
@@ -31,6 +45,14 @@ static SYNCED_STATE: Lazy<Gauge> = Lazy::new(|| {
     register_gauge!("synced_state", "current scroll bridge sync status").unwrap()
 });
 
+static READ_ONLY: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "read_only",
+        "1 while the service runs without sending transactions"
+    )
+    .unwrap()
+});
+
 impl RunningInstance {
     async fn shutdown(self) -> anyhow::Result<()> {
         info!("Sending a shutdown signal to the service.");
@@ -52,9 +74,10 @@ impl RunningInstance {
 /// A worker that commits identities to the blockchain.
 ///
 /// This uses the database to keep track of identities that need to be
-/// committed. It assumes that there's only one such worker spawned at
-/// a time. Spawning multiple worker threads will result in undefined behavior,
-/// including data duplication.
+/// committed. Replicas sharing a database elect a leader, see
+/// [`LeaderElection`](crate::leader::LeaderElection), and only the leader
+/// sends and tracks transactions. Running replicas with leader election
+/// disabled will result in undefined behavior, including data duplication.
 pub struct TaskMonitor {
     /// The instance is kept behind an RwLock<Option<...>> because
     /// when shutdown is called we want to be able to gracefully
@@ -85,104 +108,256 @@ impl TaskMonitor {
         // but for symmetry's sake we create it for every task with `.subscribe()`
         let (shutdown_sender, _) = broadcast::channel(1);
 
+        let mut handles = Vec::new();
+
+        let base_wake_up_notify = Arc::new(Notify::new());
+
+        let read_only = self.app.read_only.is_some();
+        READ_ONLY.set(if read_only { 1.0 } else { 0.0 });
+        if let Some(reason) = &self.app.read_only {
+            warn!(%reason, "Read-only, not propagating roots or tracking transactions");
+        }
+
+        // Check Status
+        let app = self.app.clone();
+        let wake_up_notify = base_wake_up_notify.clone();
+        let check_sync_state =
+            move || tasks::check_sync::check_sync(app.clone(), wake_up_notify.clone());
+
+        let check_sync_state_handle = crate::utils::spawn_monitored_with_backoff(
+            "check_sync",
+            check_sync_state,
+            shutdown_sender.clone(),
+            self.app.config.borrow().backoff.for_task("check_sync"),
+            self.shutdown.clone(),
+        );
+        handles.push(check_sync_state_handle);
+
+        // Monitor relayer balance, as long as we know the relayer
+        if self.app.config.borrow().relayer.is_some() {
+            let app = self.app.clone();
+            let monitor_balance = move || tasks::monitor_balance::monitor_balance(app.clone());
+            let monitor_balance_handle = crate::utils::spawn_monitored_with_backoff(
+                "monitor_balance",
+                monitor_balance,
+                shutdown_sender.clone(),
+                self.app.config.borrow().backoff.for_task("monitor_balance"),
+                self.shutdown.clone(),
+            );
+            handles.push(monitor_balance_handle);
+        }
+
+        // Sending and tracking transactions is left to the leader. A
+        // read-only replica doesn't compete, so it can't block one that
+        // could propagate.
+        if !read_only {
+            let database = self.app.config.borrow().database.database.clone();
+            if let Some(handle) = self
+                .app
+                .leadership
+                .campaign(database, shutdown_sender.subscribe())
+            {
+                handles.push(handle);
+            }
+
+            handles.push(self.lead(shutdown_sender.subscribe(), base_wake_up_notify));
+        }
+
+        // Create the instance
+        *instance = Some(RunningInstance {
+            handles,
+            shutdown_sender,
+        });
+    }
+
+    /// Runs the leader-only tasks for as long as this replica is the leader,
+    /// stopping them as soon as leadership is lost.
+    fn lead(
+        &self,
+        mut shutdown_receiver: broadcast::Receiver<()>,
+        wake_up_notify: Arc<Notify>,
+    ) -> JoinHandle<()> {
+        let app = self.app.clone();
+        let shutdown = self.shutdown.clone();
+
+        tokio::spawn(async move {
+            let mut is_leader = app.leadership.subscribe();
+
+            loop {
+                if !*is_leader.borrow_and_update() {
+                    tokio::select! {
+                        changed = is_leader.changed() => {
+                            if changed.is_err() {
+                                return;
+                            }
+                            continue;
+                        }
+                        _ = shutdown_receiver.recv() => return,
+                    }
+                }
+
+                info!("Leading, starting propagation tasks");
+                let term = Self::spawn_leader_tasks(&app, &shutdown, &wake_up_notify);
+
+                let shutting_down = loop {
+                    tokio::select! {
+                        changed = is_leader.changed() => {
+                            if changed.is_err() || !*is_leader.borrow_and_update() {
+                                break false;
+                            }
+                        }
+                        _ = shutdown_receiver.recv() => break true,
+                    }
+                };
+
+                if !shutting_down {
+                    warn!("Lost leadership, stopping propagation tasks");
+                }
+                if let Err(err) = term.shutdown().await {
+                    error!(?err, "Failed to stop propagation tasks");
+                }
+                if shutting_down {
+                    return;
+                }
+            }
+        })
+    }
+
+    fn spawn_leader_tasks(
+        app: &Arc<App>,
+        shutdown: &Arc<Shutdown>,
+        wake_up_notify: &Arc<Notify>,
+    ) -> RunningInstance {
+        let (shutdown_sender, _) = broadcast::channel(1);
+
         let (monitored_txs_sender, monitored_txs_receiver) =
-            mpsc::channel(self.app.config.app.monitored_txs_capacity);
+            mpsc::channel(app.config.borrow().app.monitored_txs_capacity);
 
         let monitored_txs_sender = Arc::new(monitored_txs_sender);
         let monitored_txs_receiver = Arc::new(Mutex::new(monitored_txs_receiver));
 
-
         let mut handles = Vec::new();
 
-        let base_wake_up_notify = Arc::new(Notify::new());
-        
+        let outbox_notify = Arc::new(Notify::new());
+
         // Propagate Root
-        let app = self.app.clone();
-        let wake_up_notify = base_wake_up_notify.clone();
+        let task_app = app.clone();
+        let wake_up_notify = wake_up_notify.clone();
+        let task_outbox_notify = outbox_notify.clone();
         let propagate_root = move || {
             tasks::propagate_root::propagate_root(
-                app.clone(),
-                monitored_txs_sender.clone(),
-                wake_up_notify.clone()
+                task_app.clone(),
+                wake_up_notify.clone(),
+                task_outbox_notify.clone(),
             )
         };
         let propagate_root_handle = crate::utils::spawn_monitored_with_backoff(
+            "propagate_root",
             propagate_root,
             shutdown_sender.clone(),
-            PROPAGATE_ROOT_BACKOFF,
-            self.shutdown.clone(),
+            app.config.borrow().backoff.for_task("propagate_root"),
+            shutdown.clone(),
         );
         handles.push(propagate_root_handle);
-      
-        // Check Status
-        let app = self.app.clone();
-        let wake_up_notify = base_wake_up_notify.clone();
-        let check_sync_state = move || {
-            tasks::check_sync::check_sync(
-                app.clone(),
-                wake_up_notify.clone()
+
+        // Send propagations
+        let task_app = app.clone();
+        let send_propagations = move || {
+            tasks::send_propagations::send_propagations(
+                task_app.clone(),
+                monitored_txs_sender.clone(),
+                outbox_notify.clone(),
             )
         };
-
-        let check_sync_state_handle = crate::utils::spawn_monitored_with_backoff(
-            check_sync_state,
+        let send_propagations_handle = crate::utils::spawn_monitored_with_backoff(
+            "send_propagations",
+            send_propagations,
             shutdown_sender.clone(),
-            CHECK_SYNC_STATE_BACKOFF,
-            self.shutdown.clone(),
+            app.config.borrow().backoff.for_task("send_propagations"),
+            shutdown.clone(),
         );
-        handles.push(check_sync_state_handle);
+        handles.push(send_propagations_handle);
 
-        
         // Finalize transactions
-        let app = self.app.clone();
+        let task_app = app.clone();
         let finalize_txs =
-            move || tasks::finalize_txs::finalize_txs(app.clone());
+            move || tasks::finalize_txs::finalize_txs(task_app.clone());
         let finalize_txs_handle = crate::utils::spawn_monitored_with_backoff(
+            "finalize_txs",
             finalize_txs,
             shutdown_sender.clone(),
-            FINALIZE_TXNS_BACKOFF,
-            self.shutdown.clone(),
+            app.config.borrow().backoff.for_task("finalize_txs"),
+            shutdown.clone(),
         );
         handles.push(finalize_txs_handle);
 
         // Monitor transactions
-        let app = self.app.clone();
+        let task_app = app.clone();
         let monitor_txs =
-            move || tasks::monitor_txs::monitor_txs(app.clone(), monitored_txs_receiver.clone());
+            move || tasks::monitor_txs::monitor_txs(task_app.clone(), monitored_txs_receiver.clone());
         let monitor_txs_handle = crate::utils::spawn_monitored_with_backoff(
+            "monitor_txs",
             monitor_txs,
             shutdown_sender.clone(),
-            MONITOR_TXNS_BACKOFF,
-            self.shutdown.clone(),
+            app.config.borrow().backoff.for_task("monitor_txs"),
+            shutdown.clone(),
         );
         handles.push(monitor_txs_handle);
 
-        // Create the instance
-        *instance = Some(RunningInstance {
+        // Prune old rows
+        let task_app = app.clone();
+        let retention = move || tasks::retention::retention(task_app.clone());
+        let retention_handle = crate::utils::spawn_monitored_with_backoff(
+            "retention",
+            retention,
+            shutdown_sender.clone(),
+            app.config.borrow().backoff.for_task("retention"),
+            shutdown.clone(),
+        );
+        handles.push(retention_handle);
+
+        RunningInstance {
             handles,
             shutdown_sender,
-        });
+        }
     }
 
-    async fn check_synced_state(app: &Arc<App>) -> anyhow::Result<bool> {
+    async fn check_synced_state(app: &Arc<App>) -> Result<bool, BridgeError> {
         let state = app.bridge_processor.check_sync_state().await?;
         let gauge_value = if state { 1.0 } else { 0.0 };
         SYNCED_STATE.set(gauge_value);
         Ok(state)
     }
 
-    async fn check_db_state(database: &Database, status_check: BridgeStatus) -> anyhow::Result<bool> {
-        let status = database.get_db_status().await?.unwrap_or_else(|| "unsynced".to_string());
-        let bridge_status = BridgeStatus::from_str(&status).unwrap_or(BridgeStatus::Unsynced);
+    async fn check_db_state(
+        database: &dyn BridgeStore,
+        status_check: BridgeStatus,
+    ) -> Result<bool, BridgeError> {
+        let bridge_status = database
+            .get_db_status()
+            .await?
+            .unwrap_or(BridgeStatus::Unsynced);
         Ok(bridge_status == status_check)
     }
 
-    async fn check_last_transaction_status(database: &Database, status_check: TxStatus) -> anyhow::Result<bool> {
+    async fn check_last_transaction_status(
+        database: &dyn BridgeStore,
+        status_check: TxStatus,
+    ) -> Result<bool, BridgeError> {
         let status = database.get_last_transaction_status().await?;
         // let tx_status = TxStatus::from_str(&status).unwrap_or(TxStatus::Pending);
         Ok(status == Some(status_check))
     }
 
+    /// Whether a propagation is in flight: queued or claimed in the outbox,
+    /// or sent and its transaction still pending.
+    async fn check_propagation_pending(database: &dyn BridgeStore) -> Result<bool, BridgeError> {
+        if database.has_open_propagation().await? {
+            return Ok(true);
+        }
+        Self::check_last_transaction_status(database, TxStatus::Pending).await
+    }
+
     /// # Errors
     ///
     /// Will return an Error if the committer thread cannot be shut down
@@ -197,3 +372,69 @@ impl TaskMonitor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U256;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::database::memory::MemoryStore;
+    use crate::database::types::OutboxStatus;
+    use crate::processor::mock::{MockProcessor, MockState};
+
+    /// Starts a replica on `store` that sees a new root on L1.
+    async fn replica(
+        store: &Arc<MemoryStore>,
+        config: Config,
+    ) -> (TaskMonitor, Arc<MockProcessor>) {
+        let processor = Arc::new(MockProcessor::new(MockState {
+            l1_root: U256::from(1),
+            ..MockState::default()
+        }));
+        let app = App::for_tests(config, store.clone(), processor.clone());
+        let monitor = TaskMonitor::new(app, Arc::new(Shutdown::new()));
+        monitor.start().await;
+
+        (monitor, processor)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_the_leader_propagates() {
+        let store = Arc::new(MemoryStore::initialized());
+
+        // Without election a replica leads, while one that can't reach the
+        // lock stays a follower
+        let (leader, leader_processor) = replica(&store, Config::for_tests()).await;
+        let mut config = Config::for_tests();
+        config.leader_election.enabled = true;
+        config.database.database = "postgres://postgres@127.0.0.1:1/postgres".parse().unwrap();
+        let (follower, follower_processor) = replica(&store, config).await;
+
+        time::sleep(Duration::from_secs(10 * 60)).await;
+
+        assert!(leader.app.leadership.is_leader());
+        assert!(!follower.app.leadership.is_leader());
+        assert!(follower_processor.state().sent.is_empty());
+
+        // The roots never sync, so the leader keeps propagating, and every
+        // propagation in the outbox is one it sent
+        let sent = leader_processor.state().sent.clone();
+        assert!(!sent.is_empty());
+        let outbox: Vec<_> = store
+            .outbox()
+            .into_iter()
+            .filter(|(status, _)| *status == OutboxStatus::Sent)
+            .map(|(_, tx_id)| tx_id)
+            .collect();
+        assert_eq!(
+            outbox,
+            sent.iter()
+                .map(|key| Some(MockProcessor::transaction_id(key)))
+                .collect::<Vec<_>>()
+        );
+
+        leader.shutdown().await.unwrap();
+        follower.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use ethers::types::U256;
+use tokio::sync::Mutex;
+
+use crate::config::PropagationPolicy;
+use crate::task_monitor::GWEI;
+
+/// Everything a [`PropagationPolicy`] may look at when deciding.
+#[derive(Debug, Clone)]
+pub struct PolicyContext {
+    pub now:                    DateTime<Utc>,
+    /// When the last propagation transaction was submitted.
+    pub last_propagation:       Option<DateTime<Utc>>,
+    /// Number of distinct L1 roots observed since the last propagation.
+    pub new_roots:              usize,
+    /// When the oldest of those roots was first observed.
+    pub oldest_pending_root_at: Option<DateTime<Utc>>,
+    /// Current L1 gas price in wei. Only fetched, and otherwise zero, if
+    /// the policy [needs it](PropagationPolicy::needs_gas_price).
+    pub gas_price:              U256,
+    /// How long a superseded root stays valid on L1. Only fetched, and
+    /// otherwise zero, if the policy
+    /// [needs it](PropagationPolicy::needs_root_history_expiry).
+    pub root_history_expiry:    Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyDecision {
+    pub propagate: bool,
+    pub reason:    String,
+}
+
+impl PolicyDecision {
+    fn send(reason: impl Into<String>) -> Self {
+        Self {
+            propagate: true,
+            reason:    reason.into(),
+        }
+    }
+
+    fn skip(reason: impl Into<String>) -> Self {
+        Self {
+            propagate: false,
+            reason:    reason.into(),
+        }
+    }
+
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        if self.propagate {
+            "send"
+        } else {
+            "skip"
+        }
+    }
+}
+
+impl PropagationPolicy {
+    /// Whether evaluating the policy looks at the L1 gas price.
+    #[must_use]
+    pub fn needs_gas_price(&self) -> bool {
+        match self {
+            Self::GasCeiling { .. } => true,
+            Self::All { policies } | Self::Any { policies } => {
+                policies.iter().any(Self::needs_gas_price)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether evaluating the policy looks at the L1 root history expiry.
+    #[must_use]
+    pub fn needs_root_history_expiry(&self) -> bool {
+        match self {
+            Self::BeforeExpiry { .. } => true,
+            Self::All { policies } | Self::Any { policies } => {
+                policies.iter().any(Self::needs_root_history_expiry)
+            }
+            _ => false,
+        }
+    }
+
+    #[must_use]
+    pub fn evaluate(&self, ctx: &PolicyContext) -> PolicyDecision {
+        match self {
+            Self::EveryChange => PolicyDecision::send("roots differ"),
+            Self::Interval { min_interval } => {
+                let Some(last) = ctx.last_propagation else {
+                    return PolicyDecision::send("no previous propagation");
+                };
+
+                let elapsed = (ctx.now - last).to_std().unwrap_or_default();
+                let reason = format!(
+                    "{} since last propagation (min {})",
+                    humantime::format_duration(round_secs(elapsed)),
+                    humantime::format_duration(*min_interval)
+                );
+
+                if elapsed >= *min_interval {
+                    PolicyDecision::send(reason)
+                } else {
+                    PolicyDecision::skip(reason)
+                }
+            }
+            Self::RootCount { min_new_roots } => {
+                let reason = format!("{} new roots (min {min_new_roots})", ctx.new_roots);
+
+                if ctx.new_roots >= *min_new_roots {
+                    PolicyDecision::send(reason)
+                } else {
+                    PolicyDecision::skip(reason)
+                }
+            }
+            Self::GasCeiling { max_gas_price_gwei } => {
+                let ceiling = U256::from(*max_gas_price_gwei) * GWEI;
+                let reason = format!(
+                    "gas price {} gwei (max {max_gas_price_gwei})",
+                    ctx.gas_price / GWEI
+                );
+
+                if ctx.gas_price <= ceiling {
+                    PolicyDecision::send(reason)
+                } else {
+                    PolicyDecision::skip(reason)
+                }
+            }
+            Self::BeforeExpiry { margin } => {
+                let Some(oldest) = ctx.oldest_pending_root_at else {
+                    return PolicyDecision::skip("no unpropagated roots");
+                };
+
+                let window = ctx.root_history_expiry.saturating_sub(*margin);
+                let deadline = oldest + chrono::Duration::from_std(window).unwrap_or_default();
+                let reason = format!("expiry deadline {deadline}");
+
+                if ctx.now >= deadline {
+                    PolicyDecision::send(reason)
+                } else {
+                    PolicyDecision::skip(reason)
+                }
+            }
+            Self::All { policies } => {
+                let decisions: Vec<_> = policies.iter().map(|p| p.evaluate(ctx)).collect();
+                let propagate = decisions.iter().all(|d| d.propagate);
+
+                combine("all", propagate, &decisions)
+            }
+            Self::Any { policies } => {
+                let decisions: Vec<_> = policies.iter().map(|p| p.evaluate(ctx)).collect();
+                let propagate = decisions.iter().any(|d| d.propagate);
+
+                combine("any", propagate, &decisions)
+            }
+        }
+    }
+}
+
+fn combine(name: &str, propagate: bool, decisions: &[PolicyDecision]) -> PolicyDecision {
+    let inner = decisions
+        .iter()
+        .map(|d| format!("{}: {}", d.label(), d.reason))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    PolicyDecision {
+        propagate,
+        reason: format!("{name}({inner})"),
+    }
+}
+
+fn round_secs(duration: Duration) -> Duration {
+    Duration::from_secs(duration.as_secs())
+}
+
+/// Tracks the L1 roots that were observed but not yet propagated.
+///
+/// Roots are sampled by `check_sync`, so roots that come and go between two
+/// checks are not counted.
+#[derive(Debug, Default)]
+pub struct PropagationTracker {
+    pending_roots: Mutex<Vec<(U256, DateTime<Utc>)>>,
+}
+
+impl PropagationTracker {
+    /// Records `root` as observed at `now` unless it was already seen.
+    pub async fn observe_root(&self, root: U256, now: DateTime<Utc>) {
+        let mut pending_roots = self.pending_roots.lock().await;
+
+        if !pending_roots.iter().any(|(seen, _)| *seen == root) {
+            pending_roots.push((root, now));
+        }
+    }
+
+    /// Forgets all observed roots, e.g. once they've been propagated.
+    pub async fn clear(&self) {
+        self.pending_roots.lock().await.clear();
+    }
+
+    /// Returns the number of pending roots and when the oldest one was seen.
+    pub async fn pending(&self) -> (usize, Option<DateTime<Utc>>) {
+        let pending_roots = self.pending_roots.lock().await;
+
+        (
+            pending_roots.len(),
+            pending_roots.first().map(|(_, seen_at)| *seen_at),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> PolicyContext {
+        PolicyContext {
+            now:                    Utc::now(),
+            last_propagation:       None,
+            new_roots:              1,
+            oldest_pending_root_at: None,
+            gas_price:              U256::from(20) * GWEI,
+            root_history_expiry:    Duration::from_secs(7 * 24 * 3600),
+        }
+    }
+
+    #[test]
+    fn interval() {
+        let policy = PropagationPolicy::Interval {
+            min_interval: Duration::from_secs(3600),
+        };
+        let mut ctx = context();
+        assert!(policy.evaluate(&ctx).propagate);
+
+        ctx.last_propagation = Some(ctx.now - chrono::Duration::minutes(30));
+        assert!(!policy.evaluate(&ctx).propagate);
+
+        ctx.last_propagation = Some(ctx.now - chrono::Duration::minutes(90));
+        assert!(policy.evaluate(&ctx).propagate);
+    }
+
+    #[test]
+    fn gas_ceiling() {
+        let mut ctx = context();
+
+        let policy = PropagationPolicy::GasCeiling {
+            max_gas_price_gwei: 20,
+        };
+        assert!(policy.evaluate(&ctx).propagate);
+
+        ctx.gas_price = U256::from(21) * GWEI;
+        assert!(!policy.evaluate(&ctx).propagate);
+    }
+
+    #[test]
+    fn before_expiry() {
+        let policy = PropagationPolicy::BeforeExpiry {
+            margin: Duration::from_secs(3600),
+        };
+        let mut ctx = context();
+        assert!(!policy.evaluate(&ctx).propagate);
+
+        ctx.oldest_pending_root_at = Some(ctx.now - chrono::Duration::days(6));
+        assert!(!policy.evaluate(&ctx).propagate);
+
+        ctx.oldest_pending_root_at = Some(ctx.now - chrono::Duration::hours(7 * 24 - 1));
+        assert!(policy.evaluate(&ctx).propagate);
+    }
+
+    #[test]
+    fn combinators() {
+        let policy = PropagationPolicy::Any {
+            policies: vec![
+                PropagationPolicy::All {
+                    policies: vec![
+                        PropagationPolicy::RootCount { min_new_roots: 3 },
+                        PropagationPolicy::GasCeiling {
+                            max_gas_price_gwei: 30,
+                        },
+                    ],
+                },
+                PropagationPolicy::BeforeExpiry {
+                    margin: Duration::from_secs(3600),
+                },
+            ],
+        };
+        let mut ctx = context();
+
+        let decision = policy.evaluate(&ctx);
+        assert!(!decision.propagate);
+        assert!(decision.reason.starts_with("any(skip: all("));
+
+        ctx.new_roots = 3;
+        assert!(policy.evaluate(&ctx).propagate);
+
+        ctx.gas_price = U256::from(50) * GWEI;
+        assert!(!policy.evaluate(&ctx).propagate);
+
+        ctx.oldest_pending_root_at = Some(ctx.now - chrono::Duration::days(7));
+        assert!(policy.evaluate(&ctx).propagate);
+
+        assert!(policy.needs_gas_price());
+        assert!(policy.needs_root_history_expiry());
+        assert!(!PropagationPolicy::RootCount { min_new_roots: 3 }.needs_gas_price());
+        assert!(!PropagationPolicy::EveryChange.needs_root_history_expiry());
+    }
+}
@@ -0,0 +1,260 @@
+//! Starting read-only: the whole app is built against a stub JSON-RPC chain,
+//! so that each reason is arrived at the way it is in production.
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::Uri;
+use axum::routing::post;
+use axum::{Json, Router};
+use ethers::abi::{encode, Token};
+use ethers::types::{Address, Block, FeeHistory, TxHash, H256, U256};
+use ethers::utils::id;
+use serde_json::{json, Value};
+use tokio::time;
+
+use super::tasks::testing;
+use super::TaskMonitor;
+use crate::app::{App, ReadOnlyReason};
+use crate::config::Config;
+use crate::processor::status::BridgeStatus;
+use crate::utils::shutdown::Shutdown;
+
+const BRIDGE: Address = Address::repeat_byte(0x01);
+const SCROLL_WORLD_ID: Address = Address::repeat_byte(0x02);
+const WORLD_ID: Address = Address::repeat_byte(0x03);
+
+/// Serves both chains. `WorldID` is one root ahead of `ScrollWorldID`, so a
+/// service that could send would propagate.
+struct StubChain {
+    /// Addresses with code deployed
+    deployed: HashSet<Address>,
+    /// Every JSON-RPC method and relayer path requested
+    requests: Mutex<Vec<String>>,
+}
+
+impl StubChain {
+    fn serve(deployed: &[Address]) -> (Arc<Self>, String) {
+        let chain = Arc::new(Self {
+            deployed: deployed.iter().copied().collect(),
+            requests: Mutex::new(Vec::new()),
+        });
+        let router = Router::new()
+            .route("/", post(json_rpc))
+            .fallback(relayer)
+            .with_state(chain.clone());
+
+        (chain, format!("http://{}", testing::serve(router)))
+    }
+
+    fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn call(to: Address, data: &str) -> Option<Value> {
+        let data = hex::decode(data.trim_start_matches("0x")).ok()?;
+        let calls = [
+            (BRIDGE, "owner()", Token::Address(Address::zero())),
+            (
+                BRIDGE,
+                "scrollWorldIDAddress()",
+                Token::Address(SCROLL_WORLD_ID),
+            ),
+            (BRIDGE, "worldIDAddress()", Token::Address(WORLD_ID)),
+            (WORLD_ID, "latestRoot()", Token::Uint(U256::from(2))),
+            (
+                WORLD_ID,
+                "getRootHistoryExpiry()",
+                Token::Uint(U256::from(3600)),
+            ),
+            (SCROLL_WORLD_ID, "latestRoot()", Token::Uint(U256::from(1))),
+        ];
+
+        calls
+            .into_iter()
+            .find(|(address, signature, _)| *address == to && data.starts_with(&id(signature)))
+            .map(|(_, _, result)| json!(format!("0x{}", hex::encode(encode(&[result])))))
+    }
+}
+
+async fn json_rpc(State(chain): State<Arc<StubChain>>, Json(request): Json<Value>) -> Json<Value> {
+    let method = request["method"].as_str().unwrap_or_default().to_string();
+    let params = &request["params"];
+    chain.requests.lock().unwrap().push(method.clone());
+
+    let result = match method.as_str() {
+        "web3_clientVersion" => Some(json!("stub")),
+        "eth_chainId" => Some(json!("0x7a69")),
+        "eth_getBlockByNumber" => Some(
+            serde_json::to_value(Block::<TxHash> {
+                hash: Some(H256::repeat_byte(0x11)),
+                number: Some(1.into()),
+                timestamp: chrono::Utc::now().timestamp().unsigned_abs().into(),
+                ..Block::default()
+            })
+            .unwrap(),
+        ),
+        "eth_feeHistory" => Some(
+            serde_json::to_value(FeeHistory {
+                base_fee_per_gas: vec![1.into(), 1.into()],
+                gas_used_ratio:   vec![0.5],
+                oldest_block:     1.into(),
+                reward:           vec![],
+            })
+            .unwrap(),
+        ),
+        "eth_gasPrice" => Some(json!("0x1")),
+        "eth_getBalance" => Some(json!("0xde0b6b3a7640000")),
+        "eth_getCode" => {
+            let address: Address = serde_json::from_value(params[0].clone()).unwrap();
+            let code = if chain.deployed.contains(&address) {
+                "0x6080"
+            } else {
+                "0x"
+            };
+            Some(json!(code))
+        }
+        "eth_call" => {
+            let call = &params[0];
+            let to: Address = serde_json::from_value(call["to"].clone()).unwrap();
+            let data = call["data"].as_str().or_else(|| call["input"].as_str());
+            data.and_then(|data| StubChain::call(to, data))
+        }
+        _ => None,
+    };
+
+    Json(result.map_or_else(
+        || {
+            json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": format!("{method} is not stubbed") },
+            })
+        },
+        |result| json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+    ))
+}
+
+async fn relayer(State(chain): State<Arc<StubChain>>, uri: Uri) -> axum::http::StatusCode {
+    chain.requests.lock().unwrap().push(uri.path().to_string());
+    axum::http::StatusCode::NOT_FOUND
+}
+
+fn config(url: &str, api_token: &str) -> Config {
+    toml::from_str(&indoc::formatdoc! {r#"
+            [app]
+
+            [network]
+            scroll_bridge_address = "{bridge:?}"
+
+            [providers]
+            l1_network_provider = "{url}"
+            l2_network_provider = "{url}"
+
+            [relayer]
+            kind = "tx_sitter"
+            tx_sitter_url = "{url}/api"
+            tx_sitter_address = "0x0000000000000000000000000000000000000042"
+            tx_sitter_api_token = "{api_token}"
+
+            [database]
+            database = "sqlite::memory:"
+
+            [server]
+            address = "127.0.0.1:0"
+
+            [leader_election]
+            enabled = false
+        "#,
+        bridge = BRIDGE,
+    })
+    .unwrap()
+}
+
+/// Starts the service against a chain with code deployed at `deployed`,
+/// lets it check the roots, and returns why it's read-only.
+async fn start_read_only(deployed: &[Address], api_token: &str) -> Option<ReadOnlyReason> {
+    let (chain, url) = StubChain::serve(deployed);
+    let app = App::new(config(&url, api_token)).await.unwrap();
+    app.initialize_server().await.unwrap();
+
+    let monitor = TaskMonitor::new(app.clone(), Arc::new(Shutdown::new()));
+    monitor.start().await;
+
+    // A read-only service still records the sync state
+    time::timeout(Duration::from_secs(10), async {
+        while app.database.get_db_status().await.unwrap() != Some(BridgeStatus::Unsynced) {
+            time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("the roots were never checked");
+    time::sleep(Duration::from_millis(500)).await;
+
+    // Only check_sync and monitor_balance, no leader tasks
+    assert_eq!(
+        monitor
+            .instance
+            .read()
+            .await
+            .as_ref()
+            .unwrap()
+            .handles
+            .len(),
+        2
+    );
+    assert!(app
+        .database
+        .claim_propagation_intent()
+        .await
+        .unwrap()
+        .is_none());
+    let requests = chain.requests();
+    for sent in ["eth_estimateGas", "eth_sendRawTransaction", "/api/tx"] {
+        assert!(!requests.iter().any(|r| r == sent), "{sent} was requested");
+    }
+
+    let status = app.get_service_status().await.unwrap();
+    monitor.shutdown().await.unwrap();
+
+    status.read_only
+}
+
+#[tokio::test]
+async fn relayer_init_failure() {
+    let reason = Box::pin(start_read_only(
+        &[BRIDGE, SCROLL_WORLD_ID, WORLD_ID],
+        "bad\\ntoken",
+    ))
+    .await;
+
+    assert!(
+        matches!(reason, Some(ReadOnlyReason::RelayerUnavailable(_))),
+        "{reason:?}"
+    );
+}
+
+#[tokio::test]
+async fn missing_scroll_world_id() {
+    let reason = Box::pin(start_read_only(&[BRIDGE, WORLD_ID], "token")).await;
+
+    assert_eq!(
+        reason,
+        Some(ReadOnlyReason::MissingContract(format!(
+            "ScrollWorldID at {SCROLL_WORLD_ID:?}"
+        )))
+    );
+}
+
+#[tokio::test]
+async fn missing_world_id() {
+    let reason = Box::pin(start_read_only(&[BRIDGE, SCROLL_WORLD_ID], "token")).await;
+
+    assert_eq!(
+        reason,
+        Some(ReadOnlyReason::MissingContract(format!(
+            "WorldID at {WORLD_ID:?}"
+        )))
+    );
+}
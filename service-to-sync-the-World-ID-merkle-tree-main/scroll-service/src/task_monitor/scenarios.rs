@@ -0,0 +1,186 @@
+//! End-to-end scenarios: the whole task monitor runs against a fake chain
+//! and an in-memory store, with paused time.
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::types::U256;
+use fake_chain::{FakeChain, FakeError, Outcome};
+use tokio::time;
+
+use super::TaskMonitor;
+use crate::app::App;
+use crate::config::Config;
+use crate::database::memory::MemoryStore;
+use crate::database::types::{OutboxStatus, TxStatus};
+use crate::database::BridgeStore;
+use crate::processor::status::BridgeStatus;
+use crate::utils::shutdown::Shutdown;
+
+struct Scenario {
+    store:   Arc<MemoryStore>,
+    chain:   Arc<FakeChain>,
+    monitor: TaskMonitor,
+}
+
+impl Scenario {
+    /// Starts the service with both chains at root 1.
+    async fn start() -> Self {
+        let store = Arc::new(MemoryStore::initialized());
+        let chain = Arc::new(FakeChain::new(U256::from(1)));
+        let app = App::for_tests(Config::for_tests(), store.clone(), chain.clone());
+        let monitor = TaskMonitor::new(app, Arc::new(Shutdown::new()));
+        monitor.start().await;
+
+        Self {
+            store,
+            chain,
+            monitor,
+        }
+    }
+
+    async fn status(&self) -> Option<BridgeStatus> {
+        self.store.get_db_status().await.unwrap()
+    }
+
+    fn statuses(&self) -> Vec<BridgeStatus> {
+        self.store
+            .history()
+            .into_iter()
+            .map(|change| change.to)
+            .collect()
+    }
+
+    fn propagated_roots(&self) -> Vec<U256> {
+        self.chain
+            .propagations()
+            .into_iter()
+            .map(|propagation| propagation.root)
+            .collect()
+    }
+
+    async fn stop(self) {
+        time::timeout(Duration::from_secs(60), self.monitor.shutdown())
+            .await
+            .expect("tasks didn't stop")
+            .unwrap();
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn propagates_new_roots() {
+    let scenario = Scenario::start().await;
+    time::sleep(Duration::from_secs(10)).await;
+    assert_eq!(scenario.status().await, Some(BridgeStatus::Synced));
+    assert!(scenario.chain.propagations().is_empty());
+
+    scenario.chain.set_l1_root(U256::from(2));
+    time::sleep(Duration::from_secs(10 * 60)).await;
+
+    assert_eq!(scenario.propagated_roots(), vec![U256::from(2)]);
+    assert!(scenario.chain.is_synced());
+    assert_eq!(scenario.statuses(), vec![
+        BridgeStatus::Unsynced,
+        BridgeStatus::Synced,
+        BridgeStatus::Unsynced,
+        BridgeStatus::Pending,
+        BridgeStatus::Synced
+    ]);
+    assert_eq!(
+        scenario.store.get_last_transaction_status().await.unwrap(),
+        Some(TxStatus::Mined)
+    );
+    assert_eq!(scenario.store.decisions().len(), 1);
+    scenario.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn waits_for_lagging_delivery() {
+    let scenario = Scenario::start().await;
+    scenario.chain.set_delivery_delay(Duration::from_secs(100));
+    scenario.chain.set_l1_root(U256::from(2));
+
+    time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(scenario.chain.mined().len(), 1);
+    assert!(!scenario.chain.is_synced());
+    assert_eq!(scenario.status().await, Some(BridgeStatus::Pending));
+
+    time::sleep(Duration::from_secs(10 * 60)).await;
+    assert_eq!(scenario.propagated_roots(), vec![U256::from(2)]);
+    assert_eq!(scenario.status().await, Some(BridgeStatus::Synced));
+    scenario.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn follows_roots_changing_mid_flight() {
+    let scenario = Scenario::start().await;
+    scenario.chain.set_l1_root(U256::from(2));
+    scenario
+        .chain
+        .schedule_l1_root(Duration::from_secs(150), U256::from(3));
+
+    time::sleep(Duration::from_secs(20 * 60)).await;
+
+    assert_eq!(scenario.propagated_roots(), vec![
+        U256::from(2),
+        U256::from(3)
+    ]);
+    assert_eq!(scenario.chain.l2_root(), U256::from(3));
+    assert_eq!(scenario.status().await, Some(BridgeStatus::Synced));
+    scenario.stop().await;
+}
+
+async fn repropagates_undelivered_roots(outcome: Outcome) {
+    let scenario = Scenario::start().await;
+    scenario.chain.next_outcome(outcome);
+    scenario.chain.set_l1_root(U256::from(2));
+
+    time::sleep(Duration::from_secs(20 * 60)).await;
+
+    let outcomes: Vec<_> = scenario
+        .chain
+        .propagations()
+        .into_iter()
+        .map(|propagation| propagation.outcome)
+        .collect();
+    assert_eq!(outcomes, vec![outcome, Outcome::Delivered]);
+    assert_eq!(scenario.propagated_roots(), vec![
+        U256::from(2),
+        U256::from(2)
+    ]);
+    assert_eq!(scenario.status().await, Some(BridgeStatus::Synced));
+    scenario.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn repropagates_failed_messages() {
+    repropagates_undelivered_roots(Outcome::MessageFailed).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn repropagates_reverted_transactions() {
+    repropagates_undelivered_roots(Outcome::Reverted).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_through_relayer_outages() {
+    let scenario = Scenario::start().await;
+    scenario
+        .chain
+        .fail_next_send(FakeError::Unavailable("connection refused".into()));
+    scenario
+        .chain
+        .fail_next_send(FakeError::Unavailable("connection refused".into()));
+    scenario.chain.set_l1_root(U256::from(2));
+
+    time::sleep(Duration::from_secs(10 * 60)).await;
+
+    let propagations = scenario.chain.propagations();
+    assert_eq!(propagations.len(), 1);
+    assert!(propagations[0].idempotency_key.starts_with("propagation-"));
+    assert_eq!(scenario.store.outbox(), vec![(
+        OutboxStatus::Sent,
+        Some(propagations[0].id.clone())
+    )]);
+    assert_eq!(scenario.status().await, Some(BridgeStatus::Synced));
+    scenario.stop().await;
+}
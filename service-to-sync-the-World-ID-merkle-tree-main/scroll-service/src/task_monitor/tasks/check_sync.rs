@@ -1,37 +1,236 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::database::types::TxStatus;
+use chrono::Utc;
+use ethers::types::U256;
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter_vec, IntCounterVec};
+
 use crate::processor::status::BridgeStatus;
-use crate::task_monitor::{App, TaskMonitor};
+use crate::task_monitor::policy::{PolicyContext, PolicyDecision};
+use crate::task_monitor::{reset_period, App, TaskMonitor};
+use crate::error::BridgeError;
 use tokio::sync::Notify;
 use tokio::time;
 use tracing::info;
 
-pub async fn check_sync(app: Arc<App>, wake_up_notify: Arc<Notify>) -> anyhow::Result<()> {
-    let mut timer = time::interval(Duration::from_secs(120));
+static PROPAGATION_DECISIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "propagation_decisions",
+        "Propagation policy decisions by outcome.",
+        &["decision"]
+    )
+    .unwrap()
+});
+
+pub async fn check_sync(app: Arc<App>, wake_up_notify: Arc<Notify>) -> Result<(), BridgeError> {
+    let mut timer = time::interval(app.config.borrow().tasks.check_sync_interval);
+    let mut last_decision = None;
     loop {
         _ = timer.tick().await;
+        reset_period(&mut timer, app.config.borrow().tasks.check_sync_interval);
         info!("Sync processor woken due to timeout.");
 
         let is_synced = TaskMonitor::check_synced_state(&app).await?;
 
-        let tx_pending = TaskMonitor::check_last_transaction_status(&app.database, TxStatus::Pending).await?;
+        // Followers only report the sync state, the leader records it. A
+        // read-only replica never leads, so it records the state itself.
+        if !app.leadership.is_leader() && app.read_only.is_none() {
+            continue;
+        }
 
-        let db_state_pending = TaskMonitor::check_db_state(&app.database, BridgeStatus::Pending).await?;
+        let tx_pending = TaskMonitor::check_propagation_pending(app.database.as_ref()).await?;
+
+        let db_state_pending =
+            TaskMonitor::check_db_state(app.database.as_ref(), BridgeStatus::Pending).await?;
 
         info!(?is_synced, ?tx_pending, ?db_state_pending);
 
-        // if still synced or pending continue so as not to call the propagate
-        if is_synced && db_state_pending {
-          app.database.mark_status_as_synced().await?;
+        if is_synced {
+          app.propagation_tracker.clear().await;
+          app.database.mark_status_as_synced("roots match").await?;
           continue;
-        } else if is_synced || (tx_pending && db_state_pending) {
+        }
+
+        // if pending continue so as not to call the propagate
+        if tx_pending && db_state_pending {
           continue;
         }
 
-        app.database.mark_status_as_unsynced().await?;
+        app.database.mark_status_as_unsynced("roots differ").await?;
+
+        if should_propagate(&app, &mut last_decision).await?.propagate {
+            wake_up_notify.notify_one();
+        }
+    }
+}
+
+/// Runs the configured propagation policy. The decision is recorded when
+/// it triggers a propagation or differs from the last one for the same root,
+/// `last_decision` being the root and outcome last recorded.
+async fn should_propagate(
+    app: &App,
+    last_decision: &mut Option<(U256, bool)>,
+) -> Result<PolicyDecision, BridgeError> {
+    let now = Utc::now();
+    let root = app.bridge_processor.latest_root().await?;
+    app.propagation_tracker.observe_root(root, now).await;
+
+    let policy = app.config.borrow().propagation_policy.clone();
+    let gas_price = if policy.needs_gas_price() {
+        app.bridge_processor.gas_price().await?
+    } else {
+        U256::zero()
+    };
+    let root_history_expiry = if policy.needs_root_history_expiry() {
+        app.bridge_processor.root_history_expiry().await?
+    } else {
+        Duration::ZERO
+    };
+
+    let (new_roots, oldest_pending_root_at) = app.propagation_tracker.pending().await;
+    let ctx = PolicyContext {
+        now,
+        last_propagation: app.database.get_last_transaction_time().await?,
+        new_roots,
+        oldest_pending_root_at,
+        gas_price,
+        root_history_expiry,
+    };
+
+    let decision = policy.evaluate(&ctx);
+    info!(propagate = decision.propagate, reason = %decision.reason, ?root, "Propagation policy decision");
+
+    PROPAGATION_DECISIONS
+        .with_label_values(&[decision.label()])
+        .inc();
+    if decision.propagate || *last_decision != Some((root, false)) {
+        app.database
+            .insert_propagation_decision(root, &decision)
+            .await?;
+        *last_decision = Some((root, decision.propagate));
+    }
+
+    Ok(decision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PropagationPolicy;
+    use crate::database::BridgeStore;
+    use crate::processor::mock::MockState;
+    use crate::task_monitor::tasks::testing;
+
+    #[tokio::test(start_paused = true)]
+    async fn wakes_propagation_when_roots_differ() {
+        let (app, store, _) = testing::app(MockState {
+            l1_root: U256::from(1),
+            ..MockState::default()
+        });
+        store.mark_status_as_synced("roots match").await.unwrap();
+
+        let wake_up = Arc::new(Notify::new());
+        let task = tokio::spawn(check_sync(app, wake_up.clone()));
+        time::timeout(Duration::from_secs(1), wake_up.notified())
+            .await
+            .expect("propagation wasn't woken");
+        task.abort();
+
+        let change = store.history().pop().unwrap();
+        assert_eq!(change.to, BridgeStatus::Unsynced);
+        assert_eq!(change.cause, "roots differ");
+        let decisions = store.decisions();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].root, U256::from(1));
+        assert!(decisions[0].decision.propagate);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn follows_the_roots_every_interval() {
+        let (app, store, processor) = testing::app(MockState::default());
+        store
+            .mark_status_as_pending("propagation queued")
+            .await
+            .unwrap();
+        let interval = app.config.borrow().tasks.check_sync_interval;
+
+        let task = tokio::spawn(check_sync(app, Arc::new(Notify::new())));
+        time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(
+            store.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Synced)
+        );
+
+        processor.state().l1_root = U256::from(1);
+        time::sleep(interval).await;
+        task.abort();
+
+        assert_eq!(
+            store.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Unsynced)
+        );
+        assert_eq!(store.decisions().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_a_skip_once_per_root() {
+        let (app, store, processor) = testing::app(MockState {
+            l1_root: U256::from(1),
+            gas_price_error: Some(BridgeError::RpcUnavailable("timed out".into())),
+            ..MockState::default()
+        });
+        app.config.send_modify(|config| {
+            config.propagation_policy = PropagationPolicy::RootCount { min_new_roots: 2 };
+        });
+        let interval = app.config.borrow().tasks.check_sync_interval;
+
+        // The policy doesn't look at the gas price, so it isn't fetched
+        let task = tokio::spawn(check_sync(app, Arc::new(Notify::new())));
+        time::sleep(interval * 2 + interval / 2).await;
+        assert!(!task.is_finished());
+        assert_eq!(store.decisions().len(), 1);
+        assert!(!store.decisions()[0].decision.propagate);
+
+        processor.state().l1_root = U256::from(2);
+        time::sleep(interval).await;
+        task.abort();
+
+        let decisions = store.decisions();
+        assert_eq!(decisions.len(), 2);
+        assert_eq!(decisions[1].root, U256::from(2));
+        assert!(decisions[1].decision.propagate);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_a_queued_propagation() {
+        let (app, store, processor) = testing::app(MockState {
+            l1_root: U256::from(1),
+            ..MockState::default()
+        });
+        let interval = app.config.borrow().tasks.check_sync_interval;
+
+        // Queued, but not sent yet, so there's no transaction
+        store.enqueue_propagation(U256::from(1)).await.unwrap();
+        let wake_up = Arc::new(Notify::new());
+        let task = tokio::spawn(check_sync(app, wake_up.clone()));
+        time::sleep(interval * 2 + interval / 2).await;
+        assert_eq!(
+            store.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Pending)
+        );
+        assert!(store.decisions().is_empty());
+
+        processor.state().l2_root = U256::from(1);
+        time::sleep(interval).await;
+        task.abort();
 
-        wake_up_notify.notify_one()
+        assert_eq!(
+            store.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Synced)
+        );
+        assert!(time::timeout(Duration::from_secs(1), wake_up.notified())
+            .await
+            .is_err());
     }
 }
@@ -1,17 +1,19 @@
 use std::sync::Arc;
 use tracing::{error, info};
 use tokio::time;
-use std::time::Duration;
 
 use crate::database::types::TxStatus;
-use crate::{app::App, database::query::DatabaseQuery};
+use crate::task_monitor::reset_period;
+use crate::app::App;
+use crate::error::BridgeError;
 
 pub async fn finalize_txs(
     app: Arc<App>
-) -> anyhow::Result<()> {
-  let mut timer = time::interval(Duration::from_secs(600));
+) -> Result<(), BridgeError> {
+  let mut timer = time::interval(app.config.borrow().tasks.finalize_txs_interval);
   loop {
     _ = timer.tick().await;
+    reset_period(&mut timer, app.config.borrow().tasks.finalize_txs_interval);
     let mined_transactions = app.bridge_processor.get_mined_transactions().await?;    
 
     match app.database.get_last_transaction_id().await {
@@ -31,3 +33,39 @@ pub async fn finalize_txs(
     };
   }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ethers::types::U256;
+
+    use super::*;
+    use crate::database::BridgeStore;
+    use crate::processor::mock::MockState;
+    use crate::task_monitor::tasks::testing;
+
+    #[tokio::test(start_paused = true)]
+    async fn marks_mined_transactions() {
+        let (app, store, processor) = testing::app(MockState::default());
+        let tx_id = testing::sent_propagation(&store, U256::from(1)).await;
+        let interval = app.config.borrow().tasks.finalize_txs_interval;
+
+        let task = tokio::spawn(finalize_txs(app));
+        time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(
+            store.get_last_transaction_id().await.unwrap(),
+            Some(tx_id.clone())
+        );
+
+        processor.state().mined.push(tx_id);
+        time::sleep(interval).await;
+        task.abort();
+
+        assert_eq!(store.get_last_transaction_id().await.unwrap(), None);
+        assert_eq!(
+            store.get_last_transaction_status().await.unwrap(),
+            Some(TxStatus::Mined)
+        );
+    }
+}
@@ -1,4 +1,10 @@
 pub mod propagate_root;
 pub mod monitor_txs;
 pub mod check_sync;
-pub mod finalize_txs;
\ No newline at end of file
+pub mod finalize_txs;
+pub mod monitor_balance;
+pub mod retention;
+pub mod send_propagations;
+
+#[cfg(test)]
+pub mod testing;
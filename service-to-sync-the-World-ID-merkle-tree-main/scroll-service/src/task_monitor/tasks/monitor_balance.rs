@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use ethers::types::U256;
+use tokio::time;
+use tracing::{error, info, warn};
+
+use crate::app::App;
+use crate::error::BridgeError;
+use crate::task_monitor::balance::FundsStatus;
+use crate::task_monitor::reset_period;
+
+const RELAYER_STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn monitor_balance(app: Arc<App>) -> Result<(), BridgeError> {
+    let mut timer = time::interval(app.balance_monitor.config().poll_interval);
+    loop {
+        _ = timer.tick().await;
+        let config = app.balance_monitor.config();
+        reset_period(&mut timer, config.poll_interval);
+
+        let balance = app.bridge_processor.relayer_balance().await?;
+
+        let now = Utc::now();
+        let since = chrono::Duration::from_std(config.runway_window)
+            .ok()
+            .and_then(|window| now.checked_sub_signed(window));
+        let spent = app
+            .database
+            .get_propagation_costs(since, None)
+            .await?
+            .iter()
+            .fold(U256::zero(), |acc, record| {
+                acc.saturating_add(record.cost.net())
+            });
+
+        let status = app.balance_monitor.update(balance, spent, now).await;
+
+        match status.status {
+            FundsStatus::Ok => {
+                info!(balance = %status.balance, runway_seconds = ?status.runway_seconds, "Relayer balance");
+            }
+            FundsStatus::Low => {
+                warn!(balance = %status.balance, runway_seconds = ?status.runway_seconds, "Relayer balance is low");
+            }
+            FundsStatus::InsufficientFunds => {
+                error!(
+                    balance = %status.balance,
+                    min_balance_gwei = config.min_balance_gwei,
+                    "Relayer balance is below the minimum, propagations are paused"
+                );
+            }
+        }
+
+        // Cached for /serviceStatus, so that a slow relayer doesn't hold it up
+        let relayer = match time::timeout(
+            RELAYER_STATUS_TIMEOUT,
+            app.bridge_processor.relayer_status(),
+        )
+        .await
+        {
+            Ok(Ok(relayer)) => relayer,
+            Ok(Err(err)) => {
+                warn!(?err, "Failed to fetch the relayer status");
+                None
+            }
+            Err(_) => {
+                warn!("Timed out fetching the relayer status");
+                None
+            }
+        };
+        app.balance_monitor.set_relayer(relayer).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ethereum::RelayerStatus;
+    use crate::processor::mock::MockState;
+    use crate::task_monitor::tasks::testing;
+
+    #[tokio::test(start_paused = true)]
+    async fn caches_the_relayer_status() {
+        let relayer = RelayerStatus {
+            name:        "relayer".to_string(),
+            enabled:     true,
+            nonce:       3,
+            mined_nonce: 2,
+            balance:     None,
+        };
+        let (app, _store, processor) = testing::app(MockState {
+            relayer_balance: U256::from(10).pow(18.into()),
+            relayer: Some(relayer.clone()),
+            ..MockState::default()
+        });
+        let poll_interval = app.balance_monitor.config().poll_interval;
+
+        let task = tokio::spawn(monitor_balance(app.clone()));
+        time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(app.balance_monitor.relayer().await, Some(relayer.clone()));
+
+        // Served from the last reading, not fetched for each request
+        processor.state().relayer = None;
+        let status = app.get_service_status().await.unwrap();
+        assert_eq!(status.relayer, Some(relayer));
+        assert!(status.funds.is_some());
+
+        time::sleep(poll_interval).await;
+        task.abort();
+
+        assert_eq!(app.balance_monitor.relayer().await, None);
+    }
+}
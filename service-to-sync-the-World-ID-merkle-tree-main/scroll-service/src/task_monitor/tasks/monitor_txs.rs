@@ -1,32 +1,91 @@
 use std::sync::Arc;
 
 use tokio::sync::{mpsc, Mutex};
-use tracing::{error, info};
+use tracing::{info, warn};
 
 use crate::app::App;
-use crate::database::query::DatabaseQuery;
 use crate::database::types::TxStatus;
 use crate::utils::TransactionId;
+use crate::error::BridgeError;
 
 pub async fn monitor_txs(
     app: Arc<App>,
     monitored_txs_receiver: Arc<Mutex<mpsc::Receiver<TransactionId>>>,
-) -> anyhow::Result<()> {
+) -> Result<(), BridgeError> {
     let mut monitored_txs_receiver = monitored_txs_receiver.lock().await;
 
     while let Some(tx) = monitored_txs_receiver.recv().await {
         // assert!(
         match app.bridge_processor.mine_transaction(tx.clone()).await  {
-            Ok(id) => {
-                info!("Transaction Status: {:?}", id);
-                app.database.update_transaction(&tx, TxStatus::Mined).await?;
+            Ok(outcome) => {
+                info!("Transaction Status: {:?}", outcome.succeeded);
+
+                // Written together with the status. If writing fails, the
+                // transaction is left pending, and finalize_txs marks it as
+                // mined without a cost
+                if let Some(cost) = &outcome.cost {
+                    info!(?cost, "Recording propagation cost");
+                }
+                let status = if outcome.succeeded {
+                    TxStatus::Mined
+                } else {
+                    TxStatus::Failed
+                };
+                app.database
+                    .record_transaction_outcome(&tx, status, outcome.cost.as_ref())
+                    .await?;
+                if let Some(cost) = outcome.cost {
+                    cost.record_metrics();
+                }
             },
+            // Reverted and failed transactions come back as an outcome, so
+            // this is only failing to find out. finalize_txs marks the
+            // transaction as mined once the relayer reports it
             Err(err) => {
-                error!(%err, "Transaction failed");
-                app.database.update_transaction(&tx, TxStatus::Mined).await?;
+                warn!(%err, %tx, "Failed to wait for the transaction, leaving it pending");
             }
         };
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U256;
+
+    use super::*;
+    use crate::database::BridgeStore;
+    use crate::processor::mock::MockState;
+    use crate::task_monitor::tasks::testing;
+
+    async fn monitor(state: MockState) -> Option<TxStatus> {
+        let (app, store, _) = testing::app(state);
+        let tx_id = testing::sent_propagation(&store, U256::from(1)).await;
+
+        let (sender, receiver) = mpsc::channel(1);
+        sender.send(tx_id).await.unwrap();
+        drop(sender);
+        monitor_txs(app, Arc::new(Mutex::new(receiver)))
+            .await
+            .unwrap();
+
+        store.get_last_transaction_status().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn marks_mined_transactions() {
+        assert_eq!(monitor(MockState::default()).await, Some(TxStatus::Mined));
+    }
+
+    #[tokio::test]
+    async fn leaves_transactions_pending_when_waiting_fails() {
+        let status = monitor(MockState {
+            mine_error: Some(BridgeError::RpcUnavailable("connection refused".into())),
+            ..MockState::default()
+        })
+        .await;
+
+        assert_eq!(status, Some(TxStatus::Pending));
+    }
+}
@@ -1,47 +1,120 @@
 use std::sync::Arc;
-use tokio::sync::{mpsc, Notify};
-use crate::database::query::DatabaseQuery;
-use crate::database::types::TxStatus;
+use chrono::Utc;
+use tokio::sync::Notify;
 use crate::processor::status::BridgeStatus;
+use crate::task_monitor::deferral::DeferralOutcome;
 use crate::task_monitor::{App, TaskMonitor};
-use crate::utils::TransactionId;
-use tracing::error;
+use crate::error::BridgeError;
+use tracing::{info, warn};
 
+/// Decides when to propagate and queues the propagation in the outbox.
 pub async fn propagate_root(
-    app: Arc<App>, 
-    monitored_txs_sender: Arc<mpsc::Sender<TransactionId>>,
-    wake_up_notify: Arc<Notify>
-) -> anyhow::Result<()> {
+    app: Arc<App>,
+    wake_up_notify: Arc<Notify>,
+    outbox_notify: Arc<Notify>,
+) -> Result<(), BridgeError> {
     loop {
-        _ = wake_up_notify.notified();
+        if app.gas_deferral.status().await.is_some() {
+            // Re-check the base fee on our own while deferring
+            tokio::select! {
+                () = wake_up_notify.notified() => {}
+                () = tokio::time::sleep(app.gas_deferral.config().recheck_interval) => {}
+            }
+        } else {
+            wake_up_notify.notified().await;
+        }
 
-        let is_unsynced = TaskMonitor::check_db_state(&app.database, BridgeStatus::Unsynced).await?;
+        let is_unsynced =
+            TaskMonitor::check_db_state(app.database.as_ref(), BridgeStatus::Unsynced).await?;
 
-        // there is an existing tx pending
-        let tx_pending = TaskMonitor::check_last_transaction_status(&app.database, TxStatus::Pending).await?;
+        // there is a propagation queued or an existing tx pending
+        let tx_pending = TaskMonitor::check_propagation_pending(app.database.as_ref()).await?;
 
 
         if !is_unsynced || tx_pending {
+            app.gas_deferral.reset().await;
+            continue;
+        }
+
+        if app.balance_monitor.has_insufficient_funds().await {
+            warn!("Insufficient relayer funds, not propagating root");
             continue;
         }
-        
-        let tx_id = app.bridge_processor
-            .propagate_root()
-            .await?;
-
-        // add tx_id to db
-        app.database.insert_new_transaction(&tx_id).await?;
-
-        match monitored_txs_sender.send(tx_id.clone()).await {
-            Ok(id) => {
-                id
-            },
-            Err(err) => {
-                error!(%err, "Transaction failed");
+
+        if app.gas_deferral.is_enabled() {
+            let base_fee = app.bridge_processor.base_fee().await?;
+
+            if app.gas_deferral.check(base_fee, Utc::now()).await == DeferralOutcome::Defer {
+                continue;
+            }
+        }
+
+        let root = app.bridge_processor.latest_root().await?;
+
+        // The intent and the pending status are written together, the
+        // sender picks it up from there
+        match app.database.enqueue_propagation(root).await? {
+            Some(intent_id) => {
+                info!(intent_id, ?root, "Queued root propagation");
+                app.gas_deferral.reset().await;
+                outbox_notify.notify_one();
             }
-        };
+            None => warn!("A propagation is already queued"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ethers::types::U256;
+
+    use super::*;
+    use crate::database::types::OutboxStatus;
+    use crate::database::BridgeStore;
+    use crate::processor::mock::MockState;
+    use crate::task_monitor::tasks::testing;
+
+    #[tokio::test(start_paused = true)]
+    async fn queues_a_propagation_when_unsynced() {
+        let (app, store, _) = testing::app(MockState {
+            l1_root: U256::from(1),
+            ..MockState::default()
+        });
+
+        let wake_up = Arc::new(Notify::new());
+        let outbox = Arc::new(Notify::new());
+        wake_up.notify_one();
+        let task = tokio::spawn(propagate_root(app, wake_up, outbox.clone()));
+        tokio::time::timeout(Duration::from_secs(1), outbox.notified())
+            .await
+            .expect("sender wasn't notified");
+        task.abort();
+
+        assert_eq!(store.outbox(), vec![(OutboxStatus::Pending, None)]);
+        assert_eq!(
+            store.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Pending)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_the_pending_transaction() {
+        let (app, store, _) = testing::app(MockState::default());
+        testing::sent_propagation(&store, U256::from(1)).await;
+        store.mark_status_as_unsynced("roots differ").await.unwrap();
+
+        let wake_up = Arc::new(Notify::new());
+        wake_up.notify_one();
+        let task = tokio::spawn(propagate_root(app, wake_up, Arc::new(Notify::new())));
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        task.abort();
 
-        // update db state to pending
-        app.database.mark_status_as_pending().await?;
+        assert_eq!(store.outbox().len(), 1);
+        assert_eq!(
+            store.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Unsynced)
+        );
     }
 }
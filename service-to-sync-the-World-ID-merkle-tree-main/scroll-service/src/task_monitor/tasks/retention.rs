@@ -0,0 +1,207 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_gauge, register_gauge_vec, register_int_counter_vec, Gauge, GaugeVec, IntCounterVec,
+};
+use tokio::time;
+use tracing::info;
+
+use crate::app::App;
+use crate::config::RetentionConfig;
+use crate::database::transaction::day_bounds;
+use crate::database::types::RetainedTable;
+use crate::error::BridgeError;
+use crate::task_monitor::reset_period;
+
+static ROWS_PRUNED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "retention_rows_pruned",
+        "Rows deleted by the retention task, by table.",
+        &["table"]
+    )
+    .unwrap()
+});
+
+static ROWS_ARCHIVED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "retention_rows_archived",
+        "Rows written to the archive before being pruned, by table.",
+        &["table"]
+    )
+    .unwrap()
+});
+
+static BACKLOG_DAYS: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "retention_backlog_days",
+        "Days of rows past retention still to be pruned, by table.",
+        &["table"]
+    )
+    .unwrap()
+});
+
+static LAST_RUN: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "retention_last_run_timestamp",
+        "Unix time the retention task last finished pruning."
+    )
+    .unwrap()
+});
+
+/// Prunes rows older than the configured number of days, a whole day at a
+/// time. A day is archived before it's pruned, so a crash in between only
+/// means archiving it again, in the next run's file.
+pub async fn retention(app: Arc<App>) -> Result<(), BridgeError> {
+    let mut timer = time::interval(app.config.borrow().retention.interval);
+    loop {
+        _ = timer.tick().await;
+        let config = app.config.borrow().retention.clone();
+        reset_period(&mut timer, config.interval);
+
+        if !config.enabled {
+            continue;
+        }
+
+        let run = Utc::now();
+        let cutoff = cutoff(run, config.keep_days);
+        for table in RetainedTable::ALL {
+            prune_table(&app, &config, table, cutoff, run).await?;
+        }
+        // Timestamps stay far below 2^52, so f64 holds them exactly
+        #[allow(clippy::cast_precision_loss)]
+        LAST_RUN.set(Utc::now().timestamp() as f64);
+    }
+}
+
+async fn prune_table(
+    app: &App,
+    config: &RetentionConfig,
+    table: RetainedTable,
+    cutoff: DateTime<Utc>,
+    run: DateTime<Utc>,
+) -> Result<(), BridgeError> {
+    while let Some(oldest) = app.database.oldest_prunable(table, cutoff).await? {
+        let backlog_days = i32::try_from((cutoff - oldest).num_days()).unwrap_or(i32::MAX);
+        BACKLOG_DAYS
+            .with_label_values(&[table.name()])
+            .set(f64::from(backlog_days) + 1.0);
+
+        let day = oldest.date_naive();
+        if let Some(archive_dir) = &config.archive_dir {
+            let (from, to) = day_bounds(day);
+            let rows = app.database.prunable_rows(table, from, to).await?;
+            let archived = rows.len() as u64;
+            write_archive(archive_path(archive_dir, table, day, run), rows).await?;
+            ROWS_ARCHIVED
+                .with_label_values(&[table.name()])
+                .inc_by(archived);
+        }
+
+        let pruned = app.database.prune_day(table, day).await?;
+        info!(table = table.name(), %day, pruned, "Pruned rows past retention");
+        ROWS_PRUNED
+            .with_label_values(&[table.name()])
+            .inc_by(pruned);
+    }
+    BACKLOG_DAYS.with_label_values(&[table.name()]).set(0.0);
+
+    Ok(())
+}
+
+/// Rows written before the returned time are past retention. Only whole
+/// days are pruned, so that each day is rolled up once.
+fn cutoff(now: DateTime<Utc>, keep_days: u32) -> DateTime<Utc> {
+    day_bounds(now.date_naive()).0 - Duration::days(i64::from(keep_days))
+}
+
+/// Each run archives to its own file. Rows kept back from pruning, like
+/// pending transactions, are archived by a later run, so a day can be
+/// archived more than once.
+fn archive_path(
+    archive_dir: &Path,
+    table: RetainedTable,
+    day: NaiveDate,
+    run: DateTime<Utc>,
+) -> PathBuf {
+    archive_dir.join(table.name()).join(format!(
+        "{}.{}.ndjson",
+        day.format("%Y-%m-%d"),
+        run.format("%Y%m%dT%H%M%SZ")
+    ))
+}
+
+/// Writes the archive at `path`, so that it's either complete or missing.
+async fn write_archive(path: PathBuf, rows: Vec<String>) -> std::io::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let partial = path.with_extension("ndjson.partial");
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+        for row in rows {
+            writeln!(file, "{row}")?;
+        }
+        file.into_inner()?.sync_all()?;
+
+        std::fs::rename(partial, path)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn keeps_whole_days() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap();
+
+        assert_eq!(
+            cutoff(now, 1),
+            Utc.with_ymd_and_hms(2024, 3, 9, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            cutoff(now, 30),
+            Utc.with_ymd_and_hms(2024, 2, 9, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn archives_rows_as_ndjson() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let first_run = Utc.with_ymd_and_hms(2024, 3, 12, 0, 0, 0).unwrap();
+        let first = archive_path(dir.path(), RetainedTable::Transactions, day, first_run);
+        let second = archive_path(
+            dir.path(),
+            RetainedTable::Transactions,
+            day,
+            first_run + Duration::hours(1),
+        );
+
+        write_archive(first.clone(), vec![
+            "{\"a\":1}".to_string(),
+            "{\"b\":2}".to_string(),
+        ])
+        .await
+        .unwrap();
+        write_archive(second.clone(), vec!["{\"c\":3}".to_string()])
+            .await
+            .unwrap();
+
+        // A later run archiving the same day keeps the earlier archive
+        assert!(first.ends_with("transactions/2024-03-10.20240312T000000Z.ndjson"));
+        assert_eq!(
+            std::fs::read_to_string(first).unwrap(),
+            "{\"a\":1}\n{\"b\":2}\n"
+        );
+        assert_eq!(std::fs::read_to_string(second).unwrap(), "{\"c\":3}\n");
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, Notify};
+use tracing::{error, info, warn};
+
+use crate::app::App;
+use crate::error::BridgeError;
+use crate::utils::TransactionId;
+
+/// Sends queued propagations and records the relayer's transaction.
+///
+/// Each step can be repeated after a crash: an intent left claimed is
+/// claimed again, and resending it with the same key doesn't send twice.
+pub async fn send_propagations(
+    app: Arc<App>,
+    monitored_txs_sender: Arc<mpsc::Sender<TransactionId>>,
+    outbox_notify: Arc<Notify>,
+) -> Result<(), BridgeError> {
+    loop {
+        while let Some(intent) = app.database.claim_propagation_intent().await? {
+            info!(intent_id = intent.id, root = %intent.root, attempts = intent.attempts, "Sending root propagation");
+
+            let tx_id = match app
+                .bridge_processor
+                .propagate_root(&intent.idempotency_key)
+                .await
+            {
+                Ok(tx_id) => tx_id,
+                Err(err) if err.is_retryable() => {
+                    app.database
+                        .record_intent_error(intent.id, &err.to_string(), false)
+                        .await?;
+                    return Err(err);
+                }
+                Err(err) => {
+                    error!(intent_id = intent.id, %err, "Giving up on root propagation");
+                    app.database
+                        .record_propagation_failed(&intent, &err.to_string())
+                        .await?;
+                    return Err(err);
+                }
+            };
+
+            app.database
+                .record_propagation_sent(&intent, &tx_id)
+                .await?;
+            app.propagation_tracker.clear().await;
+
+            // finalize_txs picks the transaction up from the database if
+            // this is lost
+            if let Err(err) = monitored_txs_sender.send(tx_id).await {
+                warn!(%err, "Failed to monitor the propagation transaction");
+            }
+        }
+
+        let poll_interval = app.config.borrow().tasks.outbox_poll_interval;
+        tokio::select! {
+            () = outbox_notify.notified() => {}
+            () = tokio::time::sleep(poll_interval) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ethers::types::U256;
+    use tokio::time;
+
+    use super::*;
+    use crate::database::types::OutboxStatus;
+    use crate::database::BridgeStore;
+    use crate::processor::mock::{MockProcessor, MockState};
+    use crate::processor::status::BridgeStatus;
+    use crate::task_monitor::tasks::testing;
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_queued_propagations_once() {
+        let (app, store, processor) = testing::app(MockState::default());
+        store.enqueue_propagation(U256::from(1)).await.unwrap();
+
+        let (sender, mut receiver) = mpsc::channel(1);
+        let task = tokio::spawn(send_propagations(
+            app,
+            Arc::new(sender),
+            Arc::new(Notify::new()),
+        ));
+        let tx_id = time::timeout(Duration::from_secs(1), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        time::sleep(Duration::from_secs(60)).await;
+        task.abort();
+
+        let sent = processor.state().sent;
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("propagation-"));
+        assert_eq!(tx_id, MockProcessor::transaction_id(&sent[0]));
+        assert_eq!(store.outbox(), vec![(
+            OutboxStatus::Sent,
+            Some(tx_id.clone())
+        )]);
+        assert_eq!(store.get_last_transaction_id().await.unwrap(), Some(tx_id));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_on_non_retryable_errors() {
+        let (app, store, _) = testing::app(MockState {
+            propagation_error: Some(BridgeError::ContractMismatch("wrong chain".into())),
+            ..MockState::default()
+        });
+        store.enqueue_propagation(U256::from(1)).await.unwrap();
+
+        let (sender, _receiver) = mpsc::channel(1);
+        let result = send_propagations(app, Arc::new(sender), Arc::new(Notify::new())).await;
+
+        assert!(matches!(result, Err(BridgeError::ContractMismatch(_))));
+        assert_eq!(store.outbox(), vec![(OutboxStatus::Failed, None)]);
+        assert_eq!(
+            store.get_db_status().await.unwrap(),
+            Some(BridgeStatus::Unsynced)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_claimed_intents() {
+        let (app, store, processor) = testing::app(MockState {
+            propagation_error: Some(BridgeError::RpcUnavailable("timed out".into())),
+            ..MockState::default()
+        });
+        store.enqueue_propagation(U256::from(1)).await.unwrap();
+
+        let (sender, mut receiver) = mpsc::channel(1);
+        let sender = Arc::new(sender);
+        let result = send_propagations(app.clone(), sender.clone(), Arc::new(Notify::new())).await;
+        assert!(matches!(result, Err(BridgeError::RpcUnavailable(_))));
+        assert_eq!(store.outbox(), vec![(OutboxStatus::Claimed, None)]);
+
+        let task = tokio::spawn(send_propagations(app, sender, Arc::new(Notify::new())));
+        let tx_id = time::timeout(Duration::from_secs(1), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        task.abort();
+
+        assert_eq!(processor.state().sent.len(), 1);
+        assert_eq!(store.outbox(), vec![(OutboxStatus::Sent, Some(tx_id))]);
+    }
+}
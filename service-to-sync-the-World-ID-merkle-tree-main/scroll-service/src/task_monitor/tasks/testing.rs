@@ -0,0 +1,34 @@
+//! Fixtures for stepping the task loops against an in-memory store and a
+//! mock processor.
+use std::sync::Arc;
+
+use ethers::types::U256;
+pub use fake_chain::http::serve;
+
+use crate::app::App;
+use crate::config::Config;
+use crate::database::memory::MemoryStore;
+use crate::database::BridgeStore;
+use crate::processor::mock::{MockProcessor, MockState};
+
+pub fn app(state: MockState) -> (Arc<App>, Arc<MemoryStore>, Arc<MockProcessor>) {
+    let store = Arc::new(MemoryStore::initialized());
+    let processor = Arc::new(MockProcessor::new(state));
+    let app = App::for_tests(Config::for_tests(), store.clone(), processor.clone());
+
+    (app, store, processor)
+}
+
+/// Records a propagation of `root` as sent, leaving its transaction
+/// pending. Returns the transaction id.
+pub async fn sent_propagation(store: &MemoryStore, root: U256) -> String {
+    store.enqueue_propagation(root).await.unwrap().unwrap();
+    let intent = store.claim_propagation_intent().await.unwrap().unwrap();
+    let tx_id = MockProcessor::transaction_id(&intent.idempotency_key);
+    store
+        .record_propagation_sent(&intent, &tx_id)
+        .await
+        .unwrap();
+
+    tx_id
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::config::BackoffPolicy;
+use crate::error::ErrorKind;
+
+/// Tracks consecutive failures of a monitored task and works out how long to
+/// wait before restarting it.
+#[derive(Debug)]
+pub struct Backoff {
+    policy:   BackoffPolicy,
+    failures: u32,
+}
+
+impl Backoff {
+    #[must_use]
+    pub const fn new(policy: BackoffPolicy) -> Self {
+        Self {
+            policy,
+            failures: 0,
+        }
+    }
+
+    /// Records a failure after the task ran for `runtime` and returns the wait
+    /// before the next restart. Panics carry no error kind.
+    pub fn on_failure(&mut self, kind: Option<ErrorKind>, runtime: Duration) -> Duration {
+        if runtime >= self.policy.healthy_after {
+            self.failures = 0;
+        }
+        self.failures = self.failures.saturating_add(1);
+
+        let base = kind.map_or(self.policy.initial, |kind| {
+            kind.backoff(self.policy.initial)
+        });
+        let growth = self.policy.multiplier.saturating_pow(self.failures - 1);
+        let delay = base.saturating_mul(growth).min(self.policy.max);
+
+        self.jitter(delay)
+    }
+
+    /// How long a restarted task has to run to count as having recovered.
+    #[must_use]
+    pub const fn healthy_after(&self) -> Duration {
+        self.policy.healthy_after
+    }
+
+    /// Records that the task has been running for `healthy_after` since its
+    /// last restart, which starts the backoff over.
+    pub fn on_recovered(&mut self) {
+        self.failures = 0;
+    }
+
+    #[must_use]
+    pub const fn consecutive_failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Whether the task kept failing often enough to trip the circuit breaker.
+    #[must_use]
+    pub const fn is_degraded(&self) -> bool {
+        self.failures >= self.policy.failure_threshold
+    }
+
+    fn jitter(&self, delay: Duration) -> Duration {
+        let jitter_percent = u32::from(self.policy.jitter_percent.min(100));
+        if jitter_percent == 0 {
+            return delay;
+        }
+
+        let cut = rand::thread_rng().gen_range(0..=jitter_percent);
+        delay * (100 - cut) / 100
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> BackoffPolicy {
+        BackoffPolicy {
+            initial:           Duration::from_secs(1),
+            max:               Duration::from_secs(10),
+            multiplier:        2,
+            jitter_percent:    0,
+            failure_threshold: 3,
+            healthy_after:     Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn grows_exponentially_up_to_max() {
+        let mut backoff = Backoff::new(policy());
+        let quick = Duration::from_secs(1);
+
+        let delays: Vec<_> = (0..5)
+            .map(|_| backoff.on_failure(None, quick).as_secs())
+            .collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 10]);
+        assert_eq!(backoff.consecutive_failures(), 5);
+    }
+
+    #[test]
+    fn degrades_and_recovers() {
+        let mut backoff = Backoff::new(policy());
+        let quick = Duration::from_secs(1);
+
+        backoff.on_failure(None, quick);
+        backoff.on_failure(None, quick);
+        assert!(!backoff.is_degraded());
+        backoff.on_failure(None, quick);
+        assert!(backoff.is_degraded());
+
+        // Running healthy for long enough resets the failure count
+        let delay = backoff.on_failure(None, Duration::from_secs(60));
+        assert_eq!(delay, Duration::from_secs(1));
+        assert!(!backoff.is_degraded());
+    }
+
+    #[test]
+    fn scales_with_error_kind() {
+        let mut backoff = Backoff::new(policy());
+
+        let delay = backoff.on_failure(Some(ErrorKind::RelayerRejected), Duration::ZERO);
+        assert_eq!(delay, Duration::from_secs(4));
+
+        let delay = backoff.on_failure(Some(ErrorKind::Reverted), Duration::ZERO);
+        assert_eq!(delay, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let mut backoff = Backoff::new(BackoffPolicy {
+            jitter_percent: 50,
+            ..policy()
+        });
+
+        for _ in 0..20 {
+            backoff.failures = 0;
+            let delay = backoff.on_failure(None, Duration::ZERO);
+            assert!(delay >= Duration::from_millis(500));
+            assert!(delay <= Duration::from_secs(1));
+        }
+    }
+}
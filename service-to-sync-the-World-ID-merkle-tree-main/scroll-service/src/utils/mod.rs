@@ -1,19 +1,25 @@
 use std::future::Future;
 use std::sync::Arc;
-use std::time::Duration;
 
 use futures::FutureExt;
+use once_cell::sync::Lazy;
+use prometheus::{register_gauge_vec, register_int_counter_vec, GaugeVec, IntCounterVec};
 use tokio::select;
 use tokio::sync::broadcast;
 use tokio::task::JoinHandle;
-use tracing::{error, info};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
 
 pub mod shutdown;
 
+use self::backoff::Backoff;
 use self::shutdown::Shutdown;
+use crate::config::BackoffPolicy;
+use crate::error::BridgeError;
 
 pub type TransactionId = String;
 
+pub mod backoff;
 pub mod batch_type;
 pub mod index_packing;
 pub mod min_map;
@@ -22,6 +28,33 @@ pub mod serde_utils;
 
 pub const TX_RETRY_LIMIT: u32 = 10;
 
+static TASK_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "task_errors",
+        "Number of task failures by task and error kind.",
+        &["task", "kind"]
+    )
+    .unwrap()
+});
+
+static TASK_RESTARTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "task_restarts",
+        "Number of times a monitored task was restarted.",
+        &["task"]
+    )
+    .unwrap()
+});
+
+static TASK_DEGRADED: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "task_degraded",
+        "1 while a task keeps failing past its failure threshold",
+        &["task"]
+    )
+    .unwrap()
+});
+
 /// Retries a transaction a certain number of times
 /// Only errors originating from `Transaction::commit` are retried
 /// Errors originating from the transaction function `$expression` are not
@@ -76,67 +109,120 @@ macro_rules! retry_tx {
     };
 }
 
+/// Runs the task spawned by `future_spawner` until it succeeds or the service
+/// shuts down, restarting it after failures. The wait before a restart grows
+/// with consecutive failures and is scaled by the kind of error the task
+/// failed with, see [`Backoff`].
 pub fn spawn_monitored_with_backoff<S, F>(
+    task: &'static str,
     future_spawner: S,
     shutdown_sender: broadcast::Sender<()>,
-    backoff_duration: Duration,
+    policy: BackoffPolicy,
     shutdown: Arc<Shutdown>,
 ) -> JoinHandle<()>
 where
-    F: Future<Output = anyhow::Result<()>> + Send + 'static,
+    F: Future<Output = Result<(), BridgeError>> + Send + 'static,
     S: Fn() -> F + Send + Sync + 'static,
 {
     // Run task in background, returning a handle.
     tokio::spawn(async move {
+        let mut backoff = Backoff::new(policy);
+        TASK_DEGRADED.with_label_values(&[task]).set(0.0);
+
         loop {
             let mut shutdown_receiver = shutdown_sender.subscribe();
 
+            let started_at = Instant::now();
             let future = future_spawner();
 
             // Wrap in `AssertUnwindSafe` so we can call `FuturesExt::catch_unwind` on it.
-            let future = std::panic::AssertUnwindSafe(future);
-
-            let result = select! {
-                result = future.catch_unwind() => {
-                    result
-                }
-                _ = shutdown_receiver.recv() => {
-                    info!("Woke up by shutdown signal, exiting.");
-                    return;
+            let future = std::panic::AssertUnwindSafe(future).catch_unwind();
+            tokio::pin!(future);
+
+            // Long-running tasks never return, so a restarted task counts as
+            // recovered once it has run for `healthy_after`
+            let healthy = tokio::time::sleep(backoff.healthy_after());
+            tokio::pin!(healthy);
+
+            let result = loop {
+                select! {
+                    result = &mut future => break result,
+                    () = &mut healthy, if backoff.consecutive_failures() > 0 => {
+                        if backoff.is_degraded() {
+                            info!(task, "Task recovered");
+                        }
+                        backoff.on_recovered();
+                        TASK_DEGRADED.with_label_values(&[task]).set(0.0);
+                    }
+                    _ = shutdown_receiver.recv() => {
+                        info!("Woke up by shutdown signal, exiting.");
+                        return;
+                    }
                 }
             };
 
             // let result = future.catch_unwind().await;
 
-            match result {
+            let kind = match result {
                 // Task succeeded or is shutting down gracefully
-                Ok(Ok(t)) => return t,
+                Ok(Ok(t)) => {
+                    TASK_DEGRADED.with_label_values(&[task]).set(0.0);
+                    return t;
+                }
                 Ok(Err(e)) => {
-                    error!("Task failed: {e:?}");
+                    let kind = e.kind();
+                    TASK_ERRORS.with_label_values(&[task, kind.as_str()]).inc();
 
-                    if shutdown.is_shutting_down() {
-                        return;
+                    if e.is_retryable() {
+                        warn!(task, kind = kind.as_str(), "Task failed: {e}");
+                    } else {
+                        error!(
+                            task,
+                            kind = kind.as_str(),
+                            "Task failed with non-retryable error: {e}"
+                        );
                     }
 
-                    tokio::time::sleep(backoff_duration).await;
+                    Some(kind)
                 }
                 Err(e) => {
-                    error!("Task panicked: {e:?}");
+                    TASK_ERRORS.with_label_values(&[task, "panic"]).inc();
+                    error!(task, "Task panicked: {e:?}");
 
-                    if shutdown.is_shutting_down() {
-                        return;
-                    }
+                    None
+                }
+            };
+
+            if shutdown.is_shutting_down() {
+                return;
+            }
+
+            let was_degraded = backoff.is_degraded();
+            let delay = backoff.on_failure(kind, started_at.elapsed());
+            let failures = backoff.consecutive_failures();
 
-                    tokio::time::sleep(backoff_duration).await;
+            if backoff.is_degraded() {
+                if !was_degraded {
+                    error!(task, failures, "Task is degraded, it keeps failing");
                 }
+                TASK_DEGRADED.with_label_values(&[task]).set(1.0);
+            } else {
+                if was_degraded {
+                    info!(task, "Task recovered");
+                }
+                TASK_DEGRADED.with_label_values(&[task]).set(0.0);
             }
+
+            info!(task, failures, ?delay, "Restarting task after backoff");
+            tokio::time::sleep(delay).await;
+            TASK_RESTARTS.with_label_values(&[task]).inc();
         }
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
     use std::sync::Arc;
     use std::time::Duration;
 
@@ -154,6 +240,7 @@ mod tests {
             let triggered_error = triggered_error.clone();
 
             spawn_monitored_with_backoff(
+                "test",
                 move || {
                     let can_finish = can_finish.clone();
                     let triggered_error = triggered_error.clone();
@@ -174,7 +261,11 @@ mod tests {
                     }
                 },
                 shutdown_sender,
-                Duration::from_secs_f32(0.2),
+                BackoffPolicy {
+                    initial: Duration::from_secs_f32(0.2),
+                    multiplier: 1,
+                    ..BackoffPolicy::default()
+                },
                 shutdown,
             )
         };
@@ -200,4 +291,122 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_and_degrades() {
+        let (shutdown_sender, _) = broadcast::channel(1);
+        let shutdown = Arc::new(Shutdown::new());
+        let attempts = Arc::new(AtomicU32::new(0));
+
+        let handle = {
+            let attempts = attempts.clone();
+
+            spawn_monitored_with_backoff(
+                "backs_off_and_degrades",
+                move || {
+                    let attempts = attempts.clone();
+
+                    async move {
+                        if attempts.fetch_add(1, Ordering::SeqCst) < 4 {
+                            Err(BridgeError::RpcUnavailable(
+                                "connection refused".to_string(),
+                            ))
+                        } else {
+                            Ok(())
+                        }
+                    }
+                },
+                shutdown_sender,
+                BackoffPolicy {
+                    initial:           Duration::from_secs(1),
+                    max:               Duration::from_secs(60),
+                    multiplier:        2,
+                    jitter_percent:    0,
+                    failure_threshold: 3,
+                    healthy_after:     Duration::from_secs(60),
+                },
+                shutdown,
+            )
+        };
+
+        let degraded = || {
+            TASK_DEGRADED
+                .with_label_values(&["backs_off_and_degrades"])
+                .get()
+        };
+        let restarts = || {
+            TASK_RESTARTS
+                .with_label_values(&["backs_off_and_degrades"])
+                .get()
+        };
+
+        // Failures wait 1s, 2s and 4s before restarting
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(restarts(), 1);
+        assert!(degraded() < 0.5);
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert!(degraded() > 0.5);
+
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 5);
+        assert_eq!(restarts(), 4);
+        assert!(handle.is_finished());
+        assert!(degraded() < 0.5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recovers_once_restarted_task_runs_healthy() {
+        let (shutdown_sender, _) = broadcast::channel(1);
+        let shutdown = Arc::new(Shutdown::new());
+        let attempts = Arc::new(AtomicU32::new(0));
+
+        let handle = {
+            let attempts = attempts.clone();
+
+            spawn_monitored_with_backoff(
+                "recovers_once_restarted_task_runs_healthy",
+                move || {
+                    let attempts = attempts.clone();
+
+                    async move {
+                        if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                            return Err(BridgeError::RpcUnavailable(
+                                "connection refused".to_string(),
+                            ));
+                        }
+
+                        // Runs for good once restarted
+                        std::future::pending().await
+                    }
+                },
+                shutdown_sender,
+                BackoffPolicy {
+                    initial: Duration::from_secs(1),
+                    jitter_percent: 0,
+                    failure_threshold: 1,
+                    healthy_after: Duration::from_secs(60),
+                    ..BackoffPolicy::default()
+                },
+                shutdown,
+            )
+        };
+
+        let degraded = || {
+            TASK_DEGRADED
+                .with_label_values(&["recovers_once_restarted_task_runs_healthy"])
+                .get()
+        };
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert!(degraded() > 0.5);
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(!handle.is_finished());
+        assert!(degraded() < 0.5);
+        handle.abort();
+    }
 }
@@ -19,6 +19,25 @@ impl SecretUrl {
         self.0.as_str()
     }
 
+    #[must_use]
+    pub fn scheme(&self) -> &str {
+        self.0.scheme()
+    }
+
+    /// Masks the path and query on top of the credentials, since providers
+    /// tend to put API keys there.
+    #[must_use]
+    pub fn redacted(&self) -> Self {
+        let mut url = self.format();
+        if url.path() != "/" {
+            url.set_path("**********");
+        }
+        if url.query().is_some() {
+            url.set_query(Some("**********"));
+        }
+        Self(url)
+    }
+
     fn format(&self) -> Url {
         let mut url = self.0.clone();
         if url.has_authority() {
@@ -0,0 +1,339 @@
+//! Two local anvil chains with the bridge contracts deployed, and a relay
+//! that carries the mock Scroll messages from L1 to L2.
+//!
+//! The bridge and World ID contracts come from the artifacts bundled in
+//! `contracts/`. The mock messengers come from the Foundry build at the
+//! root of the repository (`make build`), or from `$FORGE_OUT`.
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use ethers::abi::{Abi, Tokenize};
+use ethers::contract::{abigen, Contract, ContractFactory};
+use ethers::core::k256::ecdsa::SigningKey;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Bytes, H256, U256};
+use ethers::utils::{id, Anvil, AnvilInstance};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+abigen!(
+    MockScrollMessenger,
+    r#"[
+        event SentMessage(address indexed sender, address indexed target, uint256 value, uint256 messageNonce, uint256 gasLimit, bytes message)
+        function relayMessage(address from, address to, uint256 value, uint256 nonce, bytes message) external
+        function nonces(address) external view returns (uint256)
+    ]"#,
+);
+
+pub type Client = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// The root the L1 `WorldID` starts with.
+pub const INITIAL_ROOT: u64 = 0x5eed_5eed;
+
+const TREE_DEPTH: u8 = 30;
+
+/// How many storage slots of the `WorldID` proxy are searched for the
+/// latest root.
+const ROOT_SLOT_SEARCH: u64 = 1024;
+
+/// A contract's ABI and creation code from a Foundry artifact.
+pub struct Artifact {
+    pub abi:      Abi,
+    pub bytecode: Bytes,
+}
+
+impl Artifact {
+    /// Loads the artifact of `name`, preferring the ones bundled with the
+    /// service over the Foundry build.
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        let bundled = manifest_dir().join("contracts").join(format!("{name}.json"));
+        let built = forge_out()
+            .join(format!("{name}.sol"))
+            .join(format!("{name}.json"));
+        let Some(path) = [bundled, built].into_iter().find(|path| path.exists()) else {
+            bail!("No artifact for {name}, build the contracts with `make build` or set FORGE_OUT");
+        };
+
+        let json: serde_json::Value = serde_json::from_reader(File::open(&path)?)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let abi = serde_json::from_value(json["abi"].clone())?;
+        let bytecode = json["bytecode"]["object"]
+            .as_str()
+            .with_context(|| format!("No bytecode in {}", path.display()))?
+            .parse()?;
+
+        Ok(Self { abi, bytecode })
+    }
+
+    pub async fn deploy<T: Tokenize>(
+        &self,
+        client: &Arc<Client>,
+        args: T,
+    ) -> anyhow::Result<Contract<Client>> {
+        let factory = ContractFactory::new(self.abi.clone(), self.bytecode.clone(), client.clone());
+        Ok(factory.deploy(args)?.send().await?)
+    }
+}
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn forge_out() -> PathBuf {
+    std::env::var_os("FORGE_OUT")
+        .map_or_else(|| manifest_dir().join("../../out"), PathBuf::from)
+}
+
+async fn client(anvil: &AnvilInstance, key: usize) -> anyhow::Result<Arc<Client>> {
+    let provider = Provider::<Http>::try_from(anvil.endpoint())?.interval(Duration::from_millis(100));
+    let wallet = LocalWallet::from(anvil.keys()[key].clone()).with_chain_id(anvil.chain_id());
+
+    Ok(Arc::new(SignerMiddleware::new(provider, wallet)))
+}
+
+/// The deployed bridge. The chains and the relay stop when it's dropped.
+pub struct Bridge {
+    pub l1:              AnvilInstance,
+    pub l2:              AnvilInstance,
+    pub world_id:        Address,
+    pub l1_messenger:    Address,
+    pub state_bridge:    Address,
+    pub scroll_world_id: Address,
+    l1_client:           Arc<Client>,
+    l2_client:           Arc<Client>,
+    root_slot:           H256,
+    relay:               JoinHandle<()>,
+}
+
+impl Bridge {
+    /// Spawns the chains, deploys the contracts and starts relaying.
+    ///
+    /// The mock L1 messenger delivers each message to a mock L2 messenger
+    /// on L1 itself, which is what keeps its nonces. The relay replays the
+    /// messages it sent on the mock L2 messenger that `ScrollWorldID`
+    /// trusts on L2.
+    pub async fn deploy() -> anyhow::Result<Self> {
+        let l1 = Anvil::new().chain_id(31337_u64).spawn();
+        let l2 = Anvil::new().chain_id(534_351_u64).spawn();
+        // Different deployers keep L2 addresses free of code on L1
+        let l1_client = client(&l1, 0).await?;
+        let l2_client = client(&l2, 1).await?;
+
+        let scroll_world_id = Artifact::load("ScrollWorldID")?
+            .deploy(&l2_client, TREE_DEPTH)
+            .await?;
+        let messenger: Address = scroll_world_id.method("messenger", ())?.call().await?;
+        let l2_messenger = Artifact::load("MockL2ScrollMessenger")?
+            .deploy(&l2_client, ())
+            .await?;
+        let code = l2_client.get_code(l2_messenger.address(), None).await?;
+        l2_client
+            .provider()
+            .request::<_, ()>("anvil_setCode", (messenger, code))
+            .await?;
+
+        let message_sink = Artifact::load("MockL2ScrollMessenger")?
+            .deploy(&l1_client, ())
+            .await?;
+        let l1_messenger = Artifact::load("MockL1ScrollMessenger")?
+            .deploy(&l1_client, message_sink.address())
+            .await?;
+
+        let implementation = Artifact::load("WorldIDIdentityManagerImplV1")?;
+        let initialize = implementation.abi.function("initialize")?.encode_input(
+            &(
+                TREE_DEPTH,
+                U256::from(INITIAL_ROOT),
+                message_sink.address(),
+                message_sink.address(),
+                message_sink.address(),
+            )
+                .into_tokens(),
+        )?;
+        let implementation = implementation.deploy(&l1_client, ()).await?;
+        let world_id = Artifact::load("WorldIDIdentityManager")?
+            .deploy(&l1_client, (implementation.address(), Bytes::from(initialize)))
+            .await?;
+        let root_slot = find_slot(&l1_client, world_id.address(), U256::from(INITIAL_ROOT)).await?;
+
+        let state_bridge = Artifact::load("ScrollStateBridge")?
+            .deploy(&l1_client, (
+                world_id.address(),
+                scroll_world_id.address(),
+                l1_messenger.address(),
+            ))
+            .await?;
+
+        // Only roots sent by the state bridge are accepted on L2
+        scroll_world_id
+            .method_hash::<_, ()>(id("transferOwnership(address,bool)"), (
+                state_bridge.address(),
+                false,
+            ))?
+            .send()
+            .await?
+            .await?;
+
+        let relay = tokio::spawn(relay(
+            l1_client.clone(),
+            l1_messenger.address(),
+            l2_client.clone(),
+            messenger,
+        ));
+
+        info!(
+            world_id = ?world_id.address(),
+            state_bridge = ?state_bridge.address(),
+            scroll_world_id = ?scroll_world_id.address(),
+            "Deployed the bridge"
+        );
+
+        Ok(Self {
+            world_id: world_id.address(),
+            l1_messenger: l1_messenger.address(),
+            state_bridge: state_bridge.address(),
+            scroll_world_id: scroll_world_id.address(),
+            l1,
+            l2,
+            l1_client,
+            l2_client,
+            root_slot,
+            relay,
+        })
+    }
+
+    /// A funded L1 key that the contracts weren't deployed from.
+    pub fn relayer_key(&self) -> SigningKey {
+        self.l1.keys()[2].clone().into()
+    }
+
+    /// Makes `root` the latest root of the L1 `WorldID`, as if identities
+    /// were inserted.
+    pub async fn insert_root(&self, root: U256) -> anyhow::Result<()> {
+        let mut value = [0; 32];
+        root.to_big_endian(&mut value);
+        self.l1_client
+            .provider()
+            .request::<_, bool>("anvil_setStorageAt", (self.world_id, self.root_slot, H256(value)))
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn l1_root(&self) -> anyhow::Result<U256> {
+        latest_root(&self.l1_client, self.world_id).await
+    }
+
+    pub async fn l2_root(&self) -> anyhow::Result<U256> {
+        latest_root(&self.l2_client, self.scroll_world_id).await
+    }
+
+    /// Waits up to `timeout` for `root` to arrive on L2.
+    pub async fn wait_for_l2_root(&self, root: U256, timeout: Duration) -> anyhow::Result<()> {
+        tokio::time::timeout(timeout, async {
+            loop {
+                if self.l2_root().await? == root {
+                    return Ok(());
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        })
+        .await
+        .with_context(|| format!("Root {root} didn't reach L2"))?
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.relay.abort();
+    }
+}
+
+async fn latest_root(client: &Arc<Client>, address: Address) -> anyhow::Result<U256> {
+    let contract = Contract::new(
+        address,
+        serde_json::from_str::<Abi>(
+            r#"[{"type":"function","name":"latestRoot","inputs":[],"outputs":[{"name":"","type":"uint256"}],"stateMutability":"view"}]"#,
+        )?,
+        client.clone(),
+    );
+
+    Ok(contract.method("latestRoot", ())?.call().await?)
+}
+
+/// The storage slot of `address` holding `value`.
+async fn find_slot(client: &Arc<Client>, address: Address, value: U256) -> anyhow::Result<H256> {
+    for slot in 0..ROOT_SLOT_SEARCH {
+        let slot = H256::from_low_u64_be(slot);
+        let stored = client.get_storage_at(address, slot, None).await?;
+        if U256::from_big_endian(stored.as_bytes()) == value {
+            return Ok(slot);
+        }
+    }
+
+    bail!("{value} isn't stored in the first {ROOT_SLOT_SEARCH} slots of {address:?}")
+}
+
+/// Replays the messages sent through the L1 messenger on the L2 messenger.
+async fn relay(
+    l1_client: Arc<Client>,
+    l1_messenger: Address,
+    l2_client: Arc<Client>,
+    l2_messenger: Address,
+) {
+    let l1_messenger = MockScrollMessenger::new(l1_messenger, l1_client);
+    let l2_messenger = MockScrollMessenger::new(l2_messenger, l2_client);
+    let mut from_block = 0_u64;
+
+    loop {
+        match relay_new_messages(&l1_messenger, &l2_messenger, from_block).await {
+            Ok(next) => from_block = next,
+            Err(err) => warn!(?err, "Failed to relay messages"),
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// Relays the messages sent from `from_block` on, returning the block to
+/// continue from.
+async fn relay_new_messages(
+    l1_messenger: &MockScrollMessenger<Client>,
+    l2_messenger: &MockScrollMessenger<Client>,
+    from_block: u64,
+) -> anyhow::Result<u64> {
+    let latest = l1_messenger.client().get_block_number().await?.as_u64();
+    if latest < from_block {
+        return Ok(from_block);
+    }
+
+    let messages = l1_messenger
+        .sent_message_filter()
+        .from_block(from_block)
+        .to_block(latest)
+        .query()
+        .await?;
+    for message in messages {
+        let nonce = l2_messenger.nonces(message.sender).call().await?;
+        info!(sender = ?message.sender, target = ?message.target, %nonce, "Relaying message to L2");
+        l2_messenger
+            .relay_message(
+                message.sender,
+                message.target,
+                message.value,
+                nonce,
+                message.message,
+            )
+            .send()
+            .await?
+            .await?;
+    }
+
+    Ok(latest + 1)
+}
@@ -0,0 +1,91 @@
+//! Runs the service against the bridge deployed on two anvil chains, with
+//! `micro-oz` as the relayer.
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::U256;
+use scroll_service::app::App;
+use scroll_service::config::Config;
+use scroll_service::task_monitor::TaskMonitor;
+use scroll_service::utils::shutdown::Shutdown;
+
+use crate::common::{Bridge, INITIAL_ROOT};
+
+fn config(bridge: &Bridge, relayer_url: &str, relayer: ethers::types::Address) -> Config {
+    toml::from_str(&format!(
+        r#"
+        [app]
+
+        [network]
+        scroll_bridge_address = "{state_bridge:?}"
+        l1_chain_id = {l1_chain_id}
+        l2_chain_id = {l2_chain_id}
+
+        [providers]
+        l1_network_provider = "{l1}"
+        l2_network_provider = "{l2}"
+
+        [relayer]
+        kind = "oz_defender"
+        oz_api_url = "{relayer_url}"
+        oz_api_key = ""
+        oz_api_secret = ""
+        oz_address = "{relayer:?}"
+
+        [database]
+        database = "sqlite::memory:"
+
+        [server]
+        address = "127.0.0.1:0"
+
+        [tasks]
+        check_sync_interval = "1s"
+        finalize_txs_interval = "1s"
+        oz_poll_interval = "500ms"
+        outbox_poll_interval = "1s"
+
+        [leader_election]
+        enabled = false
+        "#,
+        state_bridge = bridge.state_bridge,
+        l1_chain_id = bridge.l1.chain_id(),
+        l2_chain_id = bridge.l2.chain_id(),
+        l1 = bridge.l1.endpoint(),
+        l2 = bridge.l2.endpoint(),
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires anvil and the Foundry build of the mock contracts"]
+async fn propagates_roots_end_to_end() -> anyhow::Result<()> {
+    let bridge = Bridge::deploy().await?;
+    assert_eq!(bridge.l1_root().await?, U256::from(INITIAL_ROOT));
+    assert_eq!(bridge.l2_root().await?, U256::zero());
+
+    let relayer_key = bridge.relayer_key();
+    let relayer = LocalWallet::from(relayer_key.clone()).address();
+    let micro_oz = micro_oz::spawn(bridge.l1.endpoint(), relayer_key).await?;
+
+    let app = App::new(config(&bridge, &micro_oz.endpoint(), relayer)).await?;
+    assert!(app.read_only.is_none(), "{:?}", app.read_only);
+    let monitor = TaskMonitor::new(app, Arc::new(Shutdown::new()));
+    monitor.start().await;
+
+    bridge
+        .wait_for_l2_root(U256::from(INITIAL_ROOT), Duration::from_secs(60))
+        .await?;
+
+    let root = U256::from(0x00c0_ffee);
+    bridge.insert_root(root).await?;
+    assert_eq!(bridge.l1_root().await?, root);
+    bridge.wait_for_l2_root(root, Duration::from_secs(60)).await?;
+
+    monitor.shutdown().await?;
+    micro_oz.shutdown().await;
+
+    Ok(())
+}